@@ -0,0 +1,233 @@
+//! Reads and writes the binary glTF (`.glb`) container used by VRM and VRMA files.
+//!
+//! - [`glTF binary format`](https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html#binary-gltf-layout)
+
+use crate::error::AppResult;
+use anyhow::{bail, Context};
+
+const MAGIC: &[u8; 4] = b"glTF";
+const VERSION: u32 = 2;
+const HEADER_LEN: usize = 12;
+const CHUNK_HEADER_LEN: usize = 8;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+/// The decoded contents of a `.glb` file.
+pub(crate) struct Glb {
+    /// The glTF JSON document.
+    pub json: serde_json::Value,
+    /// The binary buffer referenced by `buffers[0]` without a `uri`.
+    pub bin: Option<Vec<u8>>,
+}
+
+impl Glb {
+    /// Returns `true` if the bytes start with the `.glb` magic.
+    #[inline]
+    pub fn is_glb(bytes: &[u8]) -> bool {
+        bytes.starts_with(MAGIC)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> AppResult<Self> {
+        if !Self::is_glb(bytes) || bytes.len() < HEADER_LEN {
+            bail!("Not a glb file");
+        }
+        let length = (read_u32(bytes, 8)? as usize).min(bytes.len());
+        let mut offset = HEADER_LEN;
+        let mut json = None;
+        let mut bin = None;
+        while offset + CHUNK_HEADER_LEN <= length {
+            let chunk_len = read_u32(bytes, offset)? as usize;
+            let chunk_type = read_u32(bytes, offset + 4)?;
+            let start = offset + CHUNK_HEADER_LEN;
+            let data = bytes
+                .get(start..start + chunk_len)
+                .context("glb chunk exceeds the file length")?;
+            match chunk_type {
+                CHUNK_JSON => json = Some(serde_json::from_slice(data)?),
+                CHUNK_BIN => bin = Some(data.to_vec()),
+                _ => {}
+            }
+            offset = start + chunk_len;
+        }
+        Ok(Self {
+            json: json.context("Not found glb json chunk")?,
+            bin,
+        })
+    }
+
+    pub fn to_bytes(&self) -> AppResult<Vec<u8>> {
+        let mut json = serde_json::to_vec(&self.json)?;
+        pad(&mut json, b' ');
+        let mut bin = self.bin.clone();
+        if let Some(bin) = bin.as_mut() {
+            pad(bin, 0);
+        }
+        let length = HEADER_LEN
+            + CHUNK_HEADER_LEN
+            + json.len()
+            + bin
+                .as_ref()
+                .map(|b| CHUNK_HEADER_LEN + b.len())
+                .unwrap_or(0);
+
+        let mut bytes = Vec::with_capacity(length);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(length as u32).to_le_bytes());
+        write_chunk(&mut bytes, CHUNK_JSON, &json);
+        if let Some(bin) = bin.as_ref() {
+            write_chunk(&mut bytes, CHUNK_BIN, bin);
+        }
+        Ok(bytes)
+    }
+}
+
+/// Writes accessors into a single binary buffer while building a glTF document.
+#[derive(Default)]
+pub(crate) struct BufferWriter {
+    pub bin: Vec<u8>,
+    pub buffer_views: Vec<serde_json::Value>,
+    pub accessors: Vec<serde_json::Value>,
+}
+
+impl BufferWriter {
+    /// Pushes `f32` elements of the accessor type(`SCALAR`, `VEC3`, `VEC4`, ...)
+    /// and returns the index of the accessor.
+    ///
+    /// The `min` and `max` are written if `with_bounds` is `true`, which is required for animation inputs.
+    pub fn push_f32(
+        &mut self,
+        values: &[f32],
+        accessor_type: &str,
+        with_bounds: bool,
+    ) -> usize {
+        let components = match accessor_type {
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" => 4,
+            "MAT4" => 16,
+            _ => 1,
+        };
+        let view = self.push_view(values.iter().flat_map(|v| v.to_le_bytes()));
+        let mut accessor = serde_json::json!({
+            "bufferView": view,
+            "componentType": 5126,
+            "count": values.len() / components,
+            "type": accessor_type,
+        });
+        if with_bounds {
+            let mut min = vec![f32::MAX; components];
+            let mut max = vec![f32::MIN; components];
+            for element in values.chunks_exact(components) {
+                for (i, v) in element.iter().enumerate() {
+                    min[i] = min[i].min(*v);
+                    max[i] = max[i].max(*v);
+                }
+            }
+            accessor["min"] = serde_json::json!(min);
+            accessor["max"] = serde_json::json!(max);
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn push_view(
+        &mut self,
+        bytes: impl Iterator<Item = u8>,
+    ) -> usize {
+        pad(&mut self.bin, 0);
+        let offset = self.bin.len();
+        self.bin.extend(bytes);
+        self.buffer_views.push(serde_json::json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": self.bin.len() - offset,
+        }));
+        self.buffer_views.len() - 1
+    }
+
+    /// Writes `buffers`, `bufferViews` and `accessors` into the document and returns the [`Glb`].
+    pub fn into_glb(
+        self,
+        mut json: serde_json::Value,
+    ) -> Glb {
+        json["buffers"] = serde_json::json!([{ "byteLength": self.bin.len() }]);
+        json["bufferViews"] = serde_json::Value::Array(self.buffer_views);
+        json["accessors"] = serde_json::Value::Array(self.accessors);
+        Glb {
+            json,
+            bin: Some(self.bin),
+        }
+    }
+}
+
+fn read_u32(
+    bytes: &[u8],
+    offset: usize,
+) -> AppResult<u32> {
+    let b = bytes
+        .get(offset..offset + 4)
+        .context("Unexpected end of glb")?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn write_chunk(
+    bytes: &mut Vec<u8>,
+    chunk_type: u32,
+    data: &[u8],
+) {
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&chunk_type.to_le_bytes());
+    bytes.extend_from_slice(data);
+}
+
+fn pad(
+    data: &mut Vec<u8>,
+    fill: u8,
+) {
+    while !data.len().is_multiple_of(4) {
+        data.push(fill);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::glb::{BufferWriter, Glb};
+    use crate::success;
+    use crate::tests::TestResult;
+
+    #[test]
+    fn round_trip() -> TestResult {
+        let glb = Glb {
+            json: serde_json::json!({"asset": {"version": "2.0"}}),
+            bin: Some(vec![1, 2, 3]),
+        };
+        let bytes = glb.to_bytes()?;
+        assert_eq!(bytes.len() % 4, 0);
+        let decoded = Glb::from_bytes(&bytes)?;
+        assert_eq!(decoded.json, glb.json);
+        assert_eq!(decoded.bin, Some(vec![1, 2, 3, 0]));
+        success!()
+    }
+
+    #[test]
+    fn write_accessors() {
+        let mut writer = BufferWriter::default();
+        writer.push_f32(&[0.5], "SCALAR", false);
+        let accessor = writer.push_f32(&[0., 1., 2., 3., 4., 5.], "VEC3", true);
+        let glb = writer.into_glb(serde_json::json!({}));
+
+        assert_eq!(accessor, 1);
+        assert_eq!(glb.json["accessors"][1]["count"], 2);
+        assert_eq!(
+            glb.json["accessors"][1]["min"],
+            serde_json::json!([0., 1., 2.])
+        );
+        assert_eq!(
+            glb.json["accessors"][1]["max"],
+            serde_json::json!([3., 4., 5.])
+        );
+        assert_eq!(glb.json["bufferViews"][1]["byteOffset"], 4);
+        assert_eq!(glb.json["buffers"][0]["byteLength"], 28);
+    }
+}
@@ -1,7 +1,9 @@
 #![allow(clippy::type_complexity, clippy::too_many_arguments)]
 
 mod error;
+mod glb;
 mod macros;
+mod random;
 pub mod system_param;
 mod system_set;
 pub mod vrm;
@@ -0,0 +1,28 @@
+use bevy::prelude::{Entity, Reflect};
+
+/// A small xorshift generator for procedural motion that does not need a cryptographic quality.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Reflect)]
+pub(crate) struct XorShift32(u32);
+
+impl XorShift32 {
+    /// Creates a generator seeded from the entity so that avatars do not move in sync.
+    pub fn from_entity(entity: Entity) -> Self {
+        Self((entity.to_bits() as u32).wrapping_mul(0x9E37_79B9) | 1)
+    }
+
+    /// Returns `true` until the generator is seeded.
+    #[inline]
+    pub const fn is_unseeded(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns a random value in `0.0..1.0`.
+    pub fn next_f32(&mut self) -> f32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        (x >> 8) as f32 / (1 << 24) as f32
+    }
+}
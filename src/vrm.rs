@@ -1,35 +1,66 @@
+mod auto_blink;
+mod builder;
+mod exporter;
 pub(crate) mod expressions;
+mod first_person;
 pub(crate) mod gltf;
 pub(crate) mod humanoid_bone;
+mod lip_sync;
 mod loader;
 mod look_at;
+pub(crate) mod meta;
 mod mtoon;
 mod spawn;
 mod spring_bone;
+mod validation;
 
 use crate::new_type;
+use crate::vrm::auto_blink::AutoBlinkPlugin;
+use crate::vrm::first_person::FirstPersonPlugin;
 use crate::vrm::humanoid_bone::VrmHumanoidBonePlugin;
+use crate::vrm::lip_sync::LipSyncPlugin;
 use crate::vrm::loader::{VrmAsset, VrmLoaderPlugin};
 use crate::vrm::look_at::LookAtPlugin;
+use crate::vrm::meta::VrmMetaPlugin;
 use crate::vrm::spawn::VrmSpawnPlugin;
 use crate::vrm::spring_bone::VrmSpringBonePlugin;
+use crate::vrm::validation::VrmValidationReport;
 use bevy::app::{App, Plugin};
 use bevy::asset::AssetApp;
 use bevy::prelude::*;
+pub use expressions::VrmExpression;
 use expressions::VrmExpressionPlugin;
 use mtoon::MtoonMaterialPlugin;
 use std::path::PathBuf;
 
 pub mod prelude {
     pub use crate::vrm::{
+        auto_blink::AutoBlink,
+        builder::VrmBuilder,
+        exporter::VrmExporter,
+        expressions::{VrmExpressionPreset, VrmExpressionWeights},
+        first_person::prelude::*,
         gltf::prelude::*,
         humanoid_bone::prelude::*,
-        loader::{VrmAsset, VrmHandle},
-        look_at::LookAt,
+        lip_sync::{
+            analyze_wav, LipSyncInput, Viseme, VisemeClock, VisemeKey, VisemeTrack,
+            VisemeTrackPlayer, VisemeWeights,
+        },
+        loader::{VrmAsset, VrmHandle, VrmLoaderSettings, VRM_THUMBNAIL_LABEL},
+        look_at::{
+            LookAt, LookAtBody, LookAtBoneShare, LookAtCandidate, LookAtCandidates, LookAtFade,
+            LookAtSettings, LookAtWeight, Saccade,
+        },
+        meta::prelude::*,
         mtoon::prelude::*,
+        spring_bone::{ColliderShapes, SpringBoneSimulationSettings, SpringBoneTimestep},
+        validation::prelude::*,
         BoneRestGlobalTransform, BoneRestTransform, Vrm, VrmBone, VrmExpression, VrmPath,
         VrmPlugin,
     };
+
+    #[cfg(feature = "audio")]
+    pub use crate::vrm::lip_sync::LipSyncAudio;
 }
 
 new_type!(
@@ -38,12 +69,6 @@ new_type!(
     ty: String,
 );
 
-new_type!(
-    /// The key name of `VRMC_vrm::expressions::preset`.
-    name: VrmExpression,
-    ty: String,
-);
-
 /// A marker component attached to the entity of VRM.
 /// This component is automatically inserted after the [`VrmHandle`](crate::prelude::VrmHandle) is loaded.
 #[derive(Debug, Component, Reflect, Copy, Clone)]
@@ -99,12 +124,127 @@ impl Plugin for VrmPlugin {
             VrmExpressionPlugin,
             MtoonMaterialPlugin,
             LookAtPlugin,
+            VrmMetaPlugin,
+            FirstPersonPlugin,
+            AutoBlinkPlugin,
+            LipSyncPlugin,
         ));
 
         app.register_type::<Vrm>()
             .register_type::<VrmPath>()
             .register_type::<BoneRestTransform>()
             .register_type::<BoneRestGlobalTransform>()
-            .register_type::<VrmBone>();
+            .register_type::<VrmBone>()
+            .register_type::<VrmValidationReport>();
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::prelude::{MToonMaterial, VrmHandle};
+    use crate::vrm::auto_blink::AutoBlinkPlugin;
+    use crate::vrm::expressions::VrmExpressionPlugin;
+    use crate::vrm::first_person::FirstPersonPlugin;
+    use crate::vrm::humanoid_bone::{HumanoidBonesAttached, VrmHumanoidBonePlugin};
+    use crate::vrm::lip_sync::LipSyncPlugin;
+    use crate::vrm::loader::{VrmAsset, VrmLoaderPlugin};
+    use crate::vrm::look_at::LookAtPlugin;
+    use crate::vrm::meta::VrmMetaPlugin;
+    use crate::vrm::spawn::VrmSpawnPlugin;
+    use crate::vrm::spring_bone::VrmSpringBonePlugin;
+    use bevy::asset::io::memory::{Dir, MemoryAssetReader};
+    use bevy::asset::io::{AssetSource, AssetSourceId};
+    use bevy::gltf::GltfPlugin;
+    use bevy::prelude::*;
+    use bevy::render::camera::CameraPlugin;
+    use bevy::render::mesh::morph::{MeshMorphWeights, MorphWeights};
+    use bevy::render::mesh::skinning::SkinnedMesh;
+    use bevy::render::mesh::MeshPlugin;
+    use bevy::render::primitives::Aabb;
+    use bevy::render::view::VisibilityClass;
+    use bevy::scene::ScenePlugin;
+    use bevy::window::WindowPlugin;
+    use std::time::Duration;
+
+    /// Creates an app that spawns VRMs loaded from the `memory://` asset source backed by `dir`.
+    ///
+    /// The materials of `VRMC_materials_mtoon` are not set up because the app has no renderer.
+    pub fn vrm_app(dir: &Dir) -> App {
+        let mut app = App::new();
+        let root = dir.clone();
+        app.register_asset_source(
+            AssetSourceId::from("memory"),
+            AssetSource::build()
+                .with_reader(move || Box::new(MemoryAssetReader { root: root.clone() })),
+        );
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            ImagePlugin::default(),
+            WindowPlugin::default(),
+            CameraPlugin,
+            TransformPlugin,
+            ScenePlugin,
+            MeshPlugin,
+            GltfPlugin::default(),
+        ))
+        .init_asset::<StandardMaterial>()
+        .init_asset::<MToonMaterial>()
+        .register_type::<Visibility>()
+        .register_type::<InheritedVisibility>()
+        .register_type::<ViewVisibility>()
+        .register_type::<VisibilityClass>()
+        .register_type::<Mesh3d>()
+        .register_type::<MeshMorphWeights>()
+        .register_type::<MorphWeights>()
+        .register_type::<SkinnedMesh>()
+        .register_type::<Aabb>()
+        .register_type::<MeshMaterial3d<StandardMaterial>>()
+        .init_asset::<VrmAsset>()
+        .add_plugins((
+            VrmLoaderPlugin,
+            VrmSpawnPlugin,
+            VrmSpringBonePlugin,
+            VrmHumanoidBonePlugin,
+            VrmExpressionPlugin,
+            LookAtPlugin,
+            VrmMetaPlugin,
+            FirstPersonPlugin,
+            AutoBlinkPlugin,
+            LipSyncPlugin,
+        ));
+        app.finish();
+        app
+    }
+
+    /// Spawns the VRM at `path` of the `memory://` source and updates the app until its humanoid bones are attached.
+    pub fn spawn_vrm(
+        app: &mut App,
+        path: &str,
+    ) -> Entity {
+        let handle = app
+            .world()
+            .resource::<AssetServer>()
+            .load(format!("memory://{path}"));
+        let vrm = app.world_mut().spawn(VrmHandle(handle)).id();
+        update_until(app, |world| {
+            world.entity(vrm).contains::<HumanoidBonesAttached>()
+        });
+        vrm
+    }
+
+    /// Updates the app until the condition holds, waiting for the assets loaded in the background.
+    pub fn update_until(
+        app: &mut App,
+        condition: impl Fn(&World) -> bool,
+    ) {
+        for _ in 0..1000 {
+            if condition(app.world()) {
+                return;
+            }
+            app.update();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("The condition was not met in time");
     }
 }
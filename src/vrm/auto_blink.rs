@@ -0,0 +1,297 @@
+//! Blinks the VRM automatically through the `blink` expression.
+
+use crate::random::XorShift32;
+use crate::vrm::expressions::{
+    mix_expressions, ExpressionOverride, MixedExpressionWeights, ProceduralExpressionWeights,
+    VrmExpressionPreset, VrmExpressionRegistry,
+};
+use crate::vrm::VrmExpression;
+use bevy::app::{App, Plugin, Update};
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// Blinks the VRM automatically.
+///
+/// Insert this component into the root entity of the VRM.
+/// The `blink` expression is closed and opened at random intervals, and blinking pauses
+/// while an expression whose `overrideBlink` is `block` is active.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+///
+/// fn spawn_vrm(
+///     mut commands: Commands,
+///     asset_server: Res<AssetServer>,
+/// ) {
+///     commands.spawn((
+///         VrmHandle(asset_server.load("model.vrm")),
+///         AutoBlink::default(),
+///     ));
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component, Default)]
+pub struct AutoBlink {
+    /// The minimum interval between blinks.
+    pub min_interval: Duration,
+    /// The maximum interval between blinks.
+    pub max_interval: Duration,
+    /// The time taken to close the eyes.
+    pub close_duration: Duration,
+    /// The time taken to open the eyes.
+    pub open_duration: Duration,
+    /// The probability, between `0.0` and `1.0`, that a blink is immediately followed by another one.
+    pub double_blink_probability: f32,
+    phase: BlinkPhase,
+    rng: XorShift32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+enum BlinkPhase {
+    Waiting {
+        elapsed: Duration,
+        interval: Duration,
+    },
+    Closing {
+        elapsed: Duration,
+        double: bool,
+    },
+    Opening {
+        elapsed: Duration,
+        double: bool,
+    },
+}
+
+impl Default for AutoBlink {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_secs(2),
+            max_interval: Duration::from_secs(6),
+            close_duration: Duration::from_millis(60),
+            open_duration: Duration::from_millis(120),
+            double_blink_probability: 0.1,
+            phase: BlinkPhase::Waiting {
+                elapsed: Duration::ZERO,
+                interval: Duration::ZERO,
+            },
+            rng: XorShift32::default(),
+        }
+    }
+}
+
+impl AutoBlink {
+    /// Returns `true` while the eyes are closing or opening.
+    pub const fn is_blinking(&self) -> bool {
+        !matches!(self.phase, BlinkPhase::Waiting { .. })
+    }
+
+    fn wait(&mut self) {
+        let min = self.min_interval.as_secs_f32();
+        let max = self.max_interval.as_secs_f32().max(min);
+        let interval = Duration::from_secs_f32(min + (max - min) * self.rng.next_f32());
+        self.phase = BlinkPhase::Waiting {
+            elapsed: Duration::ZERO,
+            interval,
+        };
+    }
+
+    /// Advances the blink and returns the weight of the `blink` expression.
+    fn advance(
+        &mut self,
+        delta: Duration,
+    ) -> f32 {
+        match self.phase {
+            BlinkPhase::Waiting { elapsed, interval } => {
+                let elapsed = elapsed + delta;
+                if elapsed < interval {
+                    self.phase = BlinkPhase::Waiting { elapsed, interval };
+                } else {
+                    let double = self.rng.next_f32() < self.double_blink_probability;
+                    self.phase = BlinkPhase::Closing {
+                        elapsed: Duration::ZERO,
+                        double,
+                    };
+                }
+                0.0
+            }
+            BlinkPhase::Closing { elapsed, double } => {
+                let elapsed = elapsed + delta;
+                if elapsed < self.close_duration {
+                    self.phase = BlinkPhase::Closing { elapsed, double };
+                    elapsed.div_duration_f32(self.close_duration)
+                } else {
+                    self.phase = BlinkPhase::Opening {
+                        elapsed: Duration::ZERO,
+                        double,
+                    };
+                    1.0
+                }
+            }
+            BlinkPhase::Opening { elapsed, double } => {
+                let elapsed = elapsed + delta;
+                if elapsed < self.open_duration {
+                    self.phase = BlinkPhase::Opening { elapsed, double };
+                    1.0 - elapsed.div_duration_f32(self.open_duration)
+                } else {
+                    if double {
+                        self.phase = BlinkPhase::Closing {
+                            elapsed: Duration::ZERO,
+                            double: false,
+                        };
+                    } else {
+                        self.wait();
+                    }
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+pub(super) struct AutoBlinkPlugin;
+
+impl Plugin for AutoBlinkPlugin {
+    fn build(
+        &self,
+        app: &mut App,
+    ) {
+        app.register_type::<AutoBlink>()
+            .add_systems(Update, auto_blink.before(mix_expressions))
+            .add_observer(open_eyes);
+    }
+}
+
+fn auto_blink(
+    time: Res<Time>,
+    mut vrms: Query<(
+        Entity,
+        &mut AutoBlink,
+        &VrmExpressionRegistry,
+        &MixedExpressionWeights,
+        &mut ProceduralExpressionWeights,
+    )>,
+) {
+    let blink = VrmExpression::from(VrmExpressionPreset::Blink);
+    for (entity, mut auto_blink, registry, mixed, mut procedural) in vrms.iter_mut() {
+        if auto_blink.rng.is_unseeded() {
+            auto_blink.rng = XorShift32::from_entity(entity);
+            auto_blink.wait();
+        }
+        let blocked = registry.iter().any(|(expression, registered)| {
+            registered.override_blink == ExpressionOverride::Block && 0.0 < mixed.get(expression)
+        });
+        let weight = if blocked {
+            if auto_blink.is_blinking() {
+                auto_blink.wait();
+            }
+            0.0
+        } else {
+            auto_blink.advance(time.delta())
+        };
+        if procedural.get(&blink).copied().unwrap_or_default() != weight {
+            procedural.insert(blink.clone(), weight);
+        }
+    }
+}
+
+fn open_eyes(
+    trigger: Trigger<OnRemove, AutoBlink>,
+    mut vrms: Query<&mut ProceduralExpressionWeights>,
+) {
+    if let Ok(mut procedural) = vrms.get_mut(trigger.target()) {
+        procedural.remove(&VrmExpression::from(VrmExpressionPreset::Blink));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::tests::test_app;
+    use crate::vrm::auto_blink::AutoBlinkPlugin;
+    use crate::vrm::expressions::{
+        ExpressionOverride, MixedExpressionWeights, ProceduralExpressionWeights,
+        RegisteredExpression, VrmExpressionRegistry,
+    };
+    use bevy::platform::collections::HashMap;
+    use bevy::prelude::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn spawn_vrm(
+        app: &mut App,
+        happy: f32,
+    ) -> Entity {
+        let registry = VrmExpressionRegistry(HashMap::from_iter([
+            (
+                VrmExpression::from("blink"),
+                RegisteredExpression::default(),
+            ),
+            (
+                VrmExpression::from("happy"),
+                RegisteredExpression {
+                    override_blink: ExpressionOverride::Block,
+                    ..Default::default()
+                },
+            ),
+        ]));
+        let mut mixed = MixedExpressionWeights::default();
+        mixed.weights.insert(VrmExpression::from("happy"), happy);
+        app.world_mut()
+            .spawn((
+                AutoBlink {
+                    min_interval: Duration::from_secs(1),
+                    max_interval: Duration::from_secs(1),
+                    close_duration: Duration::from_millis(200),
+                    open_duration: Duration::from_millis(200),
+                    double_blink_probability: 0.0,
+                    ..default()
+                },
+                registry,
+                mixed,
+                ProceduralExpressionWeights::default(),
+            ))
+            .id()
+    }
+
+    fn blink_weights(
+        app: &mut App,
+        vrm: Entity,
+    ) -> Vec<f32> {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+        (0..20)
+            .map(|_| {
+                app.update();
+                app.world()
+                    .get::<ProceduralExpressionWeights>(vrm)
+                    .unwrap()
+                    .get(&VrmExpression::from("blink"))
+                    .copied()
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn blink_after_interval() {
+        let mut app = test_app();
+        app.add_plugins(AutoBlinkPlugin);
+        let vrm = spawn_vrm(&mut app, 0.0);
+        let weights = blink_weights(&mut app, vrm);
+        let first = weights.iter().position(|w| 0.0 < *w).unwrap();
+        assert!((9..=12).contains(&first), "{weights:?}");
+        assert!(weights.contains(&1.0));
+        assert_eq!(weights.last(), Some(&0.0));
+    }
+
+    #[test]
+    fn pause_while_blink_is_blocked() {
+        let mut app = test_app();
+        app.add_plugins(AutoBlinkPlugin);
+        let vrm = spawn_vrm(&mut app, 1.0);
+        let weights = blink_weights(&mut app, vrm);
+        assert!(weights.iter().all(|w| *w == 0.0));
+    }
+}
@@ -0,0 +1,544 @@
+//! Builds a minimal VRM in memory.
+
+use crate::error::AppResult;
+use crate::glb::BufferWriter;
+use crate::prelude::*;
+use crate::vrm::exporter::{write_mtoon, write_spring_joint};
+use crate::vrm::spring_bone::SpringJointProps;
+use anyhow::Context;
+use bevy::prelude::*;
+use serde_json::{json, Map, Value};
+
+/// The T-pose skeleton used by [`VrmBuilder::humanoid`].
+///
+/// The model faces `+Z`, so the left side of the model is `+X`.
+const HUMANOID_SKELETON: [(&str, Option<&str>, [f32; 3]); 17] = [
+    ("hips", None, [0., 0.9, 0.]),
+    ("spine", Some("hips"), [0., 0.1, 0.]),
+    ("chest", Some("spine"), [0., 0.15, 0.]),
+    ("neck", Some("chest"), [0., 0.2, 0.]),
+    ("head", Some("neck"), [0., 0.1, 0.]),
+    ("leftUpperArm", Some("chest"), [0.2, 0.15, 0.]),
+    ("leftLowerArm", Some("leftUpperArm"), [0.25, 0., 0.]),
+    ("leftHand", Some("leftLowerArm"), [0.25, 0., 0.]),
+    ("rightUpperArm", Some("chest"), [-0.2, 0.15, 0.]),
+    ("rightLowerArm", Some("rightUpperArm"), [-0.25, 0., 0.]),
+    ("rightHand", Some("rightLowerArm"), [-0.25, 0., 0.]),
+    ("leftUpperLeg", Some("hips"), [0.1, -0.05, 0.]),
+    ("leftLowerLeg", Some("leftUpperLeg"), [0., -0.4, 0.]),
+    ("leftFoot", Some("leftLowerLeg"), [0., -0.4, 0.]),
+    ("rightUpperLeg", Some("hips"), [-0.1, -0.05, 0.]),
+    ("rightLowerLeg", Some("rightUpperLeg"), [0., -0.4, 0.]),
+    ("rightFoot", Some("rightLowerLeg"), [0., -0.4, 0.]),
+];
+
+/// The name of the mesh node that holds the morph targets of the expressions.
+const FACE_NODE: &str = "Face";
+
+/// Builds a minimal but valid VRM 1.0 file in memory.
+///
+/// This is useful for tests and procedural avatars that don't want to depend on binary assets.
+/// The built bytes are loaded in the same way as a `.vrm` file, so the real spawn path is exercised.
+///
+/// - Each humanoid bone becomes a node with the same name as the bone.
+/// - Each expression gets one morph target of the `Face` mesh, attached to the head if it exists.
+/// - Each material gets a mesh node with the same name as the material.
+///
+/// The meshes are single triangles that are not skinned, so they don't follow the bones.
+/// Tests of the skinning need a real VRM file.
+///
+/// To load the bytes without writing a file, register an in-memory [`AssetSource`](bevy::asset::io::AssetSource)
+/// before adding `DefaultPlugins`, and load the VRM from it like any other path.
+///
+/// ```no_run
+/// use bevy::asset::io::memory::{Dir, MemoryAssetReader};
+/// use bevy::asset::io::{AssetSource, AssetSourceId};
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+/// use std::path::Path;
+///
+/// fn register_memory_source(app: &mut App) {
+///     let bytes = VrmBuilder::new("Test")
+///         .humanoid()
+///         .expression("happy")
+///         .node("Hair1", Some("head"), Vec3::new(0., 0.1, -0.1))
+///         .node("Hair2", Some("Hair1"), Vec3::new(0., -0.1, 0.))
+///         .collider(
+///             "head",
+///             ColliderShape::Sphere(bevy_vrm1::prelude::Sphere {
+///                 offset: [0.; 3],
+///                 radius: 0.1,
+///             }),
+///         )
+///         .spring(["Hair1", "Hair2"])
+///         .mtoon_material("Body", MToonMaterial::default())
+///         .build()
+///         .unwrap();
+///     let dir = Dir::default();
+///     dir.insert_asset(Path::new("test.vrm"), bytes);
+///     app.register_asset_source(
+///         AssetSourceId::from("memory"),
+///         AssetSource::build()
+///             .with_reader(move || Box::new(MemoryAssetReader { root: dir.clone() })),
+///     );
+/// }
+///
+/// fn spawn_vrm(
+///     mut commands: Commands,
+///     asset_server: Res<AssetServer>,
+/// ) {
+///     commands.spawn(VrmHandle(asset_server.load("memory://test.vrm")));
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct VrmBuilder {
+    name: String,
+    nodes: Vec<BuilderNode>,
+    bones: Vec<VrmBone>,
+    expressions: Vec<VrmExpression>,
+    materials: Vec<(String, MToonMaterial)>,
+    colliders: Vec<(String, ColliderShape)>,
+    springs: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone)]
+struct BuilderNode {
+    name: String,
+    parent: Option<String>,
+    translation: Vec3,
+}
+
+impl VrmBuilder {
+    /// Creates a new builder with the avatar name written in `VRMC_vrm::meta`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..default()
+        }
+    }
+
+    /// Adds a T-pose skeleton that has all the required humanoid bones, `chest` and `neck`.
+    pub fn humanoid(mut self) -> Self {
+        for (bone, parent, translation) in HUMANOID_SKELETON {
+            self = self.bone(bone, parent, Vec3::from_array(translation));
+        }
+        self
+    }
+
+    /// Adds a humanoid bone node named after the bone.
+    ///
+    /// The parent is specified by the node name.
+    pub fn bone(
+        mut self,
+        bone: impl Into<VrmBone>,
+        parent: Option<&str>,
+        translation: Vec3,
+    ) -> Self {
+        let bone = bone.into();
+        self = self.node(bone.0.clone(), parent, translation);
+        self.bones.push(bone);
+        self
+    }
+
+    /// Adds a node that is not a humanoid bone, such as a spring joint.
+    ///
+    /// The parent is specified by the node name.
+    pub fn node(
+        mut self,
+        name: impl Into<String>,
+        parent: Option<&str>,
+        translation: Vec3,
+    ) -> Self {
+        self.nodes.push(BuilderNode {
+            name: name.into(),
+            parent: parent.map(str::to_string),
+            translation,
+        });
+        self
+    }
+
+    /// Adds an expression bound to its own morph target of the `Face` mesh.
+    ///
+    /// The expression is written in `expressions.preset` if it is a preset name, otherwise in `expressions.custom`.
+    pub fn expression(
+        mut self,
+        expression: impl Into<VrmExpression>,
+    ) -> Self {
+        self.expressions.push(expression.into());
+        self
+    }
+
+    /// Adds a material with `VRMC_materials_mtoon` and a mesh node that uses it.
+    ///
+    /// Textures of the material are not written.
+    pub fn mtoon_material(
+        mut self,
+        name: impl Into<String>,
+        material: MToonMaterial,
+    ) -> Self {
+        self.materials.push((name.into(), material));
+        self
+    }
+
+    /// Adds a spring bone collider to the node.
+    ///
+    /// All colliders belong to a single collider group that is used by every spring.
+    pub fn collider(
+        mut self,
+        node: impl Into<String>,
+        shape: ColliderShape,
+    ) -> Self {
+        self.colliders.push((node.into(), shape));
+        self
+    }
+
+    /// Adds a spring chain whose joints are the nodes ordered from the root.
+    pub fn spring<S: Into<String>>(
+        mut self,
+        joints: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.springs
+            .push(joints.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Builds the bytes of the `.vrm` file.
+    ///
+    /// Returns an error if a node referenced by name does not exist.
+    pub fn build(&self) -> AppResult<Vec<u8>> {
+        let mut names = self
+            .nodes
+            .iter()
+            .map(|node| node.name.clone())
+            .collect::<Vec<_>>();
+        let mut nodes = self
+            .nodes
+            .iter()
+            .map(|node| {
+                json!({
+                    "name": node.name,
+                    "translation": node.translation.to_array(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut parents = self
+            .nodes
+            .iter()
+            .map(|node| {
+                node.parent
+                    .as_ref()
+                    .map(|parent| node_index(&names, parent))
+                    .transpose()
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+
+        let mut writer = BufferWriter::default();
+        let mut meshes = Vec::new();
+        let mut materials = Vec::new();
+        for (i, (name, mtoon)) in self.materials.iter().enumerate() {
+            let mut material = json!({ "name": name });
+            write_mtoon(&mut material, mtoon);
+            materials.push(material);
+            meshes.push(json!({
+                "name": name,
+                "primitives": [triangle(&mut writer, Some(i), &[])],
+            }));
+            names.push(name.clone());
+            nodes.push(json!({ "name": name, "mesh": meshes.len() - 1 }));
+            parents.push(None);
+        }
+
+        let mut expressions = json!({ "preset": {}, "custom": {} });
+        if !self.expressions.is_empty() {
+            let face = nodes.len();
+            let targets = self
+                .expressions
+                .iter()
+                .map(VrmExpression::as_str)
+                .collect::<Vec<_>>();
+            meshes.push(json!({
+                "name": FACE_NODE,
+                "primitives": [triangle(&mut writer, (!materials.is_empty()).then_some(0), &targets)],
+                "weights": vec![0.; targets.len()],
+                "extras": { "targetNames": targets },
+            }));
+            names.push(FACE_NODE.to_string());
+            nodes.push(json!({ "name": FACE_NODE, "mesh": meshes.len() - 1 }));
+            parents.push(names.iter().position(|name| name == "head"));
+            for (index, expression) in self.expressions.iter().enumerate() {
+                let kind = if expression.is_custom() {
+                    "custom"
+                } else {
+                    "preset"
+                };
+                expressions[kind][expression.as_str()] = json!({
+                    "isBinary": false,
+                    "overrideBlink": "none",
+                    "overrideLookAt": "none",
+                    "overrideMouth": "none",
+                    "morphTargetBinds": [{ "node": face, "index": index, "weight": 1.0 }],
+                });
+            }
+        }
+
+        for (child, parent) in parents.iter().enumerate() {
+            if let Some(parent) = parent {
+                let children = &mut nodes[*parent]["children"];
+                if children.is_null() {
+                    *children = json!([]);
+                }
+                children.as_array_mut().unwrap().push(json!(child));
+            }
+        }
+        let roots = parents
+            .iter()
+            .enumerate()
+            .filter_map(|(i, parent)| parent.is_none().then_some(i))
+            .collect::<Vec<_>>();
+
+        let human_bones = self
+            .bones
+            .iter()
+            .map(|bone| {
+                Ok((
+                    bone.0.clone(),
+                    json!({ "node": node_index(&names, &bone.0)? }),
+                ))
+            })
+            .collect::<AppResult<Map<_, _>>>()?;
+        let mut extensions = json!({
+            "VRMC_vrm": {
+                "specVersion": "1.0",
+                "meta": {
+                    "name": self.name,
+                    "version": "1.0",
+                    "authors": ["bevy_vrm1"],
+                    "licenseUrl": "https://vrm.dev/licenses/1.0/",
+                    "avatarPermission": "everyone",
+                    "commercialUsage": "corporation",
+                    "creditNotation": "unnecessary",
+                    "allowRedistribution": true,
+                    "modification": "allowModificationRedistribution",
+                },
+                "humanoid": { "humanBones": human_bones },
+                "expressions": expressions,
+            }
+        });
+        let mut extensions_used = vec!["VRMC_vrm"];
+        if !self.springs.is_empty() || !self.colliders.is_empty() {
+            extensions["VRMC_springBone"] = self.spring_bone(&names)?;
+            extensions_used.push("VRMC_springBone");
+        }
+        if self
+            .colliders
+            .iter()
+            .any(|(_, shape)| shape.extended().is_some())
+        {
+            extensions_used.push(VRMCSpringBoneExtendedCollider::NAME);
+        }
+        if !materials.is_empty() {
+            extensions_used.push("VRMC_materials_mtoon");
+        }
+
+        let json = json!({
+            "asset": { "version": "2.0", "generator": "bevy_vrm1" },
+            "extensionsUsed": extensions_used,
+            "scene": 0,
+            "scenes": [{ "nodes": roots }],
+            "nodes": nodes,
+            "meshes": meshes,
+            "materials": materials,
+            "extensions": extensions,
+        });
+        writer.into_glb(json).to_bytes()
+    }
+
+    fn spring_bone(
+        &self,
+        names: &[String],
+    ) -> AppResult<Value> {
+        let colliders = self
+            .colliders
+            .iter()
+            .map(|(node, shape)| Ok(json!(Collider::new(node_index(names, node)?, *shape))))
+            .collect::<AppResult<Vec<_>>>()?;
+        let has_colliders = !colliders.is_empty();
+        let collider_groups = if has_colliders {
+            json!([{ "name": "Colliders", "colliders": (0..colliders.len()).collect::<Vec<_>>() }])
+        } else {
+            json!([])
+        };
+        let props = SpringJointProps {
+            drag_force: 0.5,
+            gravity_dir: Vec3::NEG_Y,
+            gravity_power: 0.,
+            hit_radius: 0.02,
+            stiffness: 1.,
+        };
+        let springs = self
+            .springs
+            .iter()
+            .enumerate()
+            .map(|(i, joints)| {
+                let joints = joints
+                    .iter()
+                    .map(|joint| {
+                        let mut joint = json!({ "node": node_index(names, joint)? });
+                        write_spring_joint(&mut joint, &props);
+                        Ok(joint)
+                    })
+                    .collect::<AppResult<Vec<_>>>()?;
+                let mut spring = json!({ "name": format!("Spring{i}"), "joints": joints });
+                if has_colliders {
+                    spring["colliderGroups"] = json!([0]);
+                }
+                Ok(spring)
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        Ok(json!({
+            "specVersion": "1.0",
+            "colliders": colliders,
+            "colliderGroups": collider_groups,
+            "springs": springs,
+        }))
+    }
+}
+
+fn node_index(
+    names: &[String],
+    name: &str,
+) -> AppResult<usize> {
+    names
+        .iter()
+        .position(|n| n == name)
+        .with_context(|| format!("Not found node `{name}`"))
+}
+
+/// Writes a single triangle primitive with one morph target per name.
+fn triangle(
+    writer: &mut BufferWriter,
+    material: Option<usize>,
+    targets: &[&str],
+) -> Value {
+    let positions = [0., 0., 0., 0.1, 0., 0., 0., 0.1, 0.];
+    let mut primitive = json!({
+        "attributes": {
+            "POSITION": writer.push_f32(&positions, "VEC3", true),
+            "NORMAL": writer.push_f32(&[0., 0., 1., 0., 0., 1., 0., 0., 1.], "VEC3", false),
+        },
+    });
+    if let Some(material) = material {
+        primitive["material"] = json!(material);
+    }
+    if !targets.is_empty() {
+        let targets = targets
+            .iter()
+            .map(|_| {
+                let displacement = [0., 0., 0.01, 0., 0., 0.01, 0., 0., 0.01];
+                json!({ "POSITION": writer.push_f32(&displacement, "VEC3", true) })
+            })
+            .collect::<Vec<_>>();
+        primitive["targets"] = json!(targets);
+    }
+    primitive
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::AppResult;
+    use crate::glb::Glb;
+    use crate::prelude::*;
+    use crate::success;
+    use crate::tests::TestResult;
+    use crate::vrm::expressions::VrmExpressionRegistry;
+    use crate::vrm::gltf::extensions::vrmc_spring_bone::VRMCSpringBone;
+    use crate::vrm::gltf::extensions::vrmc_vrm::VrmcVrm;
+    use crate::vrm::spring_bone::registry::SpringColliderRegistry;
+    use crate::vrm::tests::{spawn_vrm, update_until, vrm_app};
+    use crate::vrm::validation::validate_json;
+    use bevy::asset::io::memory::Dir;
+    use bevy::prelude::*;
+    use std::path::Path;
+
+    fn build_bytes() -> AppResult<Vec<u8>> {
+        VrmBuilder::new("Test")
+            .humanoid()
+            .expression("happy")
+            .expression("wink")
+            .node("Hair1", Some("head"), Vec3::new(0., 0.1, -0.1))
+            .node("Hair2", Some("Hair1"), Vec3::new(0., -0.1, 0.))
+            .collider("head", ColliderShape::Sphere(default()))
+            .spring(["Hair1", "Hair2"])
+            .mtoon_material("Body", MToonMaterial::default())
+            .build()
+    }
+
+    fn build() -> AppResult<Glb> {
+        Glb::from_bytes(&build_bytes()?)
+    }
+
+    #[test]
+    fn built_vrm_is_valid() -> TestResult {
+        let glb = build()?;
+        let report = validate_json(&glb.json);
+        assert!(report.diagnostics.is_empty(), "{:?}", report.diagnostics);
+        success!()
+    }
+
+    #[test]
+    fn built_extensions_can_be_parsed() -> TestResult {
+        let glb = build()?;
+        let vrm: VrmcVrm = serde_json::from_value(glb.json["extensions"]["VRMC_vrm"].clone())?;
+        assert_eq!(vrm.humanoid.human_bones.len(), 17);
+        assert!(vrm.expressions.unwrap().preset.contains_key("happy"));
+        assert!(glb.json["extensions"]["VRMC_vrm"]["expressions"]["custom"]["wink"].is_object());
+
+        let spring_bone: VRMCSpringBone =
+            serde_json::from_value(glb.json["extensions"]["VRMC_springBone"].clone())?;
+        assert_eq!(spring_bone.springs[0].joints.len(), 2);
+        assert_eq!(spring_bone.colliders.len(), 1);
+
+        let _: VrmcMaterialsExtensitions = serde_json::from_value(
+            glb.json["materials"][0]["extensions"]["VRMC_materials_mtoon"].clone(),
+        )?;
+        success!()
+    }
+
+    #[test]
+    fn face_is_attached_to_head() -> TestResult {
+        let glb = build()?;
+        let nodes = glb.json["nodes"].as_array().unwrap();
+        let face = nodes.iter().position(|n| n["name"] == "Face").unwrap();
+        let head = nodes.iter().find(|n| n["name"] == "head").unwrap();
+        assert!(head["children"].as_array().unwrap().contains(&face.into()));
+        success!()
+    }
+
+    #[test]
+    fn spawn_built_vrm() -> TestResult {
+        let dir = Dir::default();
+        dir.insert_asset(Path::new("test.vrm"), build_bytes()?);
+        let mut app = vrm_app(&dir);
+        let vrm = spawn_vrm(&mut app, "test.vrm");
+        update_until(&mut app, |world| {
+            world
+                .get::<VrmExpressionRegistry>(vrm)
+                .is_some_and(|registry| registry.contains_key(&VrmExpression::from("happy")))
+        });
+
+        let world = app.world();
+        assert_eq!(world.get::<Name>(vrm), Some(&Name::new("Test")));
+        let head = world.get::<HeadBoneEntity>(vrm).unwrap().0;
+        assert_eq!(world.get::<Name>(head), Some(&Name::new("head")));
+        assert!(world.get::<SpringColliderRegistry>(vrm).is_some());
+        success!()
+    }
+
+    #[test]
+    fn error_if_parent_not_found() {
+        let result = VrmBuilder::new("Test")
+            .node("Child", Some("Missing"), Vec3::ZERO)
+            .build();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,554 @@
+//! Exports a spawned VRM back into a `.vrm` file.
+
+use crate::error::AppResult;
+use crate::glb::Glb;
+use crate::prelude::*;
+use crate::vrm::expressions::{VrmExpressionPreset, VrmExpressionRegistry};
+use crate::vrm::humanoid_bone::HumanoidBoneRegistry;
+use crate::vrm::loader::{VrmAsset, VrmAssetHandle};
+use crate::vrm::spring_bone::{SpringJointProps, SpringJointState};
+use crate::vrm::BoneRestTransform;
+use anyhow::Context;
+use bevy::asset::Assets;
+use bevy::ecs::system::SystemParam;
+use bevy::gltf::GltfMaterialName;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use serde_json::{json, Map, Value};
+
+/// Exports a spawned VRM into the bytes of a `.vrm`(glb) file.
+///
+/// The meshes, skins and textures are taken from the loaded asset,
+/// and the following state of the spawned entities is written over it:
+///
+/// - The rest pose of each node ([`BoneRestTransform`] for humanoid bones, the initial pose for spring joints)
+/// - `VRMC_vrm`: the humanoid bones, expressions and [`LookAtProperties`]
+/// - `VRMC_springBone`: the joint parameters and collider shapes
+/// - `VRMC_materials_mtoon`: the parameters of [`MToonMaterial`]
+///
+/// Textures replaced at runtime are not re-encoded; the original images are kept.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+///
+/// fn save(
+///     exporter: VrmExporter,
+///     vrm: Query<Entity, With<Vrm>>,
+/// ) {
+///     let bytes = exporter.export(vrm.single().unwrap()).unwrap();
+///     std::fs::write("avatar.vrm", bytes).unwrap();
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct VrmExporter<'w, 's> {
+    vrm_assets: Res<'w, Assets<VrmAsset>>,
+    vrms: Query<
+        'w,
+        's,
+        (
+            &'static VrmAssetHandle,
+            Option<&'static HumanoidBoneRegistry>,
+            Option<&'static VrmExpressionRegistry>,
+            Option<&'static LookAtProperties>,
+        ),
+        With<Vrm>,
+    >,
+    children: Query<'w, 's, &'static Children>,
+    nodes: Query<
+        'w,
+        's,
+        (
+            &'static Name,
+            &'static Transform,
+            Option<&'static BoneRestTransform>,
+            Option<&'static SpringJointState>,
+            Option<&'static SpringJointProps>,
+            Option<&'static ColliderShapes>,
+        ),
+    >,
+    primitives: Query<
+        'w,
+        's,
+        (
+            &'static GltfMaterialName,
+            &'static MeshMaterial3d<MToonMaterial>,
+        ),
+    >,
+    mtoon_materials: Res<'w, Assets<MToonMaterial>>,
+}
+
+impl VrmExporter<'_, '_> {
+    /// Exports the VRM entity into the bytes of a `.vrm` file.
+    ///
+    /// The returned bytes can be loaded again with [`VrmHandle`](crate::prelude::VrmHandle).
+    pub fn export(
+        &self,
+        vrm: Entity,
+    ) -> AppResult<Vec<u8>> {
+        let (handle, bones, expressions, look_at) = self
+            .vrms
+            .get(vrm)
+            .context("The entity is not a spawned VRM")?;
+        let asset = self
+            .vrm_assets
+            .get(handle.0.id())
+            .context("Not found the VRM asset")?;
+        let source = asset
+            .gltf
+            .source
+            .as_ref()
+            .context("Not found the glTF source")?;
+        let mut json = serde_json::to_value(source.document.as_json())?;
+
+        let node_names = node_names(&json);
+        let node_indices = node_names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (name.clone(), index))
+            .collect::<HashMap<_, _>>();
+        let entities = self
+            .children
+            .iter_descendants(vrm)
+            .filter_map(|entity| {
+                let (name, ..) = self.nodes.get(entity).ok()?;
+                Some((name.to_string(), entity))
+            })
+            .collect::<HashMap<_, _>>();
+        let node_entity = |index: usize| entities.get(node_names.get(index)?).copied();
+
+        self.write_nodes(&mut json, &node_entity);
+        self.write_materials(&mut json, vrm);
+        if let Some(vrmc_vrm) = json.pointer_mut("/extensions/VRMC_vrm") {
+            if let Some(bones) = bones {
+                write_humanoid(vrmc_vrm, bones, &node_indices);
+            }
+            if let Some(expressions) = expressions {
+                write_expressions(vrmc_vrm, expressions, &node_indices);
+            }
+            if let Some(look_at) = look_at {
+                vrmc_vrm["lookAt"] = serde_json::to_value(look_at)?;
+            }
+        }
+        let extended_collider = json
+            .pointer_mut("/extensions/VRMC_springBone")
+            .is_some_and(|spring_bone| self.write_spring_bone(spring_bone, &node_entity));
+        if extended_collider {
+            let name = Value::from(VRMCSpringBoneExtendedCollider::NAME);
+            if let Some(used) = json["extensionsUsed"].as_array_mut() {
+                if !used.contains(&name) {
+                    used.push(name);
+                }
+            }
+        }
+
+        Glb {
+            json,
+            bin: source.blob.clone(),
+        }
+        .to_bytes()
+    }
+
+    fn write_nodes(
+        &self,
+        json: &mut Value,
+        node_entity: &impl Fn(usize) -> Option<Entity>,
+    ) {
+        let Some(nodes) = json.get_mut("nodes").and_then(Value::as_array_mut) else {
+            return;
+        };
+        for (index, node) in nodes.iter_mut().enumerate() {
+            let Some((_, tf, rest, spring_state, ..)) =
+                node_entity(index).and_then(|entity| self.nodes.get(entity).ok())
+            else {
+                continue;
+            };
+            let mut tf = rest.map(|rest| rest.0).unwrap_or(*tf);
+            if let Some(state) = spring_state {
+                tf.rotation = state.initial_local_rotation();
+            }
+            write_transform(node, &tf);
+        }
+    }
+
+    fn write_materials(
+        &self,
+        json: &mut Value,
+        vrm: Entity,
+    ) {
+        let Some(materials) = json.get_mut("materials").and_then(Value::as_array_mut) else {
+            return;
+        };
+        for entity in self.children.iter_descendants(vrm) {
+            let Ok((name, handle)) = self.primitives.get(entity) else {
+                continue;
+            };
+            let Some(mtoon) = self.mtoon_materials.get(handle.id()) else {
+                continue;
+            };
+            let Some(material) = materials
+                .iter_mut()
+                .find(|material| material["name"].as_str() == Some(name.0.as_str()))
+            else {
+                continue;
+            };
+            write_mtoon(material, mtoon);
+        }
+    }
+
+    /// Returns `true` if a collider uses `VRMC_springBone_extended_collider`.
+    fn write_spring_bone(
+        &self,
+        spring_bone: &mut Value,
+        node_entity: &impl Fn(usize) -> Option<Entity>,
+    ) -> bool {
+        let node_of = |value: &Value| {
+            let index = value["node"].as_u64()? as usize;
+            self.nodes.get(node_entity(index)?).ok()
+        };
+        let mut extended = false;
+        if let Some(colliders) = spring_bone
+            .get_mut("colliders")
+            .and_then(Value::as_array_mut)
+        {
+            // The shapes of a node are in the same order as its colliders.
+            let mut written = HashMap::<u64, usize>::new();
+            for collider in colliders {
+                let Some((.., Some(shapes))) = node_of(collider) else {
+                    continue;
+                };
+                let nth = written
+                    .entry(collider["node"].as_u64().unwrap_or_default())
+                    .or_default();
+                let Some(shape) = shapes.get(*nth) else {
+                    continue;
+                };
+                *nth += 1;
+                match shape.extended() {
+                    Some(extended_shape) => {
+                        extended = true;
+                        // Keep the fallback authored in the source if any.
+                        if collider.get("shape").is_none() {
+                            collider["shape"] = json!(shape.fallback());
+                        }
+                        collider["extensions"][VRMCSpringBoneExtendedCollider::NAME] =
+                            json!(VRMCSpringBoneExtendedCollider::new(extended_shape));
+                    }
+                    None => {
+                        collider["shape"] = json!(shape);
+                        if let Some(extensions) = collider
+                            .get_mut("extensions")
+                            .and_then(Value::as_object_mut)
+                        {
+                            extensions.remove(VRMCSpringBoneExtendedCollider::NAME);
+                        }
+                    }
+                }
+            }
+        }
+        let Some(springs) = spring_bone.get_mut("springs").and_then(Value::as_array_mut) else {
+            return extended;
+        };
+        for joint in springs
+            .iter_mut()
+            .filter_map(|spring| spring.get_mut("joints").and_then(Value::as_array_mut))
+            .flatten()
+        {
+            if let Some((.., Some(props), _)) = node_of(joint) {
+                write_spring_joint(joint, props);
+            }
+        }
+        extended
+    }
+}
+
+/// Returns the node names in the same way as `bevy_gltf` names the entities.
+fn node_names(json: &Value) -> Vec<String> {
+    json["nodes"]
+        .as_array()
+        .map(|nodes| {
+            nodes
+                .iter()
+                .enumerate()
+                .map(|(index, node)| {
+                    node["name"]
+                        .as_str()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| format!("GltfNode{index}"))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn write_transform(
+    node: &mut Value,
+    tf: &Transform,
+) {
+    let Some(node) = node.as_object_mut() else {
+        return;
+    };
+    node.remove("matrix");
+    node.insert("translation".to_string(), json!(tf.translation.to_array()));
+    node.insert("rotation".to_string(), json!(tf.rotation.to_array()));
+    node.insert("scale".to_string(), json!(tf.scale.to_array()));
+}
+
+fn write_humanoid(
+    vrmc_vrm: &mut Value,
+    bones: &HumanoidBoneRegistry,
+    node_indices: &HashMap<String, usize>,
+) {
+    let human_bones = bones
+        .iter()
+        .filter_map(|(bone, name)| {
+            let node = node_indices.get(name.as_str())?;
+            Some((bone.0.clone(), json!({ "node": node })))
+        })
+        .collect::<Map<_, _>>();
+    vrmc_vrm["humanoid"]["humanBones"] = Value::Object(human_bones);
+}
+
+fn write_expressions(
+    vrmc_vrm: &mut Value,
+    expressions: &VrmExpressionRegistry,
+    node_indices: &HashMap<String, usize>,
+) {
+    for kind in ["preset", "custom"] {
+        let Some(presets) = vrmc_vrm
+            .get_mut("expressions")
+            .and_then(|expressions| expressions.get_mut(kind))
+            .and_then(Value::as_object_mut)
+        else {
+            continue;
+        };
+        for (name, preset) in presets.iter_mut() {
+            let key = match kind {
+                "preset" => VrmExpressionPreset::from_name(name).map(VrmExpression::Preset),
+                _ => Some(VrmExpression::Custom(name.clone())),
+            };
+            let Some(expression) = key.and_then(|key| expressions.get(&key)) else {
+                continue;
+            };
+            let binds = expression
+                .nodes
+                .iter()
+                .filter_map(|node| {
+                    let node_index = *node_indices.get(node.name.as_str())?;
+                    Some(json!({
+                        "node": node_index,
+                        "index": node.morph_target_index,
+                        "weight": node.weight,
+                    }))
+                })
+                .collect::<Vec<_>>();
+            preset["morphTargetBinds"] = Value::Array(binds);
+        }
+    }
+}
+
+pub(super) fn write_spring_joint(
+    joint: &mut Value,
+    props: &SpringJointProps,
+) {
+    joint["dragForce"] = json!(props.drag_force);
+    joint["gravityDir"] = json!(props.gravity_dir.to_array());
+    joint["gravityPower"] = json!(props.gravity_power);
+    joint["hitRadius"] = json!(props.hit_radius);
+    joint["stiffness"] = json!(props.stiffness);
+}
+
+pub(super) fn write_mtoon(
+    material: &mut Value,
+    mtoon: &MToonMaterial,
+) {
+    let base_color = mtoon.base_color.to_linear().to_f32_array();
+    material["pbrMetallicRoughness"]["baseColorFactor"] = json!(base_color);
+    material["emissiveFactor"] = json!(rgb(mtoon.emissive));
+    material["doubleSided"] = json!(mtoon.double_sided);
+    match mtoon.alpha_mode {
+        AlphaMode::Mask(cutoff) => {
+            material["alphaMode"] = json!("MASK");
+            material["alphaCutoff"] = json!(cutoff);
+        }
+        AlphaMode::Blend => material["alphaMode"] = json!("BLEND"),
+        _ => material["alphaMode"] = json!("OPAQUE"),
+    }
+
+    let extension = &mut material["extensions"]["VRMC_materials_mtoon"];
+    if extension.is_null() {
+        extension["specVersion"] = json!("1.0");
+    }
+    extension["transparentWithZWrite"] = json!(mtoon.transparent_with_z_write);
+    extension["renderQueueOffsetNumber"] = json!(mtoon.render_queue_offset);
+    extension["shadeColorFactor"] = json!(rgb(mtoon.shade.color));
+    extension["shadingShiftFactor"] = json!(mtoon.shade.shading_shift_factor);
+    extension["shadingToonyFactor"] = json!(mtoon.shade.toony_factor);
+    if extension["shadingShiftTexture"].is_object() {
+        extension["shadingShiftTexture"]["scale"] = json!(mtoon.shade.texture_scale);
+    }
+    extension["giEqualizationFactor"] = json!(mtoon.gi_equalization_factor);
+    extension["matcapFactor"] = json!(rgb(mtoon.rim_lighting.mat_cap_color));
+    extension["parametricRimColorFactor"] = json!(rgb(mtoon.rim_lighting.color));
+    extension["parametricRimFresnelPowerFactor"] = json!(mtoon.rim_lighting.fresnel_power);
+    extension["parametricRimLiftFactor"] = json!(mtoon.rim_lighting.lift_factor);
+    extension["rimLightingMixFactor"] = json!(mtoon.rim_lighting.mix_factor);
+    match mtoon.outline.mode {
+        OutlineWidthMode::WorldCoordinates => {
+            extension["outlineWidthMode"] = json!("worldCoordinates");
+        }
+        OutlineWidthMode::None => {
+            if extension["outlineWidthMode"].as_str() != Some("screenCoordinates") {
+                extension["outlineWidthMode"] = json!("none");
+            }
+        }
+    }
+    extension["outlineWidthFactor"] = json!(mtoon.outline.width_factor);
+    extension["outlineColorFactor"] = json!(rgb(mtoon.outline.color));
+    extension["outlineLightingMixFactor"] = json!(mtoon.outline.lighting_mix_factor);
+    extension["uvAnimationRotationSpeedFactor"] = json!(mtoon.uv_animation.rotation_speed);
+    extension["uvAnimationScrollXSpeedFactor"] = json!(mtoon.uv_animation.scroll_speed.x);
+    extension["uvAnimationScrollYSpeedFactor"] = json!(mtoon.uv_animation.scroll_speed.y);
+}
+
+#[inline]
+fn rgb(color: LinearRgba) -> [f32; 3] {
+    [color.red, color.green, color.blue]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::success;
+    use crate::tests::TestResult;
+    use crate::vrm::exporter::{node_names, write_mtoon, write_spring_joint, write_transform};
+    use crate::vrm::spring_bone::SpringJointProps;
+    use crate::vrm::tests::{spawn_vrm, update_until, vrm_app};
+    use bevy::asset::io::memory::Dir;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::*;
+    use serde_json::json;
+    use std::path::Path;
+
+    #[test]
+    fn mtoon_round_trip() -> TestResult {
+        let mtoon = MToonMaterial {
+            shade: Shade {
+                color: LinearRgba::rgb(0.1, 0.2, 0.3),
+                shading_shift_factor: -0.2,
+                toony_factor: 0.5,
+                ..default()
+            },
+            rim_lighting: RimLighting {
+                color: LinearRgba::RED,
+                lift_factor: 0.3,
+                ..default()
+            },
+            outline: MToonOutline {
+                mode: OutlineWidthMode::WorldCoordinates,
+                width_factor: 0.01,
+                color: LinearRgba::BLUE,
+                lighting_mix_factor: 0.5,
+            },
+            uv_animation: UVAnimation {
+                rotation_speed: 1.0,
+                scroll_speed: Vec2::new(0.5, -0.5),
+            },
+            gi_equalization_factor: 0.7,
+            render_queue_offset: 2.,
+            ..default()
+        };
+        let mut material = json!({ "name": "body" });
+        write_mtoon(&mut material, &mtoon);
+
+        let extension: VrmcMaterialsExtensitions =
+            serde_json::from_value(material["extensions"]["VRMC_materials_mtoon"].clone())?;
+        assert_eq!(Shade::from(&extension).color, mtoon.shade.color);
+        assert_eq!(Shade::from(&extension).toony_factor, 0.5);
+        assert_eq!(RimLighting::from(&extension), mtoon.rim_lighting);
+        assert_eq!(MToonOutline::from(&extension), mtoon.outline);
+        assert_eq!(UVAnimation::from(&extension), mtoon.uv_animation);
+        assert_eq!(extension.gi_equalization_factor, 0.7);
+        assert_eq!(extension.render_queue_offset_number, 2.);
+        success!()
+    }
+
+    #[test]
+    fn write_joint_props() -> TestResult {
+        let mut joint = json!({ "node": 3 });
+        write_spring_joint(
+            &mut joint,
+            &SpringJointProps {
+                drag_force: 0.4,
+                gravity_dir: Vec3::NEG_Y,
+                gravity_power: 1.,
+                hit_radius: 0.02,
+                stiffness: 0.5,
+            },
+        );
+        let joint: SpringJoint = serde_json::from_value(joint)?;
+        assert_eq!(joint.node, 3);
+        assert_eq!(joint.drag_force, Some(0.4));
+        assert_eq!(joint.gravity_dir, Some([0., -1., 0.]));
+        assert_eq!(joint.stiffness, Some(0.5));
+        success!()
+    }
+
+    /// Spawns the VRM and returns the collider shapes of its head.
+    fn head_collider_shapes(
+        app: &mut App,
+        path: &str,
+    ) -> (Entity, ColliderShapes) {
+        let vrm = spawn_vrm(app, path);
+        let head = app.world().get::<HeadBoneEntity>(vrm).unwrap().0;
+        update_until(app, |world| world.get::<ColliderShapes>(head).is_some());
+        let shapes = app.world().get::<ColliderShapes>(head).unwrap().clone();
+        (vrm, shapes)
+    }
+
+    #[test]
+    fn keep_several_colliders_of_a_node() -> TestResult {
+        let sphere = ColliderShape::Sphere(crate::prelude::Sphere {
+            offset: [0., 0.1, 0.],
+            radius: 0.1,
+        });
+        let capsule = ColliderShape::Capsule(crate::prelude::Capsule {
+            offset: [0., -0.1, 0.],
+            radius: 0.05,
+            tail: [0., -0.2, 0.],
+        });
+        let bytes = VrmBuilder::new("Test")
+            .humanoid()
+            .collider("head", sphere)
+            .collider("head", capsule)
+            .build()?;
+        let dir = Dir::default();
+        dir.insert_asset(Path::new("test.vrm"), bytes);
+        let mut app = vrm_app(&dir);
+        let (vrm, shapes) = head_collider_shapes(&mut app, "test.vrm");
+        assert_eq!(shapes, ColliderShapes(vec![sphere, capsule]));
+
+        let exported = app
+            .world_mut()
+            .run_system_once(move |exporter: VrmExporter| exporter.export(vrm))??;
+        dir.insert_asset(Path::new("exported.vrm"), exported);
+        let (_, shapes) = head_collider_shapes(&mut app, "exported.vrm");
+        assert_eq!(shapes, ColliderShapes(vec![sphere, capsule]));
+        success!()
+    }
+
+    #[test]
+    fn replace_matrix_with_trs() {
+        let mut node = json!({ "name": "hips", "matrix": vec![1.0; 16] });
+        write_transform(&mut node, &Transform::from_xyz(0., 1., 0.));
+        assert!(node.get("matrix").is_none());
+        assert_eq!(node["translation"], json!([0., 1., 0.]));
+        assert_eq!(node["rotation"], json!([0., 0., 0., 1.]));
+    }
+
+    #[test]
+    fn unnamed_nodes_use_bevy_names() {
+        let json = json!({ "nodes": [{ "name": "root" }, {}] });
+        assert_eq!(node_names(&json), vec!["root", "GltfNode1"]);
+    }
+}
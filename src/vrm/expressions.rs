@@ -1,20 +1,264 @@
+mod material_binds;
+mod mixer;
+
+use crate::system_param::child_searcher::ChildSearcher;
+use crate::vrm::expressions::material_binds::ExpressionMaterialBindPlugin;
+use crate::vrm::expressions::mixer::ExpressionMixerPlugin;
 use crate::vrm::gltf::extensions::vrmc_vrm::MorphTargetBind;
 use crate::vrm::gltf::extensions::VrmExtensions;
-use crate::vrm::VrmExpression;
-use bevy::app::Plugin;
+use crate::vrm::humanoid_bone::HumanoidBonesAttached;
+use bevy::app::{Plugin, Update};
 use bevy::asset::{Assets, Handle};
 use bevy::gltf::GltfNode;
 use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
 
+pub(crate) use material_binds::ExpressionMaterialBindRegistry;
+pub(crate) use mixer::{
+    mix_expressions, requested_weight, MixedExpressionWeights, ProceduralExpressionWeights,
+};
+
+/// The expression presets defined in `VRMC_vrm::expressions::preset`.
+///
+/// Expressions that are not listed here are custom expressions defined in `VRMC_vrm::expressions::custom`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub enum VrmExpressionPreset {
+    Happy,
+    Angry,
+    Sad,
+    Relaxed,
+    Surprised,
+    Aa,
+    Ih,
+    Ou,
+    Ee,
+    Oh,
+    Blink,
+    BlinkLeft,
+    BlinkRight,
+    LookUp,
+    LookDown,
+    LookLeft,
+    LookRight,
+    Neutral,
+}
+
+impl VrmExpressionPreset {
+    pub const ALL: [Self; 18] = [
+        Self::Happy,
+        Self::Angry,
+        Self::Sad,
+        Self::Relaxed,
+        Self::Surprised,
+        Self::Aa,
+        Self::Ih,
+        Self::Ou,
+        Self::Ee,
+        Self::Oh,
+        Self::Blink,
+        Self::BlinkLeft,
+        Self::BlinkRight,
+        Self::LookUp,
+        Self::LookDown,
+        Self::LookLeft,
+        Self::LookRight,
+        Self::Neutral,
+    ];
+
+    /// Returns the key name used in `VRMC_vrm::expressions::preset`.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Happy => "happy",
+            Self::Angry => "angry",
+            Self::Sad => "sad",
+            Self::Relaxed => "relaxed",
+            Self::Surprised => "surprised",
+            Self::Aa => "aa",
+            Self::Ih => "ih",
+            Self::Ou => "ou",
+            Self::Ee => "ee",
+            Self::Oh => "oh",
+            Self::Blink => "blink",
+            Self::BlinkLeft => "blinkLeft",
+            Self::BlinkRight => "blinkRight",
+            Self::LookUp => "lookUp",
+            Self::LookDown => "lookDown",
+            Self::LookLeft => "lookLeft",
+            Self::LookRight => "lookRight",
+            Self::Neutral => "neutral",
+        }
+    }
+
+    /// Returns the preset of the key name, or `None` if the name is not a preset.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.as_str() == name)
+    }
+}
+
+/// An expression of VRM.
+///
+/// The expressions read from a VRM or VRMA keep the map they were defined in,
+/// `VRMC_vrm::expressions::preset` or `VRMC_vrm::expressions::custom`.
+/// A name converted with [`From`] is a preset if it matches one, because custom expressions
+/// cannot use the names of the presets.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub enum VrmExpression {
+    Preset(VrmExpressionPreset),
+    /// An expression defined by the modeler, such as tongue out or star eyes.
+    Custom(String),
+}
+
+impl VrmExpression {
+    /// Returns the key name in `VRMC_vrm::expressions`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Preset(preset) => preset.as_str(),
+            Self::Custom(name) => name,
+        }
+    }
+
+    /// Returns the preset if this is a preset expression.
+    pub const fn preset(&self) -> Option<VrmExpressionPreset> {
+        match self {
+            Self::Preset(preset) => Some(*preset),
+            Self::Custom(_) => None,
+        }
+    }
+
+    /// Returns `true` if this is a custom expression defined by the modeler.
+    pub const fn is_custom(&self) -> bool {
+        matches!(self, Self::Custom(_))
+    }
+}
+
+impl From<VrmExpressionPreset> for VrmExpression {
+    fn from(preset: VrmExpressionPreset) -> Self {
+        Self::Preset(preset)
+    }
+}
+
+impl From<&str> for VrmExpression {
+    fn from(name: &str) -> Self {
+        match VrmExpressionPreset::from_name(name) {
+            Some(preset) => Self::Preset(preset),
+            None => Self::Custom(name.to_string()),
+        }
+    }
+}
+
+impl From<String> for VrmExpression {
+    fn from(name: String) -> Self {
+        match VrmExpressionPreset::from_name(&name) {
+            Some(preset) => Self::Preset(preset),
+            None => Self::Custom(name),
+        }
+    }
+}
+
+impl std::fmt::Display for VrmExpression {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[derive(Reflect, Debug, Clone)]
 pub(crate) struct ExpressionNode {
     pub name: Name,
     pub morph_target_index: usize,
+    /// The morph target weight when the expression weight is `1.0`.
+    pub weight: f32,
+}
+
+/// The weight of each expression of the VRM.
+///
+/// This component is automatically inserted into the VRM entity, and the bound morph targets
+/// are updated whenever it changes. Retargeted VRMA animations also write their expressions here.
+///
+/// These are the requested weights; `isBinary` and the `overrideBlink`, `overrideLookAt` and
+/// `overrideMouth` rules of each expression are applied before the morph targets are written.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+///
+/// fn smile(mut vrms: Query<&mut VrmExpressionWeights>) {
+///     for mut weights in vrms.iter_mut() {
+///         weights.set(VrmExpressionPreset::Happy, 0.8);
+///         weights.set("tongueOut", 1.0);
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Default, Clone, PartialEq, Reflect)]
+#[reflect(Component, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub struct VrmExpressionWeights(HashMap<VrmExpression, f32>);
+
+impl VrmExpressionWeights {
+    /// Sets the weight of the expression, clamped to `0.0..=1.0`.
+    pub fn set(
+        &mut self,
+        expression: impl Into<VrmExpression>,
+        weight: f32,
+    ) {
+        self.0.insert(expression.into(), weight.clamp(0.0, 1.0));
+    }
+
+    /// Returns the weight of the expression, or `0.0` if it has not been set.
+    pub fn get(
+        &self,
+        expression: impl Into<VrmExpression>,
+    ) -> f32 {
+        self.0.get(&expression.into()).copied().unwrap_or_default()
+    }
+
+    /// Iterates over the expressions whose weight has been set.
+    pub fn iter(&self) -> impl Iterator<Item = (&VrmExpression, f32)> {
+        self.0
+            .iter()
+            .map(|(expression, weight)| (expression, *weight))
+    }
+}
+
+/// How an expression suppresses the blink, look at or mouth expressions while it is active.
+#[derive(Reflect, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub(crate) enum ExpressionOverride {
+    #[default]
+    None,
+    /// Suppresses them completely while the weight is greater than 0.
+    Block,
+    /// Suppresses them in proportion to the weight.
+    Blend,
+}
+
+impl ExpressionOverride {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "block" => Self::Block,
+            "blend" => Self::Blend,
+            _ => Self::None,
+        }
+    }
+}
+
+#[derive(Reflect, Debug, Clone, Default)]
+pub(crate) struct RegisteredExpression {
+    pub nodes: Vec<ExpressionNode>,
+    pub is_binary: bool,
+    pub override_blink: ExpressionOverride,
+    pub override_look_at: ExpressionOverride,
+    pub override_mouth: ExpressionOverride,
 }
 
 #[derive(Component, Deref, Reflect)]
-pub(crate) struct VrmExpressionRegistry(HashMap<VrmExpression, Vec<ExpressionNode>>);
+pub(crate) struct VrmExpressionRegistry(pub(crate) HashMap<VrmExpression, RegisteredExpression>);
 
 impl VrmExpressionRegistry {
     pub fn new(
@@ -27,15 +271,23 @@ impl VrmExpressionRegistry {
         };
         Self(
             expressions
-                .preset
                 .iter()
-                .filter_map(|(preset_name, preset)| {
-                    let binds = preset.morph_target_binds.as_ref()?;
-                    let node = binds
-                        .iter()
-                        .filter_map(|bind| convert_to_node(bind, node_assets, nodes))
-                        .collect::<Vec<_>>();
-                    Some((VrmExpression(preset_name.clone()), node))
+                .map(|(name, expression)| {
+                    let registered = RegisteredExpression {
+                        nodes: expression
+                            .morph_target_binds
+                            .iter()
+                            .flatten()
+                            .filter_map(|bind| convert_to_node(bind, node_assets, nodes))
+                            .collect(),
+                        is_binary: expression.is_binary,
+                        override_blink: ExpressionOverride::from_name(&expression.override_blink),
+                        override_look_at: ExpressionOverride::from_name(
+                            &expression.override_look_at,
+                        ),
+                        override_mouth: ExpressionOverride::from_name(&expression.override_mouth),
+                    };
+                    (name, registered)
                 })
                 .collect(),
         )
@@ -49,7 +301,11 @@ impl Plugin for VrmExpressionPlugin {
         &self,
         app: &mut bevy::app::App,
     ) {
-        app.register_type::<VrmExpressionRegistry>();
+        app.register_type::<VrmExpressionRegistry>()
+            .register_type::<VrmExpressionWeights>()
+            .register_type::<VrmExpressionPreset>()
+            .add_plugins((ExpressionMixerPlugin, ExpressionMaterialBindPlugin))
+            .add_systems(Update, apply_expression_weights.after(mix_expressions));
     }
 }
 
@@ -63,5 +319,152 @@ fn convert_to_node(
     Some(ExpressionNode {
         name: Name::new(node.name.clone()),
         morph_target_index: bind.index,
+        weight: bind.weight,
     })
 }
+
+/// Writes the mixed expression weights into the bound morph targets.
+///
+/// Morph targets bound by several expressions receive the sum of their contributions.
+fn apply_expression_weights(
+    vrms: Query<
+        (Entity, &MixedExpressionWeights, &VrmExpressionRegistry),
+        (
+            Or<(
+                Changed<MixedExpressionWeights>,
+                Added<HumanoidBonesAttached>,
+            )>,
+            With<HumanoidBonesAttached>,
+        ),
+    >,
+    searcher: ChildSearcher,
+    mut morph_weights: Query<&mut MorphWeights>,
+) {
+    for (vrm, weights, registry) in vrms.iter() {
+        let mut targets = HashMap::<(Entity, usize), f32>::new();
+        for (expression, registered) in registry.iter() {
+            let weight = weights.get(expression);
+            for node in registered.nodes.iter() {
+                let Some(entity) = searcher.find_from_name(vrm, &node.name) else {
+                    continue;
+                };
+                *targets
+                    .entry((entity, node.morph_target_index))
+                    .or_default() += weight * node.weight;
+            }
+        }
+        for ((entity, index), weight) in targets {
+            let Ok(mut morph_weights) = morph_weights.get_mut(entity) else {
+                continue;
+            };
+            if let Some(w) = morph_weights.weights_mut().get_mut(index) {
+                *w = weight;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::tests::test_app;
+    use crate::vrm::expressions::mixer::mix_expressions;
+    use crate::vrm::expressions::{
+        apply_expression_weights, ExpressionNode, MixedExpressionWeights, RegisteredExpression,
+        VrmExpressionRegistry,
+    };
+    use crate::vrm::gltf::extensions::vrmc_vrm::Expressions;
+    use crate::vrm::humanoid_bone::HumanoidBonesAttached;
+    use bevy::platform::collections::HashMap;
+    use bevy::prelude::*;
+    use bevy::render::mesh::morph::MorphWeights;
+
+    fn expression(nodes: &[(usize, f32)]) -> RegisteredExpression {
+        RegisteredExpression {
+            nodes: nodes
+                .iter()
+                .map(|(index, weight)| ExpressionNode {
+                    name: Name::new("Face"),
+                    morph_target_index: *index,
+                    weight: *weight,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn apply_weights_with_bind_weight() {
+        let mut app = test_app();
+        app.add_systems(Update, (mix_expressions, apply_expression_weights).chain());
+        let registry = VrmExpressionRegistry(HashMap::from_iter([
+            (
+                VrmExpression::from("happy"),
+                expression(&[(0, 0.5), (1, 1.0)]),
+            ),
+            (VrmExpression::from("tongueOut"), expression(&[(1, 1.0)])),
+        ]));
+        let mut weights = VrmExpressionWeights::default();
+        weights.set(VrmExpressionPreset::Happy, 0.8);
+        weights.set("tongueOut", 2.0);
+        let vrm = app
+            .world_mut()
+            .spawn((
+                HumanoidBonesAttached,
+                registry,
+                weights,
+                MixedExpressionWeights::default(),
+            ))
+            .id();
+        let face = app
+            .world_mut()
+            .spawn((
+                ChildOf(vrm),
+                Name::new("Face"),
+                MorphWeights::new(vec![0.0; 2], None).unwrap(),
+            ))
+            .id();
+        app.update();
+
+        let morph_weights = app.world().get::<MorphWeights>(face).unwrap().weights();
+        assert_eq!(morph_weights[0], 0.4);
+        assert_eq!(morph_weights[1], 1.8);
+    }
+
+    #[test]
+    fn preset_names() {
+        for preset in VrmExpressionPreset::ALL {
+            assert_eq!(
+                VrmExpressionPreset::from_name(preset.as_str()),
+                Some(preset)
+            );
+        }
+        assert_eq!(
+            VrmExpression::from("blinkLeft").preset(),
+            Some(VrmExpressionPreset::BlinkLeft)
+        );
+        assert!(VrmExpression::from("tongueOut").is_custom());
+        assert_eq!(
+            VrmExpression::from(VrmExpressionPreset::LookUp),
+            VrmExpression::from("lookUp")
+        );
+    }
+
+    #[test]
+    fn expressions_keep_their_map() {
+        let expressions: Expressions = serde_json::from_value(serde_json::json!({
+            "preset": { "happy": {}, "unknown": {} },
+            "custom": { "tongueOut": {}, "sad": {} },
+        }))
+        .unwrap();
+        let mut names = expressions.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                VrmExpression::Preset(VrmExpressionPreset::Happy),
+                VrmExpression::Custom("tongueOut".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,395 @@
+//! Applies `materialColorBinds` and `textureTransformBinds` of the expressions to [`MToonMaterial`].
+//!
+//! Each primitive owns its [`MToonMaterial`] asset, so the binds are written per primitive
+//! and never bleed into other VRM instances that use the same material.
+
+use crate::prelude::MToonMaterial;
+use crate::vrm::expressions::mixer::mix_expressions;
+use crate::vrm::expressions::MixedExpressionWeights;
+use crate::vrm::gltf::extensions::vrmc_vrm::MaterialColorType;
+use crate::vrm::gltf::extensions::VrmExtensions;
+use crate::vrm::VrmExpression;
+use bevy::app::{App, Update};
+use bevy::gltf::{Gltf, GltfMaterialName};
+use bevy::math::{Affine2, Mat2};
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+pub(super) struct ExpressionMaterialBindPlugin;
+
+impl Plugin for ExpressionMaterialBindPlugin {
+    fn build(
+        &self,
+        app: &mut App,
+    ) {
+        app.register_type::<ExpressionMaterialBindRegistry>()
+            .register_type::<MaterialBindBase>()
+            .add_systems(Update, apply_material_binds.after(mix_expressions));
+    }
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub(crate) struct ColorBind {
+    pub material: String,
+    pub ty: MaterialColorType,
+    pub target: LinearRgba,
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub(crate) struct TextureTransformBind {
+    pub material: String,
+    pub scale: Vec2,
+    pub offset: Vec2,
+}
+
+#[derive(Reflect, Debug, Clone, Default)]
+pub(crate) struct MaterialBinds {
+    pub colors: Vec<ColorBind>,
+    pub texture_transforms: Vec<TextureTransformBind>,
+}
+
+/// The material binds of each expression, keyed by the glTF material name.
+///
+/// This is attached to the VRM entity.
+#[derive(Component, Debug, Default, Deref, Reflect)]
+#[reflect(Component)]
+pub(crate) struct ExpressionMaterialBindRegistry(HashMap<VrmExpression, MaterialBinds>);
+
+impl ExpressionMaterialBindRegistry {
+    pub fn new(
+        extensions: &VrmExtensions,
+        gltf: &Gltf,
+    ) -> Self {
+        let Some(expressions) = extensions.vrmc_vrm.expressions.as_ref() else {
+            return Self::default();
+        };
+        let material_names = gltf
+            .source
+            .as_ref()
+            .map(|source| {
+                source
+                    .materials()
+                    .map(|m| m.name().map(str::to_string))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let material_name = |index: usize| material_names.get(index).cloned().flatten();
+        Self(
+            expressions
+                .iter()
+                .filter_map(|(name, expression)| {
+                    let binds = MaterialBinds {
+                        colors: expression
+                            .material_color_binds
+                            .iter()
+                            .flatten()
+                            .filter_map(|bind| {
+                                let [r, g, b, a] = bind.target_value;
+                                Some(ColorBind {
+                                    material: material_name(bind.material)?,
+                                    ty: bind.r#type,
+                                    target: LinearRgba::new(r, g, b, a),
+                                })
+                            })
+                            .collect(),
+                        texture_transforms: expression
+                            .texture_transform_binds
+                            .iter()
+                            .flatten()
+                            .filter_map(|bind| {
+                                Some(TextureTransformBind {
+                                    material: material_name(bind.material)?,
+                                    scale: Vec2::from(bind.scale),
+                                    offset: Vec2::from(bind.offset),
+                                })
+                            })
+                            .collect(),
+                    };
+                    (!binds.colors.is_empty() || !binds.texture_transforms.is_empty())
+                        .then_some((name, binds))
+                })
+                .collect(),
+        )
+    }
+}
+
+/// The material values before any expression is applied.
+///
+/// This is attached to the primitive entity the first time one of its material binds is applied.
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+struct MaterialBindBase {
+    colors: [LinearRgba; 6],
+    uv_transform: Affine2,
+}
+
+impl MaterialBindBase {
+    fn new(material: &MToonMaterial) -> Self {
+        Self {
+            colors: [
+                material.base_color.to_linear(),
+                material.emissive,
+                material.shade.color,
+                material.rim_lighting.mat_cap_color,
+                material.rim_lighting.color,
+                material.outline.color,
+            ],
+            uv_transform: material.uv_transform,
+        }
+    }
+
+    const fn color_index(ty: MaterialColorType) -> usize {
+        match ty {
+            MaterialColorType::Color => 0,
+            MaterialColorType::EmissionColor => 1,
+            MaterialColorType::ShadeColor => 2,
+            MaterialColorType::MatcapColor => 3,
+            MaterialColorType::RimColor => 4,
+            MaterialColorType::OutlineColor => 5,
+        }
+    }
+}
+
+/// Applies the binds when the weights change or the primitives are turned into [`MToonMaterial`].
+///
+/// The [`MToonMaterial`]s appear some frames after the VRM is spawned,
+/// so the weights set before that are applied once the materials are ready.
+fn apply_material_binds(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<MToonMaterial>>,
+    vrms: Query<(
+        Entity,
+        Ref<MixedExpressionWeights>,
+        &ExpressionMaterialBindRegistry,
+    )>,
+    children: Query<&Children>,
+    primitives: Query<(
+        &GltfMaterialName,
+        Ref<MeshMaterial3d<MToonMaterial>>,
+        Option<&MaterialBindBase>,
+    )>,
+    new_primitives: Query<
+        (),
+        (
+            Changed<MeshMaterial3d<MToonMaterial>>,
+            Without<MaterialBindBase>,
+        ),
+    >,
+) {
+    let has_new_primitives = !new_primitives.is_empty();
+    for (vrm, weights, registry) in vrms.iter() {
+        let weights_changed = weights.is_changed();
+        if !weights_changed && !has_new_primitives {
+            continue;
+        }
+        for entity in children.iter_descendants(vrm) {
+            let Ok((material_name, handle, base)) = primitives.get(entity) else {
+                continue;
+            };
+            if !weights_changed && (base.is_some() || !handle.is_changed()) {
+                continue;
+            }
+            let bound = registry.values().any(|binds| {
+                binds.colors.iter().any(|b| b.material == material_name.0)
+                    || binds
+                        .texture_transforms
+                        .iter()
+                        .any(|b| b.material == material_name.0)
+            });
+            if !bound {
+                continue;
+            }
+            let Some(material) = materials.get_mut(handle.id()) else {
+                continue;
+            };
+            let base = match base {
+                Some(base) => base.clone(),
+                None => {
+                    let base = MaterialBindBase::new(material);
+                    commands.entity(entity).insert(base.clone());
+                    base
+                }
+            };
+            let (colors, uv_transform) = blend(&base, registry, &weights, &material_name.0);
+            material.base_color = Color::from(colors[0]);
+            material.emissive = colors[1];
+            material.shade.color = colors[2];
+            material.rim_lighting.mat_cap_color = colors[3];
+            material.rim_lighting.color = colors[4];
+            material.outline.color = colors[5];
+            material.uv_transform = uv_transform;
+        }
+    }
+}
+
+/// Blends the base values toward the targets of the binds in proportion to the expression weights.
+fn blend(
+    base: &MaterialBindBase,
+    registry: &ExpressionMaterialBindRegistry,
+    weights: &MixedExpressionWeights,
+    material_name: &str,
+) -> ([LinearRgba; 6], Affine2) {
+    let mut colors = base.colors;
+    let mut scale = Vec2::ONE;
+    let mut offset = Vec2::ZERO;
+    for (expression, binds) in registry.iter() {
+        let weight = weights.get(expression);
+        if weight == 0.0 {
+            continue;
+        }
+        for bind in binds.colors.iter().filter(|b| b.material == material_name) {
+            let index = MaterialBindBase::color_index(bind.ty);
+            let delta = bind.target.to_vec4() - base.colors[index].to_vec4();
+            colors[index] = LinearRgba::from_vec4(colors[index].to_vec4() + delta * weight);
+        }
+        for bind in binds
+            .texture_transforms
+            .iter()
+            .filter(|b| b.material == material_name)
+        {
+            scale += (bind.scale - Vec2::ONE) * weight;
+            offset += bind.offset * weight;
+        }
+    }
+    let uv_transform = Affine2 {
+        matrix2: base.uv_transform.matrix2 * Mat2::from_diagonal(scale),
+        translation: base.uv_transform.translation + offset,
+    };
+    (colors, uv_transform)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::MToonMaterial;
+    use crate::tests::test_app;
+    use crate::vrm::expressions::material_binds::{
+        apply_material_binds, ColorBind, ExpressionMaterialBindRegistry, MaterialBinds,
+        TextureTransformBind,
+    };
+    use crate::vrm::expressions::MixedExpressionWeights;
+    use crate::vrm::gltf::extensions::vrmc_vrm::MaterialColorType;
+    use crate::vrm::VrmExpression;
+    use bevy::gltf::GltfMaterialName;
+    use bevy::math::Affine2;
+    use bevy::platform::collections::HashMap;
+    use bevy::prelude::*;
+
+    fn spawn_vrm(
+        app: &mut App,
+        material: Handle<MToonMaterial>,
+    ) -> Entity {
+        let registry = ExpressionMaterialBindRegistry(HashMap::from_iter([(
+            VrmExpression::from("happy"),
+            MaterialBinds {
+                colors: vec![ColorBind {
+                    material: "Face".to_string(),
+                    ty: MaterialColorType::Color,
+                    target: LinearRgba::RED,
+                }],
+                texture_transforms: vec![TextureTransformBind {
+                    material: "Face".to_string(),
+                    scale: Vec2::splat(3.0),
+                    offset: Vec2::new(0.5, 0.0),
+                }],
+            },
+        )]));
+        let vrm = app
+            .world_mut()
+            .spawn((MixedExpressionWeights::default(), registry))
+            .id();
+        app.world_mut().spawn((
+            ChildOf(vrm),
+            GltfMaterialName("Face".to_string()),
+            MeshMaterial3d(material),
+        ));
+        vrm
+    }
+
+    #[test]
+    fn apply_in_proportion_to_weight_per_instance() {
+        let mut app = test_app();
+        app.init_asset::<MToonMaterial>()
+            .add_systems(Update, apply_material_binds);
+        let mut materials = app.world_mut().resource_mut::<Assets<MToonMaterial>>();
+        let base = MToonMaterial {
+            base_color: Color::from(LinearRgba::BLACK),
+            ..default()
+        };
+        let first = materials.add(base.clone());
+        let second = materials.add(base);
+        let vrm = spawn_vrm(&mut app, first.clone());
+        spawn_vrm(&mut app, second.clone());
+        app.update();
+
+        app.world_mut()
+            .get_mut::<MixedExpressionWeights>(vrm)
+            .unwrap()
+            .weights
+            .insert(VrmExpression::from("happy"), 0.5);
+        app.update();
+
+        let materials = app.world().resource::<Assets<MToonMaterial>>();
+        let first = materials.get(&first).unwrap();
+        assert_eq!(
+            first.base_color.to_linear(),
+            LinearRgba::new(0.5, 0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            first.uv_transform.matrix2,
+            Mat2::from_diagonal(Vec2::splat(2.0))
+        );
+        assert_eq!(first.uv_transform.translation, Vec2::new(0.25, 0.0));
+        let second = materials.get(&second).unwrap();
+        assert_eq!(second.base_color.to_linear(), LinearRgba::BLACK);
+        assert_eq!(second.uv_transform, Affine2::IDENTITY);
+    }
+
+    #[test]
+    fn apply_weights_set_before_materials_appear() {
+        let mut app = test_app();
+        app.init_asset::<MToonMaterial>()
+            .add_systems(Update, apply_material_binds);
+        let vrm = app
+            .world_mut()
+            .spawn((
+                MixedExpressionWeights {
+                    weights: HashMap::from_iter([(VrmExpression::from("happy"), 1.0)]),
+                    ..default()
+                },
+                ExpressionMaterialBindRegistry(HashMap::from_iter([(
+                    VrmExpression::from("happy"),
+                    MaterialBinds {
+                        colors: vec![ColorBind {
+                            material: "Face".to_string(),
+                            ty: MaterialColorType::Color,
+                            target: LinearRgba::RED,
+                        }],
+                        texture_transforms: Vec::new(),
+                    },
+                )])),
+            ))
+            .id();
+        app.update();
+        app.update();
+
+        let material = app
+            .world_mut()
+            .resource_mut::<Assets<MToonMaterial>>()
+            .add(MToonMaterial {
+                base_color: Color::from(LinearRgba::BLACK),
+                ..default()
+            });
+        app.world_mut().spawn((
+            ChildOf(vrm),
+            GltfMaterialName("Face".to_string()),
+            MeshMaterial3d(material.clone()),
+        ));
+        app.update();
+
+        let materials = app.world().resource::<Assets<MToonMaterial>>();
+        assert_eq!(
+            materials.get(&material).unwrap().base_color.to_linear(),
+            LinearRgba::RED
+        );
+    }
+}
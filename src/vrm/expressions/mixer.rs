@@ -0,0 +1,263 @@
+//! Mixes the requested expression weights into the weights actually applied to the VRM.
+//!
+//! - [`expression specification(en)`](https://github.com/vrm-c/vrm-specification/blob/master/specification/VRMC_vrm-1.0/expressions.md)
+
+use crate::system_set::VrmSystemSets;
+use crate::vrm::expressions::{
+    ExpressionOverride, RegisteredExpression, VrmExpressionPreset, VrmExpressionRegistry,
+    VrmExpressionWeights,
+};
+use crate::vrm::VrmExpression;
+use bevy::app::{App, Update};
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+pub(super) struct ExpressionMixerPlugin;
+
+impl Plugin for ExpressionMixerPlugin {
+    fn build(
+        &self,
+        app: &mut App,
+    ) {
+        app.register_type::<ProceduralExpressionWeights>()
+            .register_type::<MixedExpressionWeights>()
+            .add_systems(Update, mix_expressions.after(VrmSystemSets::Retarget));
+    }
+}
+
+/// The expression weights requested by the built-in behaviours, such as blinking and lip sync.
+///
+/// These are kept apart from [`VrmExpressionWeights`] so that they never overwrite the user's weights;
+/// the larger of the two is used.
+#[derive(Component, Debug, Default, Clone, PartialEq, Deref, DerefMut, Reflect)]
+#[reflect(Component, Default)]
+pub(crate) struct ProceduralExpressionWeights(HashMap<VrmExpression, f32>);
+
+/// The expression weights after applying `isBinary` and the override rules.
+///
+/// The morph targets and the material binds are written from these weights.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component, Default)]
+pub(crate) struct MixedExpressionWeights {
+    pub weights: HashMap<VrmExpression, f32>,
+    /// The multiplier applied to the blink expressions.
+    pub blink: f32,
+    /// The multiplier applied to the look at expressions and the eye bones.
+    pub look_at: f32,
+    /// The multiplier applied to the mouth expressions.
+    pub mouth: f32,
+}
+
+impl Default for MixedExpressionWeights {
+    fn default() -> Self {
+        Self {
+            weights: HashMap::default(),
+            blink: 1.0,
+            look_at: 1.0,
+            mouth: 1.0,
+        }
+    }
+}
+
+impl MixedExpressionWeights {
+    /// Returns the mixed weight of the expression.
+    pub fn get(
+        &self,
+        expression: &VrmExpression,
+    ) -> f32 {
+        self.weights.get(expression).copied().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ExpressionKind {
+    Blink,
+    LookAt,
+    Mouth,
+    Other,
+}
+
+impl ExpressionKind {
+    fn of(expression: &VrmExpression) -> Self {
+        match expression.preset() {
+            Some(
+                VrmExpressionPreset::Blink
+                | VrmExpressionPreset::BlinkLeft
+                | VrmExpressionPreset::BlinkRight,
+            ) => Self::Blink,
+            Some(
+                VrmExpressionPreset::LookUp
+                | VrmExpressionPreset::LookDown
+                | VrmExpressionPreset::LookLeft
+                | VrmExpressionPreset::LookRight,
+            ) => Self::LookAt,
+            Some(
+                VrmExpressionPreset::Aa
+                | VrmExpressionPreset::Ih
+                | VrmExpressionPreset::Ou
+                | VrmExpressionPreset::Ee
+                | VrmExpressionPreset::Oh,
+            ) => Self::Mouth,
+            _ => Self::Other,
+        }
+    }
+}
+
+pub(crate) fn mix_expressions(
+    mut vrms: Query<
+        (
+            &VrmExpressionRegistry,
+            &VrmExpressionWeights,
+            Option<&ProceduralExpressionWeights>,
+            &mut MixedExpressionWeights,
+        ),
+        Or<(
+            Changed<VrmExpressionRegistry>,
+            Changed<VrmExpressionWeights>,
+            Changed<ProceduralExpressionWeights>,
+        )>,
+    >
+) {
+    for (registry, weights, procedural, mut mixed) in vrms.iter_mut() {
+        mixed.set_if_neq(mix(registry, |expression| {
+            requested_weight(weights, procedural, expression)
+        }));
+    }
+}
+
+/// Returns the weight requested for the expression before `isBinary` and the override rules are applied.
+///
+/// This is the larger of the user's weight and the procedural weight.
+pub(crate) fn requested_weight(
+    weights: &VrmExpressionWeights,
+    procedural: Option<&ProceduralExpressionWeights>,
+    expression: &VrmExpression,
+) -> f32 {
+    let procedural = procedural
+        .and_then(|p| p.get(expression))
+        .copied()
+        .unwrap_or_default();
+    weights.get(expression.clone()).max(procedural)
+}
+
+fn mix(
+    registry: &VrmExpressionRegistry,
+    requested: impl Fn(&VrmExpression) -> f32,
+) -> MixedExpressionWeights {
+    let mut weights = registry
+        .iter()
+        .map(|(expression, registered)| {
+            let weight = requested(expression);
+            let weight = if registered.is_binary {
+                if weight > 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            } else {
+                weight
+            };
+            (expression.clone(), weight)
+        })
+        .collect::<HashMap<_, _>>();
+
+    let multiplier = |kind: ExpressionKind,
+                      rule: fn(&RegisteredExpression) -> ExpressionOverride| {
+        let rate = registry
+            .iter()
+            .filter(|(expression, _)| ExpressionKind::of(expression) != kind)
+            .map(|(expression, registered)| {
+                let weight = weights.get(expression).copied().unwrap_or_default();
+                match rule(registered) {
+                    ExpressionOverride::Block if 0.0 < weight => 1.0,
+                    ExpressionOverride::Blend => weight,
+                    _ => 0.0,
+                }
+            })
+            .fold(0.0_f32, f32::max);
+        1.0 - rate
+    };
+    let blink = multiplier(ExpressionKind::Blink, |e| e.override_blink);
+    let look_at = multiplier(ExpressionKind::LookAt, |e| e.override_look_at);
+    let mouth = multiplier(ExpressionKind::Mouth, |e| e.override_mouth);
+
+    for (expression, weight) in weights.iter_mut() {
+        *weight *= match ExpressionKind::of(expression) {
+            ExpressionKind::Blink => blink,
+            ExpressionKind::LookAt => look_at,
+            ExpressionKind::Mouth => mouth,
+            ExpressionKind::Other => 1.0,
+        };
+    }
+    MixedExpressionWeights {
+        weights,
+        blink,
+        look_at,
+        mouth,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vrm::expressions::mixer::mix;
+    use crate::vrm::expressions::{
+        ExpressionOverride, RegisteredExpression, VrmExpressionRegistry,
+    };
+    use crate::vrm::VrmExpression;
+    use bevy::platform::collections::HashMap;
+
+    fn registry(happy: RegisteredExpression) -> VrmExpressionRegistry {
+        VrmExpressionRegistry(HashMap::from_iter([
+            (VrmExpression::from("happy"), happy),
+            (
+                VrmExpression::from("blink"),
+                RegisteredExpression::default(),
+            ),
+            (VrmExpression::from("aa"), RegisteredExpression::default()),
+        ]))
+    }
+
+    fn requested(expression: &VrmExpression) -> f32 {
+        match expression.as_str() {
+            "happy" => 0.4,
+            _ => 1.0,
+        }
+    }
+
+    #[test]
+    fn block_blink_while_active() {
+        let registry = registry(RegisteredExpression {
+            override_blink: ExpressionOverride::Block,
+            ..Default::default()
+        });
+        let mixed = mix(&registry, requested);
+        assert_eq!(mixed.get(&VrmExpression::from("happy")), 0.4);
+        assert_eq!(mixed.get(&VrmExpression::from("blink")), 0.0);
+        assert_eq!(mixed.get(&VrmExpression::from("aa")), 1.0);
+        assert_eq!(mixed.look_at, 1.0);
+    }
+
+    #[test]
+    fn blend_mouth_by_weight() {
+        let registry = registry(RegisteredExpression {
+            override_mouth: ExpressionOverride::Blend,
+            ..Default::default()
+        });
+        let mixed = mix(&registry, requested);
+        assert_eq!(mixed.get(&VrmExpression::from("blink")), 1.0);
+        assert_eq!(mixed.get(&VrmExpression::from("aa")), 0.6);
+        assert_eq!(mixed.mouth, 0.6);
+    }
+
+    #[test]
+    fn binary_weight_and_override() {
+        let registry = registry(RegisteredExpression {
+            is_binary: true,
+            override_blink: ExpressionOverride::Blend,
+            ..Default::default()
+        });
+        let mixed = mix(&registry, requested);
+        assert_eq!(mixed.get(&VrmExpression::from("happy")), 0.0);
+        assert_eq!(mixed.get(&VrmExpression::from("blink")), 1.0);
+    }
+}
@@ -0,0 +1,437 @@
+//! Applies the `firstPerson` mesh annotations of VRM to the render layers of meshes.
+//!
+//! - [`first person specification(en)`](https://github.com/vrm-c/vrm-specification/blob/master/specification/VRMC_vrm-1.0/firstPerson.md)
+//! - [`first person specification(ja)`](https://github.com/vrm-c/vrm-specification/blob/master/specification/VRMC_vrm-1.0/firstPerson.ja.md)
+
+use crate::prelude::*;
+use crate::vrm::gltf::extensions::VrmExtensions;
+use crate::vrm::humanoid_bone::HumanoidBonesAttached;
+use bevy::app::{App, Plugin, Update};
+use bevy::asset::{Assets, Handle};
+use bevy::gltf::GltfNode;
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use bevy::render::mesh::morph::MeshMorphWeights;
+use bevy::render::mesh::skinning::SkinnedMesh;
+use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::render::view::RenderLayers;
+
+pub mod prelude {
+    pub use crate::vrm::first_person::{FirstPersonLayers, FirstPersonType};
+}
+
+/// The render layers used to route the meshes of VRM to first-person and third-person cameras.
+///
+/// Insert this component into the root entity of the VRM, and give the first-person camera
+/// [`FirstPersonLayers::first_person`] and the other cameras [`FirstPersonLayers::third_person`].
+/// The meshes are assigned according to the `firstPerson` mesh annotations of VRM.
+///
+/// | annotation        | render layers                   |
+/// |-------------------|---------------------------------|
+/// | `both`            | `first_person` + `third_person` |
+/// | `firstPersonOnly` | `first_person`                  |
+/// | `thirdPersonOnly` | `third_person`                  |
+/// | `auto`            | see [`FirstPersonType::Auto`]   |
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy::render::view::RenderLayers;
+/// use bevy_vrm1::prelude::*;
+///
+/// fn spawn_vrm(
+///     mut commands: Commands,
+///     asset_server: Res<AssetServer>,
+/// ) {
+///     commands.spawn((Camera3d::default(), RenderLayers::layer(1)));
+///     commands.spawn((
+///         VrmHandle(asset_server.load("model.vrm")),
+///         FirstPersonLayers {
+///             first_person: RenderLayers::layer(1),
+///             third_person: RenderLayers::layer(2),
+///         },
+///     ));
+/// }
+/// ```
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct FirstPersonLayers {
+    /// The layers of the meshes visible from the first-person camera.
+    pub first_person: RenderLayers,
+    /// The layers of the meshes visible from the third-person cameras.
+    pub third_person: RenderLayers,
+}
+
+/// The type of the mesh annotation obtained from `VRMC_vrm::firstPerson`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub enum FirstPersonType {
+    /// If the mesh is skinned, the triangles weighted to the head bone or its descendants are
+    /// removed from a copy of the mesh that is only visible in first-person,
+    /// and the original mesh is only visible in third-person.
+    ///
+    /// If the mesh is not skinned, it's treated as [`FirstPersonType::ThirdPersonOnly`]
+    /// when it is a descendant of the head bone, otherwise [`FirstPersonType::Both`].
+    ///
+    /// Meshes that are not annotated are also treated as this.
+    #[default]
+    Auto,
+    /// The mesh is visible from both first-person and third-person.
+    Both,
+    /// The mesh is only visible from third-person.
+    ThirdPersonOnly,
+    /// The mesh is only visible from first-person.
+    FirstPersonOnly,
+}
+
+impl From<&str> for FirstPersonType {
+    fn from(value: &str) -> Self {
+        match value {
+            "both" => Self::Both,
+            "thirdPersonOnly" => Self::ThirdPersonOnly,
+            "firstPersonOnly" => Self::FirstPersonOnly,
+            _ => Self::Auto,
+        }
+    }
+}
+
+/// The mesh annotations keyed by the name of the annotated node.
+#[derive(Component, Deref, Reflect, Default)]
+pub(crate) struct FirstPersonRegistry(HashMap<Name, FirstPersonType>);
+
+impl FirstPersonRegistry {
+    pub fn new(
+        extensions: &VrmExtensions,
+        node_assets: &Assets<GltfNode>,
+        nodes: &[Handle<GltfNode>],
+    ) -> Self {
+        let Some(first_person) = extensions.vrmc_vrm.first_person.as_ref() else {
+            return Self::default();
+        };
+        Self(
+            first_person
+                .mesh_annotations
+                .iter()
+                .filter_map(|annotation| {
+                    let node = node_assets.get(nodes.get(annotation.node)?)?;
+                    Some((
+                        Name::new(node.name.clone()),
+                        FirstPersonType::from(annotation.r#type.as_str()),
+                    ))
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Holds the entity of the headless copy created from the mesh annotated with [`FirstPersonType::Auto`].
+#[derive(Component, Debug, Copy, Clone, Reflect)]
+#[reflect(Component)]
+struct HeadlessMesh(Entity);
+
+/// A marker component attached to the headless copy of the mesh.
+#[derive(Component, Debug, Copy, Clone, Reflect)]
+#[reflect(Component)]
+struct Headless;
+
+/// A marker component indicating that the [`FirstPersonLayers`] has been applied to the meshes.
+#[derive(Component, Debug, Copy, Clone, Reflect)]
+#[reflect(Component)]
+pub(crate) struct FirstPersonLayersApplied;
+
+pub(super) struct FirstPersonPlugin;
+
+impl Plugin for FirstPersonPlugin {
+    fn build(
+        &self,
+        app: &mut App,
+    ) {
+        app.register_type::<FirstPersonLayers>()
+            .register_type::<FirstPersonType>()
+            .register_type::<FirstPersonRegistry>()
+            .register_type::<HeadlessMesh>()
+            .register_type::<Headless>()
+            .register_type::<FirstPersonLayersApplied>()
+            .add_systems(Update, apply_first_person_layers);
+    }
+}
+
+fn apply_first_person_layers(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    vrms: Query<
+        (
+            Entity,
+            &FirstPersonRegistry,
+            &FirstPersonLayers,
+            &HeadBoneEntity,
+        ),
+        (
+            With<HumanoidBonesAttached>,
+            Or<(
+                Changed<FirstPersonLayers>,
+                Without<FirstPersonLayersApplied>,
+            )>,
+        ),
+    >,
+    children: Query<&Children>,
+    parents: Query<&ChildOf>,
+    names: Query<&Name>,
+    primitives: Query<
+        (
+            &Mesh3d,
+            &Transform,
+            Option<&SkinnedMesh>,
+            Option<&HeadlessMesh>,
+            Option<&MeshMorphWeights>,
+            Option<&MeshMaterial3d<StandardMaterial>>,
+            Option<&MeshMaterial3d<MToonMaterial>>,
+        ),
+        Without<Headless>,
+    >,
+) {
+    for (vrm_entity, registry, layers, head) in vrms.iter() {
+        let head_bones = children
+            .iter_descendants(head.0)
+            .chain([head.0])
+            .collect::<HashSet<_>>();
+        let targets = children
+            .iter_descendants(vrm_entity)
+            .filter(|entity| primitives.contains(*entity))
+            .collect::<Vec<_>>();
+        if targets.iter().any(|entity| {
+            primitives
+                .get(*entity)
+                .is_ok_and(|(mesh, ..)| !meshes.contains(mesh.id()))
+        }) {
+            continue;
+        }
+
+        for entity in targets {
+            let Ok((mesh, transform, skinned, headless, morph_weights, standard, mtoon)) =
+                primitives.get(entity)
+            else {
+                continue;
+            };
+            let Ok(ChildOf(node)) = parents.get(entity) else {
+                continue;
+            };
+            let ty = names
+                .get(*node)
+                .ok()
+                .and_then(|name| registry.0.get(name).copied())
+                .unwrap_or_default();
+            let render_layers = match ty {
+                FirstPersonType::Both => layers.first_person.union(&layers.third_person),
+                FirstPersonType::ThirdPersonOnly => layers.third_person.clone(),
+                FirstPersonType::FirstPersonOnly => layers.first_person.clone(),
+                FirstPersonType::Auto => {
+                    let Some(skinned) = skinned else {
+                        let render_layers = if head_bones.contains(node) {
+                            layers.third_person.clone()
+                        } else {
+                            layers.first_person.union(&layers.third_person)
+                        };
+                        commands.entity(entity).insert(render_layers);
+                        continue;
+                    };
+                    if let Some(HeadlessMesh(headless)) = headless {
+                        commands
+                            .entity(*headless)
+                            .insert(layers.first_person.clone());
+                        layers.third_person.clone()
+                    } else {
+                        let Some(source) = meshes.get(mesh.id()) else {
+                            continue;
+                        };
+                        match erase_head(source, skinned, &head_bones) {
+                            Erased::Nothing => layers.first_person.union(&layers.third_person),
+                            Erased::All => layers.third_person.clone(),
+                            Erased::Partially(headless_mesh) => {
+                                let name = names
+                                    .get(entity)
+                                    .map(|name| format!("{name}.Headless"))
+                                    .unwrap_or_else(|_| "Headless".to_string());
+                                let mut cmd = commands.spawn((
+                                    Name::new(name),
+                                    Headless,
+                                    Mesh3d(meshes.add(headless_mesh)),
+                                    *transform,
+                                    skinned.clone(),
+                                    layers.first_person.clone(),
+                                    ChildOf(*node),
+                                ));
+                                if let Some(morph_weights) = morph_weights {
+                                    cmd.insert(morph_weights.clone());
+                                }
+                                if let Some(material) = mtoon {
+                                    cmd.insert(material.clone());
+                                } else if let Some(material) = standard {
+                                    cmd.insert(material.clone());
+                                }
+                                let headless = cmd.id();
+                                commands.entity(entity).insert(HeadlessMesh(headless));
+                                layers.third_person.clone()
+                            }
+                        }
+                    }
+                }
+            };
+            commands.entity(entity).insert(render_layers);
+        }
+        commands.entity(vrm_entity).insert(FirstPersonLayersApplied);
+    }
+}
+
+enum Erased {
+    Nothing,
+    All,
+    Partially(Mesh),
+}
+
+/// Removes the triangles that contain a vertex weighted to the head bones, the same as `UniVRM`.
+fn erase_head(
+    mesh: &Mesh,
+    skinned: &SkinnedMesh,
+    head_bones: &HashSet<Entity>,
+) -> Erased {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return Erased::Nothing;
+    }
+    let head_joints = skinned
+        .joints
+        .iter()
+        .enumerate()
+        .filter(|(_, joint)| head_bones.contains(*joint))
+        .map(|(i, _)| i)
+        .collect::<HashSet<_>>();
+    if head_joints.is_empty() {
+        return Erased::Nothing;
+    }
+    let (
+        Some(VertexAttributeValues::Uint16x4(joint_indices)),
+        Some(VertexAttributeValues::Float32x4(joint_weights)),
+    ) = (
+        mesh.attribute(Mesh::ATTRIBUTE_JOINT_INDEX),
+        mesh.attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT),
+    )
+    else {
+        return Erased::Nothing;
+    };
+    let is_head_vertex = |vertex: usize| {
+        let (Some(indices), Some(weights)) = (joint_indices.get(vertex), joint_weights.get(vertex))
+        else {
+            return false;
+        };
+        indices
+            .iter()
+            .zip(weights)
+            .any(|(joint, weight)| 0. < *weight && head_joints.contains(&(*joint as usize)))
+    };
+    let indices = match mesh.indices() {
+        Some(indices) => indices.iter().collect::<Vec<_>>(),
+        None => (0..mesh.count_vertices()).collect(),
+    };
+    let remaining = indices
+        .chunks_exact(3)
+        .filter(|triangle| !triangle.iter().any(|vertex| is_head_vertex(*vertex)))
+        .flatten()
+        .map(|vertex| *vertex as u32)
+        .collect::<Vec<_>>();
+    if remaining.len() == indices.len() {
+        Erased::Nothing
+    } else if remaining.is_empty() {
+        Erased::All
+    } else {
+        let mut headless = mesh.clone();
+        headless.insert_indices(Indices::U32(remaining));
+        Erased::Partially(headless)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::success;
+    use crate::tests::TestResult;
+    use crate::vrm::first_person::{erase_head, Erased, FirstPersonType};
+    use bevy::asset::RenderAssetUsages;
+    use bevy::platform::collections::HashSet;
+    use bevy::prelude::*;
+    use bevy::render::mesh::skinning::SkinnedMesh;
+    use bevy::render::mesh::{Indices, PrimitiveTopology};
+
+    fn skinned_quad(head_weight: f32) -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0., 0., 0.]; 4])
+        .with_inserted_attribute(Mesh::ATTRIBUTE_JOINT_INDEX, {
+            let indices: Vec<[u16; 4]> = vec![[0, 1, 0, 0]; 4];
+            bevy::render::mesh::VertexAttributeValues::Uint16x4(indices)
+        })
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_JOINT_WEIGHT,
+            vec![
+                [1., 0., 0., 0.],
+                [1., 0., 0., 0.],
+                [1., 0., 0., 0.],
+                [1. - head_weight, head_weight, 0., 0.],
+            ],
+        )
+        .with_inserted_indices(Indices::U16(vec![0, 1, 2, 1, 2, 3]))
+    }
+
+    fn skinned_mesh(joints: Vec<Entity>) -> SkinnedMesh {
+        SkinnedMesh {
+            inverse_bindposes: Handle::default(),
+            joints,
+        }
+    }
+
+    #[test]
+    fn parse_first_person_type() {
+        assert_eq!(FirstPersonType::from("both"), FirstPersonType::Both);
+        assert_eq!(
+            FirstPersonType::from("thirdPersonOnly"),
+            FirstPersonType::ThirdPersonOnly
+        );
+        assert_eq!(
+            FirstPersonType::from("firstPersonOnly"),
+            FirstPersonType::FirstPersonOnly
+        );
+        assert_eq!(FirstPersonType::from("auto"), FirstPersonType::Auto);
+    }
+
+    #[test]
+    fn erase_head_weighted_triangles() -> TestResult {
+        let body = Entity::from_raw(1);
+        let head = Entity::from_raw(2);
+        let head_bones = HashSet::from([head]);
+        let skinned = skinned_mesh(vec![body, head]);
+
+        let Erased::Partially(headless) = erase_head(&skinned_quad(0.1), &skinned, &head_bones)
+        else {
+            panic!("expected the mesh to be partially erased");
+        };
+        assert_eq!(
+            headless.indices().unwrap().iter().collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert!(matches!(
+            erase_head(&skinned_quad(0.), &skinned, &head_bones),
+            Erased::Nothing
+        ));
+        success!()
+    }
+
+    #[test]
+    fn erase_all_triangles_weighted_to_head() {
+        let head = Entity::from_raw(2);
+        let skinned = skinned_mesh(vec![head, head]);
+        assert!(matches!(
+            erase_head(&skinned_quad(0.), &skinned, &HashSet::from([head])),
+            Erased::All
+        ));
+    }
+}
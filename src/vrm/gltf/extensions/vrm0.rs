@@ -0,0 +1,1268 @@
+//! Converts the legacy `VRM` (0.x) glTF extension into the VRM 1.0 structures.
+//!
+//! VRM 0.x avatars face `-Z` and store the spring bone and first person values in Unity coordinates,
+//! so the conversion also rotates the nodes, meshes and skins 180 degrees around the Y axis like `UniVRM` does,
+//! and flips the stored vectors.
+//!
+//! - [`VRM 0.x specification`](https://github.com/vrm-c/vrm-specification/tree/master/specification/0.0)
+//! - [`Migration to VRM 1.0`](https://github.com/vrm-c/vrm-specification/blob/master/specification/VRMC_vrm-1.0/README.md)
+
+use crate::error::AppResult;
+use crate::glb::Glb;
+use crate::vrm::gltf::extensions::vrmc_spring_bone::{
+    Collider, ColliderGroup, ColliderShape, Sphere, Spring, SpringJoint, VRMCSpringBone,
+};
+use crate::vrm::gltf::extensions::vrmc_vrm::{
+    Expressions, FirstPerson, Humanoid, LookAtProperties, LookAtType, MaterialColorBind,
+    MaterialColorType, MeshAnnotation, Meta, MorphTargetBind, RangeMap, TextureTransformBind,
+    VrmPreset, VrmcVrm,
+};
+use crate::vrm::gltf::extensions::VrmNode;
+use crate::vrm::gltf::materials::{
+    KhrTextureTransform, MatcapTexture, OutlineWidthMultiplyTexture, RimMultiplyTexture,
+    UVAnimationMaskTexture, VrmTexture, VrmTextureExtensions, VrmcMaterialsExtensitions,
+};
+use anyhow::{bail, Context};
+use bevy::math::{Mat4, Quat, Vec3};
+use bevy::platform::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::f32::consts::TAU;
+
+/// The length of the virtual tail that `UniVRM` appends to the leaf of each spring chain.
+const TAIL_LENGTH: f32 = 0.07;
+
+/// The `componentType` of `FLOAT`.
+const COMPONENT_FLOAT: u64 = 5126;
+
+/// The components of a vector negated by the rotation around the Y axis.
+const VECTOR_FLIPS: [usize; 2] = [0, 2];
+
+/// The elements of a column-major matrix negated by the rotation around the Y axis on both sides.
+const MATRIX_FLIPS: [usize; 8] = [1, 3, 4, 6, 9, 11, 12, 14];
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Vrm0 {
+    pub spec_version: Option<String>,
+    pub meta: Option<Vrm0Meta>,
+    pub humanoid: Vrm0Humanoid,
+    pub first_person: Option<Vrm0FirstPerson>,
+    pub blend_shape_master: Option<Vrm0BlendShapeMaster>,
+    pub secondary_animation: Option<Vrm0SecondaryAnimation>,
+    pub material_properties: Vec<Vrm0MaterialProperties>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Vrm0Meta {
+    pub title: Option<String>,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    pub contact_information: Option<String>,
+    pub reference: Option<String>,
+    /// The index of the thumbnail texture.
+    pub texture: Option<usize>,
+    pub allowed_user_name: Option<String>,
+    pub violent_ussage_name: Option<String>,
+    pub sexual_ussage_name: Option<String>,
+    pub commercial_ussage_name: Option<String>,
+    pub other_permission_url: Option<String>,
+    pub license_name: Option<String>,
+    pub other_license_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Vrm0Humanoid {
+    pub human_bones: Vec<Vrm0HumanBone>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct Vrm0HumanBone {
+    pub bone: String,
+    pub node: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone)]
+#[serde(default)]
+pub(crate) struct Vrm0Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Vrm0FirstPerson {
+    pub first_person_bone_offset: Option<Vrm0Vec3>,
+    pub mesh_annotations: Vec<Vrm0MeshAnnotation>,
+    pub look_at_type_name: Option<String>,
+    pub look_at_horizontal_inner: Option<Vrm0DegreeMap>,
+    pub look_at_horizontal_outer: Option<Vrm0DegreeMap>,
+    pub look_at_vertical_down: Option<Vrm0DegreeMap>,
+    pub look_at_vertical_up: Option<Vrm0DegreeMap>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Vrm0MeshAnnotation {
+    pub mesh: usize,
+    pub first_person_flag: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Vrm0DegreeMap {
+    pub x_range: f32,
+    pub y_range: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Vrm0BlendShapeMaster {
+    pub blend_shape_groups: Vec<Vrm0BlendShapeGroup>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Vrm0BlendShapeGroup {
+    pub name: String,
+    pub preset_name: String,
+    pub binds: Vec<Vrm0BlendShapeBind>,
+    pub material_values: Vec<Vrm0MaterialValue>,
+    pub is_binary: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Vrm0MaterialValue {
+    pub material_name: String,
+    /// The Unity shader property such as `_Color` or `_MainTex_ST`.
+    pub property_name: String,
+    pub target_value: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct Vrm0BlendShapeBind {
+    /// The index of the mesh, not the node.
+    pub mesh: usize,
+    pub index: usize,
+    /// The weight in the range of `0..=100`.
+    pub weight: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Vrm0SecondaryAnimation {
+    pub bone_groups: Vec<Vrm0BoneGroup>,
+    pub collider_groups: Vec<Vrm0ColliderGroup>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Vrm0BoneGroup {
+    pub comment: Option<String>,
+    /// The name is misspelled in the specification.
+    pub stiffiness: f32,
+    pub gravity_power: f32,
+    pub gravity_dir: Vrm0Vec3,
+    pub drag_force: f32,
+    /// `-1` if the group has no center node.
+    pub center: Option<i64>,
+    pub hit_radius: f32,
+    /// The root nodes of the spring chains.
+    pub bones: Vec<usize>,
+    pub collider_groups: Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub(crate) struct Vrm0ColliderGroup {
+    pub node: usize,
+    pub colliders: Vec<Vrm0Collider>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub(crate) struct Vrm0Collider {
+    pub offset: Vrm0Vec3,
+    pub radius: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Vrm0MaterialProperties {
+    pub name: String,
+    pub shader: String,
+    pub render_queue: Option<i32>,
+    pub float_properties: HashMap<String, f32>,
+    pub vector_properties: HashMap<String, Vec<f32>>,
+    pub texture_properties: HashMap<String, usize>,
+}
+
+/// Returns `true` if the document only has the legacy `VRM` extension.
+pub(crate) fn is_vrm0(root: &Value) -> bool {
+    let Some(extensions) = root.get("extensions").and_then(Value::as_object) else {
+        return false;
+    };
+    extensions.contains_key("VRM") && !extensions.contains_key("VRMC_vrm")
+}
+
+/// Converts the legacy `VRM` extension of the glTF document into
+/// `VRMC_vrm`, `VRMC_springBone` and `VRMC_materials_mtoon` in place.
+pub(crate) fn convert_document(glb: &mut Glb) -> AppResult {
+    let root = &mut glb.json;
+    let vrm0: Vrm0 = serde_json::from_value(root["extensions"]["VRM"].clone())?;
+
+    let vrmc_vrm = vrm0.to_vrmc_vrm(root);
+    let spring_bone = vrm0
+        .secondary_animation
+        .as_ref()
+        .map(|secondary| secondary.to_spring_bone(root));
+    let materials = vrm0
+        .material_properties
+        .iter()
+        .enumerate()
+        .filter_map(|(i, properties)| Some((i, properties.to_mtoon()?)))
+        .collect::<Vec<_>>();
+
+    let extensions = root["extensions"]
+        .as_object_mut()
+        .expect("checked by is_vrm0");
+    extensions.insert("VRMC_vrm".to_string(), serde_json::to_value(vrmc_vrm)?);
+    if let Some(spring_bone) = spring_bone {
+        extensions.insert(
+            "VRMC_springBone".to_string(),
+            serde_json::to_value(spring_bone)?,
+        );
+    }
+    for (i, mtoon) in materials {
+        let Some(material) = root["materials"].get_mut(i) else {
+            continue;
+        };
+        material["extensions"]["VRMC_materials_mtoon"] = serde_json::to_value(mtoon)?;
+    }
+    rotate_y180(glb)
+}
+
+impl Vrm0 {
+    pub fn to_vrmc_vrm(
+        &self,
+        root: &Value,
+    ) -> VrmcVrm {
+        VrmcVrm {
+            expressions: self
+                .blend_shape_master
+                .as_ref()
+                .map(|master| master.to_expressions(root)),
+            first_person: self.first_person.as_ref().map(|first_person| FirstPerson {
+                mesh_annotations: first_person
+                    .mesh_annotations
+                    .iter()
+                    .flat_map(|annotation| {
+                        let r#type = match annotation.first_person_flag.as_str() {
+                            "FirstPersonOnly" => "firstPersonOnly",
+                            "ThirdPersonOnly" => "thirdPersonOnly",
+                            "Both" => "both",
+                            _ => "auto",
+                        };
+                        nodes_with_mesh(root, annotation.mesh)
+                            .into_iter()
+                            .map(move |node| MeshAnnotation {
+                                node,
+                                r#type: r#type.to_string(),
+                            })
+                    })
+                    .collect(),
+            }),
+            humanoid: Humanoid {
+                human_bones: self
+                    .humanoid
+                    .human_bones
+                    .iter()
+                    .map(|bone| {
+                        (
+                            convert_bone_name(&bone.bone).to_string(),
+                            VrmNode { node: bone.node },
+                        )
+                    })
+                    .collect(),
+            },
+            look_at: self.first_person.as_ref().map(Vrm0FirstPerson::to_look_at),
+            meta: self.meta.as_ref().map(|meta| meta.to_meta(root)),
+            spec_version: "1.0".to_string(),
+        }
+    }
+}
+
+impl Vrm0Meta {
+    fn to_meta(
+        &self,
+        root: &Value,
+    ) -> Meta {
+        let license = self.license_name.as_deref().unwrap_or_default();
+        let allow = |usage: &Option<String>| usage.as_deref() == Some("Allow");
+        Meta {
+            allow_antisocial_or_hate_usage: false,
+            allow_excessively_sexual_usage: allow(&self.sexual_ussage_name),
+            allow_excessively_violent_usage: allow(&self.violent_ussage_name),
+            allow_political_or_religious_usage: false,
+            allow_redistribution: license != "Redistribution_Prohibited",
+            authors: self.author.iter().cloned().collect(),
+            avatar_permission: self.allowed_user_name.as_deref().map(|name| {
+                match name {
+                    "Everyone" => "everyone",
+                    "ExplicitlyLicensedPerson" => "onlySeparatelyLicensedPerson",
+                    _ => "onlyAuthor",
+                }
+                .to_string()
+            }),
+            commercial_usage: Some(
+                if allow(&self.commercial_ussage_name) {
+                    "personalProfit"
+                } else {
+                    "personalNonProfit"
+                }
+                .to_string(),
+            ),
+            contact_information: self.contact_information.clone(),
+            copyright_information: None,
+            credit_notation: Some(
+                if license.starts_with("CC_BY") {
+                    "required"
+                } else {
+                    "unnecessary"
+                }
+                .to_string(),
+            ),
+            license_url: Some("https://vrm.dev/licenses/1.0/".to_string()),
+            modification: Some(
+                if license.contains("ND") {
+                    "prohibited"
+                } else {
+                    "allowModificationRedistribution"
+                }
+                .to_string(),
+            ),
+            name: self.title.clone(),
+            other_license_url: self
+                .other_license_url
+                .clone()
+                .or_else(|| self.other_permission_url.clone())
+                .filter(|url| !url.is_empty()),
+            references: self.reference.iter().cloned().collect(),
+            third_party_licenses: None,
+            thumbnail_image: self
+                .texture
+                .and_then(|texture| texture_source(root, texture))
+                .map(|image| image as i64),
+            version: self.version.clone(),
+        }
+    }
+}
+
+impl Vrm0FirstPerson {
+    fn to_look_at(&self) -> LookAtProperties {
+        let (r#type, default_output) = match self.look_at_type_name.as_deref() {
+            Some("BlendShape") => (LookAtType::Expression, 1.0),
+            _ => (LookAtType::Bone, 10.0),
+        };
+        let range_map = |map: Option<Vrm0DegreeMap>| {
+            map.map(|map| RangeMap {
+                input_max_value: map.x_range,
+                output_scale: map.y_range,
+            })
+            .unwrap_or(RangeMap {
+                input_max_value: 90.0,
+                output_scale: default_output,
+            })
+        };
+        let offset = self.first_person_bone_offset.unwrap_or_default();
+        LookAtProperties {
+            offset_from_head_bone: [-offset.x, offset.y, offset.z],
+            range_map_horizontal_inner: range_map(self.look_at_horizontal_inner),
+            range_map_horizontal_outer: range_map(self.look_at_horizontal_outer),
+            range_map_vertical_down: range_map(self.look_at_vertical_down),
+            range_map_vertical_up: range_map(self.look_at_vertical_up),
+            r#type,
+        }
+    }
+}
+
+impl Vrm0BlendShapeMaster {
+    /// Converts the blend shape groups into expressions.
+    ///
+    /// Groups with the `unknown` preset become custom expressions named after the group.
+    fn to_expressions(
+        &self,
+        root: &Value,
+    ) -> Expressions {
+        let mut expressions = Expressions {
+            preset: HashMap::default(),
+            custom: HashMap::default(),
+        };
+        for group in self.blend_shape_groups.iter() {
+            let expression = group.to_expression(root);
+            match convert_preset_name(&group.preset_name) {
+                Some(preset) => {
+                    expressions.preset.insert(preset.to_string(), expression);
+                }
+                None if !group.name.is_empty() => {
+                    expressions.custom.insert(group.name.clone(), expression);
+                }
+                None => {}
+            }
+        }
+        expressions
+    }
+}
+
+impl Vrm0BlendShapeGroup {
+    fn to_expression(
+        &self,
+        root: &Value,
+    ) -> VrmPreset {
+        VrmPreset {
+            is_binary: self.is_binary,
+            morph_target_binds: Some(
+                self.binds
+                    .iter()
+                    .flat_map(|bind| {
+                        nodes_with_mesh(root, bind.mesh)
+                            .into_iter()
+                            .map(|node| MorphTargetBind {
+                                node,
+                                index: bind.index,
+                                weight: bind.weight / 100.0,
+                            })
+                    })
+                    .collect(),
+            ),
+            material_color_binds: Some(self.material_color_binds(root)),
+            texture_transform_binds: Some(self.texture_transform_binds(root)),
+            override_blink: "none".to_string(),
+            override_look_at: "none".to_string(),
+            override_mouth: "none".to_string(),
+        }
+    }
+
+    fn material_color_binds(
+        &self,
+        root: &Value,
+    ) -> Vec<MaterialColorBind> {
+        self.material_values
+            .iter()
+            .filter_map(|value| {
+                let r#type = match value.property_name.as_str() {
+                    "_Color" => MaterialColorType::Color,
+                    "_EmissionColor" => MaterialColorType::EmissionColor,
+                    "_ShadeColor" => MaterialColorType::ShadeColor,
+                    "_RimColor" => MaterialColorType::RimColor,
+                    "_OutlineColor" => MaterialColorType::OutlineColor,
+                    _ => return None,
+                };
+                let target = value.target_value.get(..4)?;
+                Some(MaterialColorBind {
+                    material: material_index(root, &value.material_name)?,
+                    r#type,
+                    target_value: [target[0], target[1], target[2], target[3]],
+                })
+            })
+            .collect()
+    }
+
+    fn texture_transform_binds(
+        &self,
+        root: &Value,
+    ) -> Vec<TextureTransformBind> {
+        self.material_values
+            .iter()
+            .filter(|value| value.property_name == "_MainTex_ST")
+            .filter_map(|value| {
+                let st = value.target_value.get(..4)?;
+                Some(TextureTransformBind {
+                    material: material_index(root, &value.material_name)?,
+                    scale: [st[0], st[1]],
+                    // Unity's UV origin is the bottom-left, while glTF's is the top-left.
+                    offset: [st[2], 1.0 - st[1] - st[3]],
+                })
+            })
+            .collect()
+    }
+}
+
+fn material_index(
+    root: &Value,
+    name: &str,
+) -> Option<usize> {
+    root["materials"]
+        .as_array()?
+        .iter()
+        .position(|material| material["name"].as_str() == Some(name))
+}
+
+impl Vrm0SecondaryAnimation {
+    /// Converts the bone groups into spring chains.
+    ///
+    /// VRM 0.x only lists the root of each chain, so the chains are built by walking down the node hierarchy.
+    /// Since VRM 1.0 needs an explicit tail joint, a virtual tail node is appended to each leaf like `UniVRM` does.
+    fn to_spring_bone(
+        &self,
+        root: &mut Value,
+    ) -> VRMCSpringBone {
+        let mut colliders = Vec::new();
+        let collider_groups = self
+            .collider_groups
+            .iter()
+            .map(|group| ColliderGroup {
+                name: node_name(root, group.node).unwrap_or_default(),
+                colliders: group
+                    .colliders
+                    .iter()
+                    .map(|collider| {
+                        colliders.push(Collider::new(
+                            group.node,
+                            ColliderShape::Sphere(Sphere {
+                                offset: [-collider.offset.x, collider.offset.y, collider.offset.z],
+                                radius: collider.radius,
+                            }),
+                        ));
+                        (colliders.len() - 1) as u64
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let mut visited = HashSet::new();
+        let mut springs = Vec::new();
+        for group in self.bone_groups.iter() {
+            let mut chains = Vec::new();
+            for bone in group.bones.iter() {
+                collect_chains(root, *bone, &mut visited, &mut chains);
+            }
+            for mut chain in chains {
+                let Some(leaf) = chain.last().copied() else {
+                    continue;
+                };
+                if let Some(tail) = append_tail_node(root, leaf) {
+                    chain.push(tail);
+                }
+                springs.push(Spring {
+                    name: group.comment.clone().unwrap_or_default(),
+                    joints: chain
+                        .into_iter()
+                        .map(|node| SpringJoint {
+                            node,
+                            drag_force: Some(group.drag_force),
+                            gravity_dir: Some([
+                                -group.gravity_dir.x,
+                                group.gravity_dir.y,
+                                group.gravity_dir.z,
+                            ]),
+                            gravity_power: Some(group.gravity_power),
+                            hit_radius: Some(group.hit_radius),
+                            stiffness: Some(group.stiffiness),
+                        })
+                        .collect(),
+                    collider_groups: Some(group.collider_groups.clone()),
+                    center: group.center.and_then(|center| usize::try_from(center).ok()),
+                });
+            }
+        }
+
+        VRMCSpringBone {
+            spec_version: "1.0".to_string(),
+            colliders,
+            collider_groups,
+            springs,
+        }
+    }
+}
+
+impl Vrm0MaterialProperties {
+    /// Converts the `VRM/MToon` properties into `VRMC_materials_mtoon`.
+    ///
+    /// Returns `None` for the other shaders such as `VRM/UnlitTexture`, so that they remain [`StandardMaterial`](bevy::prelude::StandardMaterial).
+    fn to_mtoon(&self) -> Option<VrmcMaterialsExtensitions> {
+        if self.shader != "VRM/MToon" {
+            return None;
+        }
+        let float =
+            |key: &str, default: f32| self.float_properties.get(key).copied().unwrap_or(default);
+        let color = |key: &str| {
+            self.vector_properties
+                .get(key)
+                .map(|c| {
+                    let channel = |i: usize| c.get(i).copied().unwrap_or_default();
+                    [channel(0), channel(1), channel(2)]
+                })
+                .unwrap_or_default()
+        };
+        let texture = |key: &str| self.texture_properties.get(key).copied();
+
+        let shade_shift = float("_ShadeShift", 0.0);
+        let shading_toony_factor = lerp(float("_ShadeToony", 0.9), 1.0, 0.5 + 0.5 * shade_shift);
+        let outline_width_mode = match float("_OutlineWidthMode", 0.0) as i32 {
+            1 => "worldCoordinates",
+            2 => "screenCoordinates",
+            _ => "none",
+        };
+        let blend_mode = float("_BlendMode", 0.0) as i32;
+        let render_queue_offset_number = match (blend_mode, self.render_queue) {
+            (2, Some(queue)) => (queue - 3000).clamp(-9, 0),
+            (3, Some(queue)) => (queue - 2501).clamp(0, 9),
+            _ => 0,
+        };
+        let matcap_texture = texture("_SphereAdd");
+
+        Some(VrmcMaterialsExtensitions {
+            spec_version: "1.0".to_string(),
+            matcap_factor: if matcap_texture.is_some() {
+                [1.0; 3]
+            } else {
+                [0.0; 3]
+            },
+            matcap_texture: matcap_texture.map(|index| MatcapTexture { index }),
+            parametric_rim_fresnel_power: float("_RimFresnelPower", 1.0),
+            rim_multiply_texture: texture("_RimTexture").map(|index| RimMultiplyTexture { index }),
+            outline_color_factor: color("_OutlineColor"),
+            outline_lighting_mix_factor: if float("_OutlineColorMode", 0.0) as i32 == 1 {
+                float("_OutlineLightingMix", 1.0)
+            } else {
+                0.0
+            },
+            // VRM 0.x stores the world width in centimeters.
+            outline_width_factor: Some(float("_OutlineWidth", 0.0) * 0.01),
+            outline_width_multiply_texture: texture("_OutlineWidthTexture")
+                .map(|index| OutlineWidthMultiplyTexture { index }),
+            outline_width_mode: outline_width_mode.to_string(),
+            parametric_rim_color_factor: color("_RimColor"),
+            parametric_rim_lift_factor: float("_RimLift", 0.0),
+            rim_lighting_mix_factor: float("_RimLightingMix", 0.0),
+            shade_color_factor: color("_ShadeColor"),
+            shade_multiply_texture: texture("_ShadeTexture").map(|index| VrmTexture {
+                extensions: VrmTextureExtensions {
+                    khr_texture_transform: KhrTextureTransform::default(),
+                },
+                index,
+            }),
+            render_queue_offset_number: render_queue_offset_number as f32,
+            shading_shift_factor: -shade_shift - (1.0 - shading_toony_factor),
+            shading_shift_texture: None,
+            shading_toony_factor,
+            transparent_with_z_write: blend_mode == 3,
+            uv_animation_mask_texture: texture("_UvAnimMaskTexture")
+                .map(|index| UVAnimationMaskTexture { index }),
+            uv_animation_rotation_speed_factor: float("_UvAnimRotation", 0.0) * TAU,
+            uv_animation_scroll_x_speed_factor: float("_UvAnimScrollX", 0.0),
+            uv_animation_scroll_y_speed_factor: -float("_UvAnimScrollY", 0.0),
+            gi_equalization_factor: 1.0 - float("_IndirectLightIntensity", 0.1),
+        })
+    }
+}
+
+/// Converts the humanoid bone name of VRM 0.x into VRM 1.0.
+///
+/// Only the thumb bones have been renamed.
+fn convert_bone_name(bone: &str) -> &str {
+    match bone {
+        "leftThumbProximal" => "leftThumbMetacarpal",
+        "leftThumbIntermediate" => "leftThumbProximal",
+        "rightThumbProximal" => "rightThumbMetacarpal",
+        "rightThumbIntermediate" => "rightThumbProximal",
+        _ => bone,
+    }
+}
+
+/// Converts the blend shape preset name of VRM 0.x into the expression preset name of VRM 1.0.
+///
+/// Returns `None` for `unknown`, which has no preset in VRM 1.0.
+fn convert_preset_name(preset: &str) -> Option<&'static str> {
+    Some(match preset.to_lowercase().as_str() {
+        "neutral" => "neutral",
+        "a" => "aa",
+        "i" => "ih",
+        "u" => "ou",
+        "e" => "ee",
+        "o" => "oh",
+        "blink" => "blink",
+        "blink_l" => "blinkLeft",
+        "blink_r" => "blinkRight",
+        "joy" => "happy",
+        "angry" => "angry",
+        "sorrow" => "sad",
+        "fun" => "relaxed",
+        "lookup" => "lookUp",
+        "lookdown" => "lookDown",
+        "lookleft" => "lookLeft",
+        "lookright" => "lookRight",
+        _ => return None,
+    })
+}
+
+/// Collects the chains starting from the `joint` node.
+///
+/// The first child continues the current chain, and the other children start new chains.
+fn collect_chains(
+    root: &Value,
+    joint: usize,
+    visited: &mut HashSet<usize>,
+    chains: &mut Vec<Vec<usize>>,
+) {
+    let mut chain = Vec::new();
+    let mut current = Some(joint);
+    while let Some(node) = current {
+        if !visited.insert(node) {
+            break;
+        }
+        chain.push(node);
+        let mut children = node_children(root, node).into_iter();
+        current = children.next();
+        for branch in children {
+            collect_chains(root, branch, visited, chains);
+        }
+    }
+    if !chain.is_empty() {
+        chains.push(chain);
+    }
+}
+
+fn append_tail_node(
+    root: &mut Value,
+    leaf: usize,
+) -> Option<usize> {
+    let (scale, rotation, translation) = local_transform(root["nodes"].get(leaf)?);
+    if translation.length_squared() <= f32::EPSILON {
+        return None;
+    }
+    let tail = rotation.inverse() * translation.normalize() * TAIL_LENGTH / scale;
+    let name = format!(
+        "{}_end",
+        node_name(root, leaf).unwrap_or_else(|| leaf.to_string())
+    );
+    let nodes = root["nodes"].as_array_mut()?;
+    nodes.push(json!({
+        "name": name,
+        "translation": [tail.x, tail.y, tail.z],
+    }));
+    let tail_index = nodes.len() - 1;
+    let leaf_node = nodes.get_mut(leaf)?.as_object_mut()?;
+    leaf_node
+        .entry("children")
+        .or_insert_with(|| json!([]))
+        .as_array_mut()?
+        .push(json!(tail_index));
+    Some(tail_index)
+}
+
+/// Rotates the nodes, meshes and skins 180 degrees around the Y axis, so that the avatar faces `+Z` like VRM 1.0.
+///
+/// Every node transform is conjugated by the rotation instead of being wrapped in a rotated node,
+/// so the global rotations of the bones in the rest pose stay the same as in VRM 1.0 avatars.
+/// The vertices, normals, tangents, morph targets and inverse bind matrices are rotated accordingly.
+fn rotate_y180(glb: &mut Glb) -> AppResult {
+    let root = &mut glb.json;
+    if let Some(nodes) = root["nodes"].as_array_mut() {
+        for node in nodes.iter_mut() {
+            rotate_node(node);
+        }
+    }
+
+    let mut vectors = BTreeSet::new();
+    let primitives = root["meshes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|mesh| mesh["primitives"].as_array().into_iter().flatten());
+    for primitive in primitives {
+        let targets = primitive["targets"].as_array().into_iter().flatten();
+        for attributes in std::iter::once(&primitive["attributes"]).chain(targets) {
+            for attribute in ["POSITION", "NORMAL", "TANGENT"] {
+                if let Some(accessor) = attributes[attribute].as_u64() {
+                    vectors.insert(accessor as usize);
+                }
+            }
+        }
+    }
+    let matrices = root["skins"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|skin| skin["inverseBindMatrices"].as_u64())
+        .map(|accessor| accessor as usize)
+        .collect::<BTreeSet<_>>();
+
+    for accessor in vectors {
+        negate_accessor(glb, accessor, &VECTOR_FLIPS)?;
+    }
+    for accessor in matrices {
+        negate_accessor(glb, accessor, &MATRIX_FLIPS)?;
+    }
+    Ok(())
+}
+
+fn rotate_node(node: &mut Value) {
+    for key in ["translation", "rotation"] {
+        negate_elements(&mut node[key], &VECTOR_FLIPS);
+    }
+    negate_elements(&mut node["matrix"], &MATRIX_FLIPS);
+}
+
+fn negate_elements(
+    array: &mut Value,
+    indices: &[usize],
+) {
+    let Some(array) = array.as_array_mut() else {
+        return;
+    };
+    for i in indices {
+        if let Some(v) = array.get_mut(*i).and_then(|v| v.as_f64()) {
+            array[*i] = json!(-v);
+        }
+    }
+}
+
+/// Negates the components of each element of the accessor, including its sparse values and bounds.
+fn negate_accessor(
+    glb: &mut Glb,
+    index: usize,
+    components: &[usize],
+) -> AppResult {
+    let json = &mut glb.json;
+    let accessor = json["accessors"]
+        .get(index)
+        .with_context(|| format!("Not found accessor {index}"))?;
+    if accessor["componentType"].as_u64() != Some(COMPONENT_FLOAT) {
+        bail!(
+            "Accessor {index} is not a float accessor, which VRM 0.x conversion does not support"
+        );
+    }
+    let element_size = match accessor["type"].as_str() {
+        Some("VEC3") => 12,
+        Some("VEC4") => 16,
+        Some("MAT4") => 64,
+        _ => bail!("Accessor {index} has an unexpected type"),
+    };
+    let mut ranges = Vec::new();
+    if let Some(view) = accessor["bufferView"].as_u64() {
+        let count = accessor["count"]
+            .as_u64()
+            .context("Not found accessor count")?;
+        let offset = accessor["byteOffset"].as_u64().unwrap_or(0);
+        ranges.push((view, offset, count, true));
+    }
+    if let Some(values) = accessor["sparse"]["values"].as_object() {
+        let view = values["bufferView"]
+            .as_u64()
+            .context("Not found sparse bufferView")?;
+        let count = accessor["sparse"]["count"]
+            .as_u64()
+            .context("Not found sparse count")?;
+        let offset = values
+            .get("byteOffset")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        ranges.push((view, offset, count, false));
+    }
+
+    let bin = glb
+        .bin
+        .as_mut()
+        .context("VRM 0.x conversion needs the binary chunk")?;
+    for (view, offset, count, strided) in ranges {
+        let view = &json["bufferViews"][view as usize];
+        if view["buffer"].as_u64() != Some(0) {
+            bail!("VRM 0.x conversion only supports the binary chunk of glb");
+        }
+        let start = (view["byteOffset"].as_u64().unwrap_or(0) + offset) as usize;
+        let stride = view["byteStride"]
+            .as_u64()
+            .filter(|_| strided)
+            .map_or(element_size, |stride| stride as usize);
+        for element in 0..count as usize {
+            for component in components {
+                let at = start + element * stride + component * 4;
+                let bytes = bin
+                    .get_mut(at..at + 4)
+                    .context("The accessor exceeds the binary chunk")?;
+                let v = -f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                bytes.copy_from_slice(&v.to_le_bytes());
+            }
+        }
+    }
+
+    let accessor = &mut json["accessors"][index];
+    if let (Some(min), Some(max)) = (accessor["min"].as_array(), accessor["max"].as_array()) {
+        let (mut min, mut max) = (min.clone(), max.clone());
+        for component in components {
+            if let (Some(lo), Some(hi)) = (
+                min.get(*component).and_then(Value::as_f64),
+                max.get(*component).and_then(Value::as_f64),
+            ) {
+                min[*component] = json!(-hi);
+                max[*component] = json!(-lo);
+            }
+        }
+        accessor["min"] = Value::Array(min);
+        accessor["max"] = Value::Array(max);
+    }
+    Ok(())
+}
+
+fn local_transform(node: &Value) -> (Vec3, Quat, Vec3) {
+    if let Some(matrix) = node.get("matrix").and_then(floats::<16>) {
+        return Mat4::from_cols_array(&matrix).to_scale_rotation_translation();
+    }
+    let translation = node
+        .get("translation")
+        .and_then(floats::<3>)
+        .map(Vec3::from_array)
+        .unwrap_or(Vec3::ZERO);
+    let rotation = node
+        .get("rotation")
+        .and_then(floats::<4>)
+        .map(Quat::from_array)
+        .unwrap_or(Quat::IDENTITY);
+    let scale = node
+        .get("scale")
+        .and_then(floats::<3>)
+        .map(Vec3::from_array)
+        .unwrap_or(Vec3::ONE);
+    (scale, rotation, translation)
+}
+
+fn floats<const N: usize>(value: &Value) -> Option<[f32; N]> {
+    let array = value.as_array()?;
+    let mut out = [0.0; N];
+    for (o, v) in out.iter_mut().zip(array.iter()) {
+        *o = v.as_f64()? as f32;
+    }
+    (array.len() == N).then_some(out)
+}
+
+fn node_name(
+    root: &Value,
+    node: usize,
+) -> Option<String> {
+    root["nodes"]
+        .get(node)?
+        .get("name")?
+        .as_str()
+        .map(str::to_string)
+}
+
+fn node_children(
+    root: &Value,
+    node: usize,
+) -> Vec<usize> {
+    root["nodes"]
+        .get(node)
+        .and_then(|node| node.get("children"))
+        .and_then(Value::as_array)
+        .map(|children| {
+            children
+                .iter()
+                .filter_map(|child| child.as_u64().map(|c| c as usize))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the indices of the nodes that instantiate the mesh.
+fn nodes_with_mesh(
+    root: &Value,
+    mesh: usize,
+) -> Vec<usize> {
+    root["nodes"]
+        .as_array()
+        .map(|nodes| {
+            nodes
+                .iter()
+                .enumerate()
+                .filter(|(_, node)| node.get("mesh").and_then(Value::as_u64) == Some(mesh as u64))
+                .map(|(i, _)| i)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn texture_source(
+    root: &Value,
+    texture: usize,
+) -> Option<usize> {
+    root["textures"]
+        .get(texture)?
+        .get("source")?
+        .as_u64()
+        .map(|s| s as usize)
+}
+
+#[inline]
+fn lerp(
+    a: f32,
+    b: f32,
+    t: f32,
+) -> f32 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::AppResult;
+    use crate::glb::{BufferWriter, Glb};
+    use crate::prelude::*;
+    use crate::success;
+    use crate::tests::TestResult;
+    use crate::vrm::gltf::extensions::vrm0::{
+        convert_document, is_vrm0, negate_elements, VECTOR_FLIPS,
+    };
+    use crate::vrm::gltf::extensions::vrmc_spring_bone::VRMCSpringBone;
+    use crate::vrm::gltf::extensions::vrmc_vrm::{MaterialColorType, VrmcVrm};
+    use crate::vrm::tests::{spawn_vrm, update_until, vrm_app};
+    use bevy::asset::io::memory::Dir;
+    use bevy::prelude::*;
+    use serde_json::json;
+    use std::f32::consts::PI;
+    use std::path::Path;
+
+    fn vrm0_document() -> serde_json::Value {
+        json!({
+            "scenes": [{"nodes": [0]}],
+            "nodes": [
+                {"name": "Root", "children": [1, 3]},
+                {"name": "Hips", "translation": [0.1, 0.9, 0.2], "rotation": [0.1, 0.2, 0.3, 0.9], "children": [2]},
+                {"name": "Thumb"},
+                {"name": "Hair", "translation": [0.0, 0.1, 0.0], "children": [4]},
+                {"name": "Hair2", "translation": [0.0, 0.1, 0.0]},
+                {"name": "Face", "mesh": 0}
+            ],
+            "materials": [{"name": "Skin"}],
+            "extensions": {
+                "VRM": {
+                    "meta": {"title": "Avatar", "author": "me", "licenseName": "CC_BY_ND"},
+                    "humanoid": {"humanBones": [
+                        {"bone": "hips", "node": 1},
+                        {"bone": "leftThumbProximal", "node": 2}
+                    ]},
+                    "blendShapeMaster": {"blendShapeGroups": [
+                        {"name": "Joy", "presetName": "joy", "binds": [{"mesh": 0, "index": 3, "weight": 100}], "materialValues": [
+                            {"materialName": "Skin", "propertyName": "_Color", "targetValue": [1.0, 0.5, 0.5, 1.0]},
+                            {"materialName": "Skin", "propertyName": "_MainTex_ST", "targetValue": [1.0, 1.0, 0.5, 0.0]}
+                        ]},
+                        {"name": "Star", "presetName": "unknown", "binds": []}
+                    ]},
+                    "secondaryAnimation": {
+                        "boneGroups": [{
+                            "stiffiness": 1.0, "gravityPower": 0.0, "gravityDir": {"x": 1.0, "y": -1.0, "z": 0.0},
+                            "dragForce": 0.4, "center": -1, "hitRadius": 0.02, "bones": [3], "colliderGroups": [0]
+                        }],
+                        "colliderGroups": [{"node": 1, "colliders": [{"offset": {"x": 0.0, "y": 0.0, "z": 0.5}, "radius": 0.1}]}]
+                    },
+                    "materialProperties": [{
+                        "name": "Skin", "shader": "VRM/MToon",
+                        "floatProperties": {"_OutlineWidthMode": 1, "_OutlineWidth": 2.0},
+                        "vectorProperties": {"_ShadeColor": [0.5, 0.5], "_RimColor": [1.0, 0.0, 0.0, 1.0]}
+                    }]
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn convert_vrm0() -> TestResult {
+        let mut glb = Glb {
+            json: vrm0_document(),
+            bin: None,
+        };
+        assert!(is_vrm0(&glb.json));
+        convert_document(&mut glb)?;
+        let root = glb.json;
+        assert!(!is_vrm0(&root));
+
+        let vrm: VrmcVrm = serde_json::from_value(root["extensions"]["VRMC_vrm"].clone())?;
+        assert_eq!(vrm.humanoid.human_bones["hips"].node, 1);
+        assert_eq!(vrm.humanoid.human_bones["leftThumbMetacarpal"].node, 2);
+        let expressions = vrm.expressions.unwrap();
+        assert_eq!(expressions.preset.len(), 1);
+        assert!(expressions.custom.contains_key("Star"));
+        let bind = &expressions.preset["happy"]
+            .morph_target_binds
+            .as_ref()
+            .unwrap()[0];
+        assert_eq!((bind.node, bind.index, bind.weight), (5, 3, 1.0));
+        let happy = &expressions.preset["happy"];
+        let color = &happy.material_color_binds.as_ref().unwrap()[0];
+        assert_eq!(color.material, 0);
+        assert_eq!(color.r#type, MaterialColorType::Color);
+        let transform = &happy.texture_transform_binds.as_ref().unwrap()[0];
+        assert_eq!(transform.offset, [0.5, 0.0]);
+        let meta = vrm.meta.unwrap();
+        assert_eq!(meta.name.as_deref(), Some("Avatar"));
+        assert_eq!(meta.modification.as_deref(), Some("prohibited"));
+        assert_eq!(meta.credit_notation.as_deref(), Some("required"));
+
+        let spring: VRMCSpringBone =
+            serde_json::from_value(root["extensions"]["VRMC_springBone"].clone())?;
+        let joints = spring.springs[0]
+            .joints
+            .iter()
+            .map(|j| j.node)
+            .collect::<Vec<_>>();
+        // The virtual tail node is appended after the original nodes.
+        assert_eq!(joints, vec![3, 4, 6]);
+        assert_eq!(spring.springs[0].center, None);
+        assert_eq!(
+            spring.springs[0].joints[0].gravity_dir,
+            Some([-1.0, -1.0, 0.0])
+        );
+        let ColliderShape::Sphere(sphere) = spring.colliders[0].shape else {
+            panic!("expected sphere collider");
+        };
+        assert_eq!(sphere.offset, [0.0, 0.0, 0.5]);
+
+        let mtoon = &root["materials"][0]["extensions"]["VRMC_materials_mtoon"];
+        assert_eq!(mtoon["outlineWidthMode"], "worldCoordinates");
+        assert_eq!(
+            mtoon["outlineWidthFactor"].as_f64(),
+            Some(0.019999999552965164)
+        );
+
+        // Short color arrays are padded instead of panicking.
+        assert_eq!(mtoon["shadeColorFactor"], json!([0.5, 0.5, 0.0]));
+        assert_eq!(mtoon["parametricRimColorFactor"], json!([1.0, 0.0, 0.0]));
+
+        // The nodes are rotated 180 degrees around the Y axis instead of being wrapped.
+        assert_eq!(root["scenes"][0]["nodes"], json!([0]));
+        assert_eq!(root["nodes"].as_array().unwrap().len(), 7);
+        let hips = &root["nodes"][1];
+        assert_eq!(hips["translation"], json!([-0.1, 0.9, -0.2]));
+        assert_eq!(hips["rotation"], json!([-0.1, 0.2, -0.3, 0.9]));
+        success!()
+    }
+
+    #[test]
+    fn rotate_meshes_and_skins() -> TestResult {
+        let bind =
+            Mat4::from_rotation_translation(Quat::from_rotation_x(0.3), Vec3::new(0.1, -0.9, 0.2));
+        let mut writer = BufferWriter::default();
+        let positions = writer.push_f32(&[0.1, 0.2, 0.3, -0.4, 0.5, 0.6], "VEC3", true);
+        let normals = writer.push_f32(&[0., 0., -1., 1., 0., 0.], "VEC3", false);
+        let displacement = writer.push_f32(&[0.01, 0., 0.02, 0., 0., 0.], "VEC3", true);
+        let inverse_binds = writer.push_f32(&bind.to_cols_array(), "MAT4", false);
+        let mut glb = writer.into_glb(json!({
+            "nodes": [
+                {"name": "Root", "matrix": bind.to_cols_array()},
+                {"name": "Body", "mesh": 0, "skin": 0}
+            ],
+            "meshes": [{"primitives": [{
+                "attributes": {"POSITION": positions, "NORMAL": normals},
+                "targets": [{"POSITION": displacement}]
+            }]}],
+            "skins": [{"joints": [0], "inverseBindMatrices": inverse_binds}],
+            "extensions": {"VRM": {}}
+        }));
+        convert_document(&mut glb)?;
+
+        let bytes = Glb::from_bytes(&glb.to_bytes()?)?.bin.unwrap();
+        let floats = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect::<Vec<_>>();
+        assert_eq!(floats[0..6], [-0.1, 0.2, -0.3, 0.4, 0.5, -0.6]);
+        assert_eq!(floats[6..12], [-0., 0., 1., -1., 0., -0.]);
+        assert_eq!(floats[12..15], [-0.01, 0., -0.02]);
+        let turn = Mat4::from_rotation_y(PI);
+        let expected = turn * bind * turn.inverse();
+        let inverse_bind = Mat4::from_cols_slice(&floats[18..34]);
+        assert!(inverse_bind.abs_diff_eq(expected, 1e-6), "{inverse_bind}");
+
+        let positions = &glb.json["accessors"][positions];
+        assert_eq!(
+            positions["min"],
+            json!([-0.1f32 as f64, 0.2f32 as f64, -0.6f32 as f64])
+        );
+        assert_eq!(
+            positions["max"],
+            json!([0.4f32 as f64, 0.5f32 as f64, -0.3f32 as f64])
+        );
+        let matrix = serde_json::from_value::<Vec<f32>>(glb.json["nodes"][0]["matrix"].clone())?;
+        assert!(Mat4::from_cols_slice(&matrix).abs_diff_eq(expected, 1e-6));
+        success!()
+    }
+
+    /// Builds a VRM 0.x avatar facing `-Z` from the skeleton of [`VrmBuilder`].
+    fn vrm0_avatar() -> AppResult<Vec<u8>> {
+        let bytes = VrmBuilder::new("Avatar")
+            .humanoid()
+            .bone("leftEye", Some("head"), Vec3::new(0.03, 0.05, 0.0))
+            .bone("rightEye", Some("head"), Vec3::new(-0.03, 0.05, 0.0))
+            .build()?;
+        let mut glb = Glb::from_bytes(&bytes)?;
+        for node in glb.json["nodes"].as_array_mut().unwrap() {
+            negate_elements(&mut node["translation"], &VECTOR_FLIPS);
+        }
+        let human_bones = glb.json["extensions"]["VRMC_vrm"]["humanoid"]["humanBones"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(bone, node)| json!({"bone": bone, "node": node["node"]}))
+            .collect::<Vec<_>>();
+        let degree_map = json!({"xRange": 90.0, "yRange": 90.0});
+        glb.json["extensionsUsed"] = json!(["VRM"]);
+        glb.json["extensions"] = json!({"VRM": {
+            "meta": {"title": "Avatar", "allowedUserName": "Everyone", "licenseName": "CC0"},
+            "humanoid": {"humanBones": human_bones},
+            "firstPerson": {
+                "firstPersonBoneOffset": {"x": 0.0, "y": 0.05, "z": 0.0},
+                "lookAtTypeName": "Bone",
+                "lookAtHorizontalInner": degree_map,
+                "lookAtHorizontalOuter": degree_map,
+                "lookAtVerticalDown": degree_map,
+                "lookAtVerticalUp": degree_map
+            }
+        }});
+        glb.to_bytes()
+    }
+
+    #[test]
+    fn spawn_converted_vrm0_facing_forward() -> TestResult {
+        let dir = Dir::default();
+        dir.insert_asset(Path::new("avatar.vrm"), vrm0_avatar()?);
+        let mut app = vrm_app(&dir);
+        let vrm = spawn_vrm(&mut app, "avatar.vrm");
+        let world = app.world();
+        let hips = world.get::<HipsBoneEntity>(vrm).unwrap().0;
+        let left_eye = world.get::<LeftEyeBoneEntity>(vrm).unwrap().0;
+
+        // The target is 45 degrees to the left of the avatar facing `+Z`.
+        app.world_mut()
+            .entity_mut(vrm)
+            .insert(LookAt::Position(Vec3::new(1.0, 1.5, 1.0)));
+        update_until(&mut app, |world| {
+            world.get::<Transform>(left_eye).unwrap().rotation != Quat::IDENTITY
+        });
+        let rotation = app.world().get::<Transform>(left_eye).unwrap().rotation;
+        let yaw = rotation.to_euler(EulerRot::YXZ).0.to_degrees();
+        assert!((yaw - 45.0).abs() < 0.1, "{yaw}");
+
+        // The retargeted hips translation is a global delta written in the local space,
+        // so moving it forward must move the hips toward `+Z`.
+        let rest = *app.world().get::<BoneRestGlobalTransform>(hips).unwrap();
+        assert!(rest
+            .translation()
+            .abs_diff_eq(Vec3::new(0.0, 0.9, 0.0), 1e-6));
+        app.world_mut()
+            .entity_mut(hips)
+            .insert(Transform::from_translation(
+                rest.translation() + Vec3::Z * 0.1,
+            ));
+        app.update();
+        let hips_gtf = app.world().get::<GlobalTransform>(hips).unwrap();
+        assert!(hips_gtf
+            .translation()
+            .abs_diff_eq(Vec3::new(0.0, 0.9, 0.1), 1e-6));
+        success!()
+    }
+}
@@ -50,10 +50,110 @@ pub struct ColliderGroup {
 
 /// Represents the collision detection for spring bone.
 /// It consists of the target node index and the collider shape.
-#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Collider {
     pub node: usize,
+    /// The base shape, which is also the fallback of `VRMC_springBone_extended_collider`.
     pub shape: ColliderShape,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<ColliderExtensions>,
+}
+
+impl Collider {
+    /// Creates a collider; the extension is used if the shape is not supported by the base shape.
+    pub fn new(
+        node: usize,
+        shape: ColliderShape,
+    ) -> Self {
+        Self {
+            node,
+            shape: shape.fallback(),
+            extensions: shape.extended().map(|shape| ColliderExtensions {
+                extended_collider: Some(VRMCSpringBoneExtendedCollider::new(shape)),
+            }),
+        }
+    }
+
+    /// Returns the shape of `VRMC_springBone_extended_collider` if present, or the base shape otherwise.
+    pub fn resolved_shape(&self) -> ColliderShape {
+        self.extensions
+            .as_ref()
+            .and_then(|extensions| extensions.extended_collider.as_ref())
+            .filter(|extended| extended.spec_version.starts_with("1."))
+            .map(|extended| ColliderShape::from(extended.shape))
+            .unwrap_or(self.shape)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ColliderExtensions {
+    #[serde(
+        rename = "VRMC_springBone_extended_collider",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub extended_collider: Option<VRMCSpringBoneExtendedCollider>,
+}
+
+/// `VRMC_springBone_extended_collider` that adds plane colliders and inside colliders.
+///
+/// - [`VRMC_springBone_extended_collider`](https://github.com/vrm-c/vrm-specification/tree/master/specification/VRMC_springBone_extended_collider-1.0)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VRMCSpringBoneExtendedCollider {
+    #[serde(rename = "specVersion")]
+    pub spec_version: String,
+    pub shape: ExtendedColliderShape,
+}
+
+impl VRMCSpringBoneExtendedCollider {
+    pub const NAME: &'static str = "VRMC_springBone_extended_collider";
+
+    pub fn new(shape: ExtendedColliderShape) -> Self {
+        Self {
+            spec_version: "1.0".to_string(),
+            shape,
+        }
+    }
+}
+
+/// The shape of [`VRMCSpringBoneExtendedCollider`].
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ExtendedColliderShape {
+    Sphere {
+        #[serde(flatten)]
+        sphere: Sphere,
+        #[serde(default)]
+        inside: bool,
+    },
+    Capsule {
+        #[serde(flatten)]
+        capsule: Capsule,
+        #[serde(default)]
+        inside: bool,
+    },
+    Plane(Plane),
+}
+
+impl From<ExtendedColliderShape> for ColliderShape {
+    fn from(shape: ExtendedColliderShape) -> Self {
+        match shape {
+            ExtendedColliderShape::Sphere { sphere, inside } => {
+                if inside {
+                    Self::InsideSphere(sphere)
+                } else {
+                    Self::Sphere(sphere)
+                }
+            }
+            ExtendedColliderShape::Capsule { capsule, inside } => {
+                if inside {
+                    Self::InsideCapsule(capsule)
+                } else {
+                    Self::Capsule(capsule)
+                }
+            }
+            ExtendedColliderShape::Plane(plane) => Self::Plane(plane),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -87,12 +187,21 @@ pub struct SpringJoint {
 }
 
 /// The shape of the collision detection for [Collider]
+///
+/// [`ColliderShape::Plane`], [`ColliderShape::InsideSphere`] and [`ColliderShape::InsideCapsule`]
+/// come from `VRMC_springBone_extended_collider`.
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Component, Reflect)]
 #[reflect(Component, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum ColliderShape {
     Sphere(Sphere),
     Capsule(Capsule),
+    /// Keeps the joints on the front side of the plane.
+    Plane(Plane),
+    /// Keeps the joints inside the sphere.
+    InsideSphere(Sphere),
+    /// Keeps the joints inside the capsule.
+    InsideCapsule(Capsule),
 }
 
 impl Default for ColliderShape {
@@ -111,35 +220,127 @@ impl ColliderShape {
         joint_radius: f32,
         bone_length: f32,
     ) {
-        let (scale, _, _) = collider.to_scale_rotation_translation();
+        let (scale, rotation, _) = collider.to_scale_rotation_translation();
         let max_collider_scale = scale.abs().max_element();
-        match self {
-            Self::Sphere(sphere) => {
-                let translation = collider.transform_point(Vec3::from(sphere.offset));
-                let r = joint_radius + sphere.radius * max_collider_scale;
-                let delta = *next_tail - translation;
-                if delta.length() <= r * r {
-                    let dir = delta.normalize();
-                    let pos_from_collider = translation + dir * r;
-                    *next_tail = head_global_pos
-                        + (pos_from_collider - head_global_pos).normalize() * bone_length;
-                }
-            }
-            Self::Capsule(_) => {
-                //TODO: Not supported yet
+        let pos_from_collider = match self {
+            Self::Sphere(sphere) => push_out(
+                *next_tail,
+                collider.transform_point(Vec3::from(sphere.offset)),
+                joint_radius + sphere.radius * max_collider_scale,
+            ),
+            Self::Capsule(capsule) => push_out(
+                *next_tail,
+                nearest_on_capsule(*next_tail, collider, capsule),
+                joint_radius + capsule.radius * max_collider_scale,
+            ),
+            Self::Plane(plane) => {
+                let point = collider.transform_point(Vec3::from(plane.offset));
+                let normal = (rotation * Vec3::from(plane.normal)).normalize_or_zero();
+                let distance = (*next_tail - point).dot(normal) - joint_radius;
+                (distance < 0.0).then(|| *next_tail - normal * distance)
             }
+            Self::InsideSphere(sphere) => pull_in(
+                *next_tail,
+                collider.transform_point(Vec3::from(sphere.offset)),
+                sphere.radius * max_collider_scale - joint_radius,
+            ),
+            Self::InsideCapsule(capsule) => pull_in(
+                *next_tail,
+                nearest_on_capsule(*next_tail, collider, capsule),
+                capsule.radius * max_collider_scale - joint_radius,
+            ),
+        };
+        if let Some(pos_from_collider) = pos_from_collider {
+            *next_tail =
+                head_global_pos + (pos_from_collider - head_global_pos).normalize() * bone_length;
+        }
+    }
+
+    /// Returns the shape written into `shape` of the collider.
+    ///
+    /// The shapes of `VRMC_springBone_extended_collider` fall back to a sphere with zero radius,
+    /// so that the loaders without the extension are barely affected.
+    pub fn fallback(&self) -> Self {
+        match self {
+            Self::Sphere(_) | Self::Capsule(_) => *self,
+            Self::Plane(Plane { offset, .. })
+            | Self::InsideSphere(Sphere { offset, .. })
+            | Self::InsideCapsule(Capsule { offset, .. }) => Self::Sphere(Sphere {
+                offset: *offset,
+                radius: 0.0,
+            }),
+        }
+    }
+
+    /// Returns the shape of `VRMC_springBone_extended_collider` if the shape needs the extension.
+    pub fn extended(&self) -> Option<ExtendedColliderShape> {
+        match self {
+            Self::Sphere(_) | Self::Capsule(_) => None,
+            Self::Plane(plane) => Some(ExtendedColliderShape::Plane(*plane)),
+            Self::InsideSphere(sphere) => Some(ExtendedColliderShape::Sphere {
+                sphere: *sphere,
+                inside: true,
+            }),
+            Self::InsideCapsule(capsule) => Some(ExtendedColliderShape::Capsule {
+                capsule: *capsule,
+                inside: true,
+            }),
         }
     }
 
     #[inline]
     pub const fn radius(&self) -> f32 {
         match self {
-            Self::Sphere(sphere) => sphere.radius,
-            Self::Capsule(capsule) => capsule.radius,
+            Self::Sphere(sphere) | Self::InsideSphere(sphere) => sphere.radius,
+            Self::Capsule(capsule) | Self::InsideCapsule(capsule) => capsule.radius,
+            Self::Plane(_) => 0.0,
         }
     }
 }
 
+/// Returns the nearest point of the capsule's segment to the tail.
+fn nearest_on_capsule(
+    tail: Vec3,
+    collider: &GlobalTransform,
+    capsule: &Capsule,
+) -> Vec3 {
+    let start = collider.transform_point(Vec3::from(capsule.offset));
+    let end = collider.transform_point(Vec3::from(capsule.tail));
+    let segment = end - start;
+    let t = if segment == Vec3::ZERO {
+        0.0
+    } else {
+        ((tail - start).dot(segment) / segment.length_squared()).clamp(0.0, 1.0)
+    };
+    start + segment * t
+}
+
+/// Returns the position pushed out to `radius` from `nearest` if the tail is inside of it.
+fn push_out(
+    tail: Vec3,
+    nearest: Vec3,
+    radius: f32,
+) -> Option<Vec3> {
+    let delta = tail - nearest;
+    if radius < delta.length() {
+        return None;
+    }
+    Some(nearest + delta.try_normalize()? * radius)
+}
+
+/// Returns the position pulled in to `radius` from `nearest` if the tail is outside of it.
+fn pull_in(
+    tail: Vec3,
+    nearest: Vec3,
+    radius: f32,
+) -> Option<Vec3> {
+    let delta = tail - nearest;
+    if delta.length() <= radius {
+        return None;
+    }
+    Some(nearest + delta.try_normalize()? * radius.max(0.0))
+}
+
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Component, Reflect, Default)]
 #[reflect(Component, Serialize, Deserialize)]
 pub struct Sphere {
@@ -161,11 +362,66 @@ pub struct Capsule {
     pub tail: [f32; 3],
 }
 
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Component, Reflect)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct Plane {
+    /// Local coordinate of a point on the plane
+    pub offset: [f32; 3],
+    /// Local direction of the plane's normal; the joints are kept on this side
+    pub normal: [f32; 3],
+}
+
+impl Default for Plane {
+    fn default() -> Self {
+        Self {
+            offset: [0.0; 3],
+            normal: [0.0, 0.0, 1.0],
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::success;
     use crate::tests::TestResult;
-    use crate::vrm::gltf::extensions::vrmc_spring_bone::VRMCSpringBone;
+    use crate::vrm::gltf::extensions::vrmc_spring_bone::{
+        Capsule, Collider, ColliderShape, Plane, Sphere, VRMCSpringBone,
+    };
+    use bevy::prelude::*;
+    use serde_json::json;
+
+    const CAPSULE: ColliderShape = ColliderShape::Capsule(Capsule {
+        offset: [0.0; 3],
+        radius: 0.1,
+        tail: [0.0, 1.0, 0.0],
+    });
+
+    fn collide(
+        collider: Transform,
+        head: Vec3,
+        tail: Vec3,
+    ) -> Vec3 {
+        collide_with(CAPSULE, collider, head, tail)
+    }
+
+    fn collide_with(
+        shape: ColliderShape,
+        collider: Transform,
+        head: Vec3,
+        tail: Vec3,
+    ) -> Vec3 {
+        let mut next_tail = tail;
+        let bone_length = head.distance(tail);
+        shape.apply_collision(
+            &mut next_tail,
+            &GlobalTransform::from(collider),
+            head,
+            0.0,
+            bone_length,
+        );
+        assert!((head.distance(next_tail) - bone_length).abs() < 1e-5);
+        next_tail
+    }
 
     #[test]
     fn deserialize_vrmc_spring_bone() -> TestResult {
@@ -173,4 +429,121 @@ mod tests {
             serde_json::from_str(include_str!("vrmc_spring_bone.json"))?;
         success!()
     }
+
+    #[test]
+    fn push_out_of_sphere_within_radius() {
+        let sphere = ColliderShape::Sphere(Sphere {
+            offset: [0.0; 3],
+            radius: 0.5,
+        });
+        // Farther than the squared radius, but still inside the sphere.
+        let tail = Vec3::new(0.4, 0.0, 0.0);
+        let next_tail = collide_with(sphere, Transform::default(), Vec3::new(0.4, 1.0, 0.0), tail);
+        assert!(tail.length() < next_tail.length(), "{next_tail}");
+
+        let outside = Vec3::new(0.6, 0.0, 0.0);
+        assert_eq!(
+            collide_with(
+                sphere,
+                Transform::default(),
+                Vec3::new(0.6, 1.0, 0.0),
+                outside
+            ),
+            outside
+        );
+    }
+
+    #[test]
+    fn push_out_of_capsule_side() {
+        let tail = Vec3::new(0.05, 0.5, 0.0);
+        let next_tail = collide(Transform::default(), Vec3::new(0.05, 0.9, 0.0), tail);
+        assert!(0.09 < next_tail.x, "{next_tail}");
+
+        let outside = Vec3::new(0.15, 0.5, 0.0);
+        assert_eq!(
+            collide(Transform::default(), Vec3::new(0.15, 0.9, 0.0), outside),
+            outside
+        );
+    }
+
+    #[test]
+    fn capsule_follows_collider_scale() {
+        // Beyond the tail of the unscaled capsule, but inside the scaled one.
+        let head = Vec3::new(0.1, 2.5, 0.0);
+        let tail = Vec3::new(0.1, 2.1, 0.0);
+        assert_eq!(collide(Transform::default(), head, tail), tail);
+
+        let scaled = Transform::from_scale(Vec3::splat(2.0));
+        let next_tail = collide(scaled, head, tail);
+        let to_end = |tail: Vec3| tail.distance(Vec3::new(0.0, 2.0, 0.0));
+        assert!(to_end(tail) < to_end(next_tail), "{next_tail}");
+    }
+
+    #[test]
+    fn resolve_extended_collider() -> TestResult {
+        let collider = |spec_version: &str| {
+            serde_json::from_value::<Collider>(json!({
+                "node": 0,
+                "shape": { "sphere": { "offset": [0.0, 0.0, 0.0], "radius": 0.0 } },
+                "extensions": {
+                    "VRMC_springBone_extended_collider": {
+                        "specVersion": spec_version,
+                        "shape": { "plane": { "offset": [0.0, 0.0, 0.0], "normal": [0.0, 1.0, 0.0] } }
+                    }
+                }
+            }))
+        };
+        assert_eq!(
+            collider("1.0")?.resolved_shape(),
+            ColliderShape::Plane(Plane {
+                offset: [0.0; 3],
+                normal: [0.0, 1.0, 0.0],
+            })
+        );
+        assert_eq!(
+            collider("2.0")?.resolved_shape(),
+            ColliderShape::Sphere(Sphere::default())
+        );
+
+        let inside = ColliderShape::InsideCapsule(Capsule {
+            offset: [0.0; 3],
+            radius: 0.5,
+            tail: [0.0, 1.0, 0.0],
+        });
+        let json = serde_json::to_value(Collider::new(0, inside))?;
+        assert_eq!(
+            json["extensions"]["VRMC_springBone_extended_collider"]["shape"]["capsule"]["inside"],
+            true
+        );
+        assert_eq!(
+            serde_json::from_value::<Collider>(json)?.resolved_shape(),
+            inside
+        );
+        success!()
+    }
+
+    #[test]
+    fn keep_inside_and_above_plane() {
+        let plane = ColliderShape::Plane(Plane {
+            offset: [0.0; 3],
+            normal: [0.0, 1.0, 0.0],
+        });
+        // The tail is kept at the bone length, so it approaches the surface over frames.
+        let tail = Vec3::new(0.2, -0.1, 0.0);
+        let next_tail = collide_with(plane, Transform::default(), Vec3::new(0.0, 0.3, 0.0), tail);
+        assert!(tail.y < next_tail.y, "{next_tail}");
+
+        let inside = ColliderShape::InsideSphere(Sphere {
+            offset: [0.0; 3],
+            radius: 0.5,
+        });
+        let tail = Vec3::new(0.3, 0.6, 0.0);
+        let next_tail = collide_with(inside, Transform::default(), Vec3::new(0.0, 0.4, 0.0), tail);
+        assert!(next_tail.length() < tail.length(), "{next_tail}");
+        let tail = Vec3::new(0.1, 0.2, 0.0);
+        assert_eq!(
+            collide_with(inside, Transform::default(), Vec3::ZERO, tail),
+            tail
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use crate::vrm::expressions::{VrmExpression, VrmExpressionPreset};
 use crate::vrm::gltf::extensions::VrmNode;
 use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
@@ -6,8 +7,8 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize)]
 pub struct VrmcVrm {
     pub expressions: Option<Expressions>,
-    // #[serde(rename = "firstPerson")]
-    // pub first_person: Option<FirstPerson>,
+    #[serde(rename = "firstPerson")]
+    pub first_person: Option<FirstPerson>,
     pub humanoid: Humanoid,
     #[serde(rename = "lookAt")]
     pub look_at: Option<LookAtProperties>,
@@ -18,24 +19,59 @@ pub struct VrmcVrm {
 
 #[derive(Serialize, Deserialize)]
 pub struct Expressions {
+    #[serde(default)]
     pub preset: HashMap<String, VrmPreset>,
+    /// The expressions defined by the modeler, such as tongue out or star eyes.
+    #[serde(default)]
+    pub custom: HashMap<String, VrmPreset>,
+}
+
+impl Expressions {
+    /// Iterates over both the preset and custom expressions.
+    ///
+    /// Presets with unknown names and custom expressions whose name conflicts with a preset are ignored.
+    pub fn iter(&self) -> impl Iterator<Item = (VrmExpression, &VrmPreset)> {
+        self.preset
+            .iter()
+            .filter_map(|(name, expression)| {
+                Some((
+                    VrmExpression::Preset(VrmExpressionPreset::from_name(name)?),
+                    expression,
+                ))
+            })
+            .chain(
+                self.custom
+                    .iter()
+                    .filter(|(name, _)| VrmExpressionPreset::from_name(name).is_none())
+                    .map(|(name, expression)| (VrmExpression::Custom(name.clone()), expression)),
+            )
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct VrmPreset {
     /// If this value is `true`, `weight` value greater than 0.5 is 1.0, otherwise 0.0.
-    #[serde(rename = "isBinary")]
+    #[serde(rename = "isBinary", default)]
     pub is_binary: bool,
     #[serde(rename = "morphTargetBinds")]
     pub morph_target_binds: Option<Vec<MorphTargetBind>>,
-    #[serde(rename = "overrideBlink")]
+    #[serde(rename = "materialColorBinds", default)]
+    pub material_color_binds: Option<Vec<MaterialColorBind>>,
+    #[serde(rename = "textureTransformBinds", default)]
+    pub texture_transform_binds: Option<Vec<TextureTransformBind>>,
+    /// `none`, `block` or `blend`.
+    #[serde(rename = "overrideBlink", default = "default_override")]
     pub override_blink: String,
-    #[serde(rename = "overrideLookAt")]
+    #[serde(rename = "overrideLookAt", default = "default_override")]
     pub override_look_at: String,
-    #[serde(rename = "overrideMouth")]
+    #[serde(rename = "overrideMouth", default = "default_override")]
     pub override_mouth: String,
 }
 
+fn default_override() -> String {
+    "none".to_string()
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct MorphTargetBind {
     pub index: usize,
@@ -43,6 +79,46 @@ pub struct MorphTargetBind {
     pub weight: f32,
 }
 
+/// Changes a color of the material toward `targetValue` in proportion to the expression weight.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MaterialColorBind {
+    /// The index of the material.
+    pub material: usize,
+    #[serde(rename = "type")]
+    pub r#type: MaterialColorType,
+    /// The linear RGBA color.
+    #[serde(rename = "targetValue")]
+    pub target_value: [f32; 4],
+}
+
+/// The color property changed by [`MaterialColorBind`].
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+#[serde(rename_all = "camelCase")]
+pub enum MaterialColorType {
+    Color,
+    EmissionColor,
+    ShadeColor,
+    MatcapColor,
+    RimColor,
+    OutlineColor,
+}
+
+/// Changes the UV scale and offset of the material in proportion to the expression weight.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TextureTransformBind {
+    /// The index of the material.
+    pub material: usize,
+    #[serde(default = "default_texture_scale")]
+    pub scale: [f32; 2],
+    #[serde(default)]
+    pub offset: [f32; 2],
+}
+
+const fn default_texture_scale() -> [f32; 2] {
+    [1.0, 1.0]
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Humanoid {
     #[serde(rename = "humanBones")]
@@ -50,16 +126,17 @@ pub struct Humanoid {
 }
 
 #[derive(Serialize, Deserialize)]
-pub struct Struct6 {
-    pub node: i64,
+pub struct MeshAnnotation {
+    pub node: usize,
+    /// `auto`, `both`, `thirdPersonOnly` or `firstPersonOnly`.
     #[serde(rename = "type")]
     pub r#type: String,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct FirstPerson {
-    #[serde(rename = "meshAnnotations")]
-    pub mesh_annotations: Vec<Struct6>,
+    #[serde(rename = "meshAnnotations", default)]
+    pub mesh_annotations: Vec<MeshAnnotation>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -76,21 +153,26 @@ pub struct Struct5 {
 
 #[derive(Serialize, Deserialize)]
 pub struct Meta {
-    #[serde(rename = "allowAntisocialOrHateUsage")]
+    #[serde(rename = "allowAntisocialOrHateUsage", default)]
     pub allow_antisocial_or_hate_usage: bool,
-    #[serde(rename = "allowExcessivelySexualUsage")]
+    #[serde(rename = "allowExcessivelySexualUsage", default)]
     pub allow_excessively_sexual_usage: bool,
-    #[serde(rename = "allowExcessivelyViolentUsage")]
+    #[serde(rename = "allowExcessivelyViolentUsage", default)]
     pub allow_excessively_violent_usage: bool,
-    #[serde(rename = "allowPoliticalOrReligiousUsage")]
+    #[serde(rename = "allowPoliticalOrReligiousUsage", default)]
     pub allow_political_or_religious_usage: bool,
-    #[serde(rename = "allowRedistribution")]
+    #[serde(rename = "allowRedistribution", default)]
     pub allow_redistribution: bool,
+    #[serde(default)]
     pub authors: Vec<String>,
     #[serde(rename = "avatarPermission")]
     pub avatar_permission: Option<String>,
     #[serde(rename = "commercialUsage")]
     pub commercial_usage: Option<String>,
+    #[serde(rename = "contactInformation", default)]
+    pub contact_information: Option<String>,
+    #[serde(rename = "copyrightInformation", default)]
+    pub copyright_information: Option<String>,
     #[serde(rename = "creditNotation")]
     pub credit_notation: Option<String>,
     #[serde(rename = "licenseUrl")]
@@ -99,6 +181,10 @@ pub struct Meta {
     pub name: Option<String>,
     #[serde(rename = "otherLicenseUrl")]
     pub other_license_url: Option<String>,
+    #[serde(default)]
+    pub references: Vec<String>,
+    #[serde(rename = "thirdPartyLicenses", default)]
+    pub third_party_licenses: Option<String>,
     #[serde(rename = "thumbnailImage")]
     pub thumbnail_image: Option<i64>,
     pub version: Option<String>,
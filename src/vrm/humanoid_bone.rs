@@ -177,3 +177,73 @@ fn setup_bones(
         }
     }
 }
+
+macro_rules! remove_bone_entities {
+    ($entity_commands: expr, $($bone: ident),+$(,)?) => {
+        paste::paste!{
+            $($entity_commands.remove::<[<$bone BoneEntity>]>();)+
+        }
+    };
+}
+
+/// Removes the components that hold the entities of the bones from the VRM entity.
+pub(crate) fn remove_bone_entities(vrm: &mut EntityCommands) {
+    remove_bone_entities!(
+        vrm,
+        Hips,
+        RightRingProximal,
+        RightThumbDistal,
+        RightRingIntermediate,
+        RightUpperArm,
+        LeftIndexProximal,
+        LeftUpperLeg,
+        LeftFoot,
+        LeftIndexDistal,
+        LeftThumbMetacarpal,
+        RightLowerArm,
+        LeftMiddleDistal,
+        RightUpperLeg,
+        LeftToes,
+        LeftThumbDistal,
+        RightShoulder,
+        RightThumbMetacarpal,
+        Spine,
+        LeftLowerLeg,
+        LeftShoulder,
+        LeftUpperArm,
+        UpperChest,
+        RightToes,
+        RightIndexDistal,
+        LeftMiddleProximal,
+        LeftRingProximal,
+        LeftRingDistal,
+        LeftThumbProximal,
+        LeftIndexIntermediate,
+        LeftLittleProximal,
+        LeftLittleDistal,
+        RightHand,
+        RightLittleProximal,
+        LeftRingIntermediate,
+        RightIndexIntermediate,
+        Chest,
+        LeftHand,
+        RightLittleIntermediate,
+        RightFoot,
+        RightLowerLeg,
+        LeftLittleIntermediate,
+        LeftLowerArm,
+        RightLittleDistal,
+        RightMiddleIntermediate,
+        RightMiddleProximal,
+        RightThumbProximal,
+        Neck,
+        Jaw,
+        Head,
+        LeftEye,
+        RightEye,
+        LeftMiddleIntermediate,
+        RightRingDistal,
+        RightIndexProximal,
+        RightMiddleDistal,
+    );
+}
@@ -0,0 +1,438 @@
+//! Moves the mouth of VRM along with speech audio.
+//!
+//! The samples are analyzed by their volume and formants and drive the
+//! `aa`, `ih`, `ou`, `ee` and `oh` expressions.
+
+mod analyzer;
+#[cfg(feature = "audio")]
+mod audio;
+mod viseme_track;
+mod wav;
+
+use crate::error::AppResult;
+use crate::vrm::expressions::{mix_expressions, ProceduralExpressionWeights, VrmExpressionPreset};
+use crate::vrm::lip_sync::viseme_track::VisemeTrackPlugin;
+use crate::vrm::lip_sync::wav::Wav;
+use crate::vrm::VrmExpression;
+use anyhow::bail;
+use bevy::app::{App, Plugin, Update};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+#[cfg(feature = "audio")]
+pub use audio::LipSyncAudio;
+pub use viseme_track::{Viseme, VisemeClock, VisemeKey, VisemeTrack, VisemeTrackPlayer};
+
+const DEFAULT_VOLUME_THRESHOLD: f32 = 0.01;
+/// The length of the samples analyzed at once.
+const ANALYSIS_WINDOW: Duration = Duration::from_millis(32);
+
+/// The weights of the mouth expressions.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub struct VisemeWeights {
+    pub aa: f32,
+    pub ih: f32,
+    pub ou: f32,
+    pub ee: f32,
+    pub oh: f32,
+}
+
+impl VisemeWeights {
+    /// Iterates over the mouth presets and their weights.
+    pub fn iter(&self) -> impl Iterator<Item = (VrmExpressionPreset, f32)> {
+        [
+            (VrmExpressionPreset::Aa, self.aa),
+            (VrmExpressionPreset::Ih, self.ih),
+            (VrmExpressionPreset::Ou, self.ou),
+            (VrmExpressionPreset::Ee, self.ee),
+            (VrmExpressionPreset::Oh, self.oh),
+        ]
+        .into_iter()
+    }
+
+    /// Returns the larger weight of each viseme.
+    pub fn max(
+        &self,
+        other: &Self,
+    ) -> Self {
+        Self {
+            aa: self.aa.max(other.aa),
+            ih: self.ih.max(other.ih),
+            ou: self.ou.max(other.ou),
+            ee: self.ee.max(other.ee),
+            oh: self.oh.max(other.oh),
+        }
+    }
+
+    /// Linearly interpolates each weight toward `other`.
+    pub fn lerp(
+        &self,
+        other: &Self,
+        t: f32,
+    ) -> Self {
+        Self {
+            aa: self.aa.lerp(other.aa, t),
+            ih: self.ih.lerp(other.ih, t),
+            ou: self.ou.lerp(other.ou, t),
+            ee: self.ee.lerp(other.ee, t),
+            oh: self.oh.lerp(other.oh, t),
+        }
+    }
+
+    pub(crate) fn write_to(
+        &self,
+        procedural: &mut Mut<ProceduralExpressionWeights>,
+    ) {
+        for (preset, weight) in self.iter() {
+            let expression = VrmExpression::from(preset);
+            if procedural.get(&expression).copied().unwrap_or_default() != weight {
+                procedural.insert(expression, weight);
+            }
+        }
+    }
+}
+
+/// Moves the mouth of VRM with the pushed PCM samples.
+///
+/// Insert this component into the root entity of the VRM and push mono samples, such as the output of TTS.
+/// The samples are consumed in real time, so push them when the audio starts playing.
+/// The mouth expressions are still suppressed by the expressions whose `overrideMouth` is set.
+///
+/// The bytes of a `.wav` `AudioSource` of `bevy_audio` can be passed to [`LipSyncInput::push_wav`] as they are.
+/// With the `audio` feature, `LipSyncAudio` pushes the samples along with the playback of an `AudioPlayer`.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+///
+/// fn speak(mut vrms: Query<&mut LipSyncInput>) {
+///     let wav = std::fs::read("speech.wav").unwrap();
+///     for mut input in vrms.iter_mut() {
+///         input.push_wav(&wav).unwrap();
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component, Default)]
+pub struct LipSyncInput {
+    /// The sample rate of the pushed samples.
+    pub sample_rate: u32,
+    /// The time constant to smooth the mouth movement.
+    pub smoothing: Duration,
+    /// The RMS volume below which the mouth is closed.
+    pub volume_threshold: f32,
+    #[reflect(ignore)]
+    queue: VecDeque<f32>,
+    #[reflect(ignore)]
+    window: VecDeque<f32>,
+    carry: f32,
+    weights: VisemeWeights,
+}
+
+impl Default for LipSyncInput {
+    fn default() -> Self {
+        Self::new(48000)
+    }
+}
+
+impl LipSyncInput {
+    /// Creates a new input whose samples have the given sample rate.
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            smoothing: Duration::from_millis(50),
+            volume_threshold: DEFAULT_VOLUME_THRESHOLD,
+            queue: VecDeque::new(),
+            window: VecDeque::new(),
+            carry: 0.0,
+            weights: VisemeWeights::default(),
+        }
+    }
+
+    /// Queues mono samples in `-1.0..=1.0`.
+    pub fn push(
+        &mut self,
+        samples: &[f32],
+    ) {
+        self.queue.extend(samples);
+    }
+
+    /// Decodes the `.wav` bytes and queues the samples.
+    ///
+    /// If nothing is queued, the sample rate is changed to the one of the wav.
+    pub fn push_wav(
+        &mut self,
+        bytes: &[u8],
+    ) -> AppResult {
+        let wav = Wav::from_bytes(bytes)?;
+        if self.queue.is_empty() {
+            self.sample_rate = wav.sample_rate;
+        } else if self.sample_rate != wav.sample_rate {
+            bail!(
+                "The sample rate of the wav ({}) differs from the queued samples ({})",
+                wav.sample_rate,
+                self.sample_rate
+            );
+        }
+        self.push(&wav.samples);
+        Ok(())
+    }
+
+    /// Discards the queued samples.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.window.clear();
+    }
+
+    /// Returns the duration of the samples that have not been played yet.
+    pub fn queued(&self) -> Duration {
+        Duration::from_secs_f32(self.queue.len() as f32 / self.sample_rate.max(1) as f32)
+    }
+
+    /// Returns the current smoothed weights of the mouth expressions.
+    #[inline]
+    pub const fn weights(&self) -> VisemeWeights {
+        self.weights
+    }
+
+    fn advance(
+        &mut self,
+        delta: Duration,
+    ) -> VisemeWeights {
+        let rate = self.sample_rate.max(1) as f32;
+        let consume = self.carry + delta.as_secs_f32() * rate;
+        let count = (consume as usize).min(self.queue.len());
+        self.carry = if self.queue.len() <= count {
+            0.0
+        } else {
+            consume.fract()
+        };
+        let target = if count == 0 {
+            self.window.clear();
+            VisemeWeights::default()
+        } else {
+            self.window.extend(self.queue.drain(..count));
+            let window_len = (ANALYSIS_WINDOW.as_secs_f32() * rate) as usize;
+            let excess = self.window.len().saturating_sub(window_len);
+            self.window.drain(..excess);
+            analyzer::analyze(
+                self.window.make_contiguous(),
+                self.sample_rate,
+                self.volume_threshold,
+            )
+        };
+        let t = if self.smoothing.is_zero() {
+            1.0
+        } else {
+            1.0 - (-delta.as_secs_f32() / self.smoothing.as_secs_f32()).exp()
+        };
+        self.weights = self.weights.lerp(&target, t);
+        self.weights
+    }
+}
+
+/// Analyzes the `.wav` bytes offline and returns the viseme weights of each frame.
+///
+/// No smoothing is applied.
+pub fn analyze_wav(
+    bytes: &[u8],
+    frame: Duration,
+) -> AppResult<Vec<VisemeWeights>> {
+    let wav = Wav::from_bytes(bytes)?;
+    let frame_len = (frame.as_secs_f32() * wav.sample_rate as f32) as usize;
+    if frame_len == 0 {
+        bail!("The frame is shorter than a sample");
+    }
+    Ok(wav
+        .samples
+        .chunks(frame_len)
+        .map(|samples| analyzer::analyze(samples, wav.sample_rate, DEFAULT_VOLUME_THRESHOLD))
+        .collect())
+}
+
+pub(super) struct LipSyncPlugin;
+
+impl Plugin for LipSyncPlugin {
+    fn build(
+        &self,
+        app: &mut App,
+    ) {
+        app.register_type::<LipSyncInput>()
+            .register_type::<VisemeWeights>()
+            .add_plugins(VisemeTrackPlugin)
+            .add_systems(
+                Update,
+                (lip_sync, write_mouth).chain().before(mix_expressions),
+            )
+            .add_observer(close_mouth);
+
+        #[cfg(feature = "audio")]
+        app.register_type::<LipSyncAudio>().add_systems(
+            Update,
+            audio::follow_audio
+                .run_if(resource_exists::<Assets<bevy::audio::AudioSource>>)
+                .before(lip_sync),
+        );
+    }
+}
+
+fn lip_sync(
+    time: Res<Time>,
+    mut inputs: Query<&mut LipSyncInput>,
+) {
+    for mut input in inputs.iter_mut() {
+        input.advance(time.delta());
+    }
+}
+
+/// Writes the mouth weights of [`LipSyncInput`] and [`VisemeTrackPlayer`].
+///
+/// If a VRM has both, the larger weight of each viseme is written,
+/// so the audio can open the mouth wider than the track and vice versa.
+fn write_mouth(
+    mut vrms: Query<
+        (
+            Option<&LipSyncInput>,
+            Option<&VisemeTrackPlayer>,
+            &mut ProceduralExpressionWeights,
+        ),
+        Or<(With<LipSyncInput>, With<VisemeTrackPlayer>)>,
+    >
+) {
+    for (input, player, mut procedural) in vrms.iter_mut() {
+        let input = input.map(LipSyncInput::weights).unwrap_or_default();
+        let track = player.map(VisemeTrackPlayer::weights).unwrap_or_default();
+        input.max(&track).write_to(&mut procedural);
+    }
+}
+
+/// Leaves the mouth to [`VisemeTrackPlayer`] if it is still playing, otherwise closes it.
+fn close_mouth(
+    trigger: Trigger<OnRemove, LipSyncInput>,
+    mut vrms: Query<(Option<&VisemeTrackPlayer>, &mut ProceduralExpressionWeights)>,
+) {
+    if let Ok((player, mut procedural)) = vrms.get_mut(trigger.target()) {
+        player
+            .map(VisemeTrackPlayer::weights)
+            .unwrap_or_default()
+            .write_to(&mut procedural);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::success;
+    use crate::tests::{test_app, TestResult};
+    use crate::vrm::expressions::ProceduralExpressionWeights;
+    use crate::vrm::lip_sync::analyzer::tests::synthesize_vowel;
+    use crate::vrm::lip_sync::wav::Wav;
+    use crate::vrm::lip_sync::LipSyncPlugin;
+    use bevy::prelude::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn vowel_wav() -> Vec<u8> {
+        let mut samples = synthesize_vowel(800.0, 1300.0, 16000, 8000);
+        samples.extend([0.0; 8000]);
+        Wav {
+            samples,
+            sample_rate: 16000,
+        }
+        .to_bytes()
+    }
+
+    #[test]
+    fn analyze_wav_offline() -> TestResult {
+        let frames = analyze_wav(&vowel_wav(), Duration::from_millis(100))?;
+        assert_eq!(frames.len(), 10);
+        assert!(frames[..5].iter().all(|w| 0.5 < w.aa));
+        assert!(frames[5..].iter().all(|w| *w == VisemeWeights::default()));
+        success!()
+    }
+
+    #[test]
+    fn drive_mouth_expressions() -> TestResult {
+        let mut app = test_app();
+        app.add_plugins(LipSyncPlugin)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                50,
+            )));
+        let mut input = LipSyncInput::default();
+        input.push_wav(&vowel_wav())?;
+        assert_eq!(input.sample_rate, 16000);
+        let vrm = app
+            .world_mut()
+            .spawn((input, ProceduralExpressionWeights::default()))
+            .id();
+        let aa = |app: &App| {
+            app.world()
+                .get::<ProceduralExpressionWeights>(vrm)
+                .unwrap()
+                .get(&VrmExpression::from("aa"))
+                .copied()
+                .unwrap_or_default()
+        };
+        for _ in 0..8 {
+            app.update();
+        }
+        assert!(0.5 < aa(&app));
+        for _ in 0..20 {
+            app.update();
+        }
+        assert!(aa(&app) < 0.01);
+        assert_eq!(
+            app.world().get::<LipSyncInput>(vrm).unwrap().queued(),
+            Duration::ZERO
+        );
+        success!()
+    }
+
+    #[test]
+    fn combine_with_viseme_track() -> TestResult {
+        let mut app = test_app();
+        app.add_plugins(LipSyncPlugin)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                50,
+            )));
+        let track =
+            app.world_mut()
+                .resource_mut::<Assets<VisemeTrack>>()
+                .add(VisemeTrack::from_json(
+                    br#"[{ "time": 0.0, "viseme": "ih", "weight": 0.5 }]"#,
+                )?);
+        let mut input = LipSyncInput::default();
+        input.push_wav(&vowel_wav())?;
+        let vrm = app
+            .world_mut()
+            .spawn((
+                input,
+                VisemeTrackPlayer::new(track).with_clock(VisemeClock::Manual(Duration::ZERO)),
+                ProceduralExpressionWeights::default(),
+            ))
+            .id();
+        let weight = |app: &App, expression: &str| {
+            app.world()
+                .get::<ProceduralExpressionWeights>(vrm)
+                .unwrap()
+                .get(&VrmExpression::from(expression))
+                .copied()
+                .unwrap_or_default()
+        };
+        for _ in 0..8 {
+            app.update();
+        }
+        assert!(0.5 < weight(&app, "aa"));
+        assert_eq!(weight(&app, "ih"), 0.5);
+
+        app.world_mut().entity_mut(vrm).remove::<LipSyncInput>();
+        assert_eq!(weight(&app, "aa"), 0.0);
+        assert_eq!(weight(&app, "ih"), 0.5);
+        app.update();
+        assert_eq!(weight(&app, "ih"), 0.5);
+        success!()
+    }
+}
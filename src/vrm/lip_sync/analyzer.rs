@@ -0,0 +1,220 @@
+//! Estimates the viseme weights of a short frame of speech from its volume and formants.
+//!
+//! The first and second formants are found from the envelope of linear prediction (LPC),
+//! and compared with the typical formants of each vowel.
+
+use crate::vrm::lip_sync::VisemeWeights;
+use std::f32::consts::PI;
+
+/// The sample rate the samples are decimated to before the formant analysis.
+const ANALYSIS_RATE: f32 = 11025.0;
+/// The highest frequency searched for the first and second formants.
+const MAX_FORMANT: f32 = 3000.0;
+const PRE_EMPHASIS: f32 = 0.97;
+
+/// The typical first and second formants (Hz) of each vowel.
+const VOWELS: [(f32, f32); 5] = [
+    // aa
+    (800.0, 1300.0),
+    // ih
+    (300.0, 2300.0),
+    // ou
+    (350.0, 1300.0),
+    // ee
+    (500.0, 1900.0),
+    // oh
+    (500.0, 850.0),
+];
+
+/// Analyzes one frame and returns the viseme weights.
+///
+/// `volume_threshold` is the RMS below which the mouth is closed;
+/// the mouth opens fully at 20 dB above it.
+pub(crate) fn analyze(
+    samples: &[f32],
+    sample_rate: u32,
+    volume_threshold: f32,
+) -> VisemeWeights {
+    let volume = volume(samples, volume_threshold);
+    if volume == 0.0 {
+        return VisemeWeights::default();
+    }
+    let factor = ((sample_rate as f32 / ANALYSIS_RATE).round() as usize).max(1);
+    let rate = sample_rate as f32 / factor as f32;
+    let decimated = samples
+        .chunks(factor)
+        .map(|chunk| chunk.iter().sum::<f32>() / chunk.len() as f32)
+        .collect::<Vec<_>>();
+    let order = (2 + (rate / 1000.0) as usize).clamp(8, 18);
+    let Some((f1, f2)) = lpc(&decimated, order).and_then(|a| formants(&a, rate)) else {
+        // Voiced sound whose formants are unclear, such as a consonant.
+        return VisemeWeights {
+            aa: volume * 0.5,
+            ..Default::default()
+        };
+    };
+    let scores = VOWELS.map(|(c1, c2)| {
+        let d1 = (f1 / c1).ln();
+        let d2 = (f2 / c2).ln();
+        (-(d1 * d1 + d2 * d2) / (2.0 * 0.2 * 0.2)).exp()
+    });
+    let total = scores.iter().sum::<f32>().max(f32::EPSILON);
+    let [aa, ih, ou, ee, oh] = scores.map(|score| score / total * volume);
+    VisemeWeights { aa, ih, ou, ee, oh }
+}
+
+fn volume(
+    samples: &[f32],
+    threshold: f32,
+) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+    if rms <= threshold || threshold <= 0.0 {
+        return 0.0;
+    }
+    (rms / threshold).log10().clamp(0.0, 1.0)
+}
+
+/// Returns the LPC coefficients `a[1..=order]` by the autocorrelation method.
+fn lpc(
+    samples: &[f32],
+    order: usize,
+) -> Option<Vec<f32>> {
+    if samples.len() <= order {
+        return None;
+    }
+    let len = samples.len();
+    let windowed = (0..len)
+        .map(|i| {
+            let emphasized =
+                samples[i] - PRE_EMPHASIS * i.checked_sub(1).map_or(0.0, |j| samples[j]);
+            let hamming = 0.54 - 0.46 * (2.0 * PI * i as f32 / (len - 1) as f32).cos();
+            emphasized * hamming
+        })
+        .collect::<Vec<_>>();
+    let r = (0..=order)
+        .map(|lag| {
+            windowed[lag..]
+                .iter()
+                .zip(windowed.iter())
+                .map(|(a, b)| a * b)
+                .sum::<f32>()
+        })
+        .collect::<Vec<_>>();
+    if r[0] <= f32::EPSILON {
+        return None;
+    }
+
+    // Levinson-Durbin recursion
+    let mut a = vec![0.0; order + 1];
+    a[0] = 1.0;
+    let mut error = r[0];
+    for i in 1..=order {
+        let acc = (1..i).map(|j| a[j] * r[i - j]).sum::<f32>();
+        let k = -(r[i] + acc) / error;
+        let previous = a.clone();
+        for j in 1..i {
+            a[j] = previous[j] + k * previous[i - j];
+        }
+        a[i] = k;
+        error *= 1.0 - k * k;
+        if error <= 0.0 {
+            return None;
+        }
+    }
+    Some(a)
+}
+
+/// Finds the first and second formants from the peaks of the LPC envelope.
+fn formants(
+    a: &[f32],
+    rate: f32,
+) -> Option<(f32, f32)> {
+    let max = MAX_FORMANT.min(rate / 2.0);
+    let envelope = |frequency: f32| {
+        let w = 2.0 * PI * frequency / rate;
+        let (re, im) = a.iter().enumerate().fold((0.0, 0.0), |(re, im), (k, ak)| {
+            (
+                re + ak * (w * k as f32).cos(),
+                im - ak * (w * k as f32).sin(),
+            )
+        });
+        1.0 / (re * re + im * im).max(f32::EPSILON)
+    };
+    let step = 10.0;
+    let frequencies = (0..)
+        .map(|i| 100.0 + i as f32 * step)
+        .take_while(|f| *f <= max)
+        .collect::<Vec<_>>();
+    let power = frequencies.iter().map(|f| envelope(*f)).collect::<Vec<_>>();
+    // The two strongest peaks are the first and second formants;
+    // weak bumps between them come from the harmonics of the pitch.
+    let mut peaks = (1..power.len().saturating_sub(1))
+        .filter(|&i| power[i - 1] < power[i] && power[i + 1] <= power[i])
+        .filter(|&i| 200.0 <= frequencies[i])
+        .collect::<Vec<_>>();
+    peaks.sort_by(|a, b| power[*b].total_cmp(&power[*a]));
+    let (&a, &b) = (peaks.first()?, peaks.get(1)?);
+    let (f1, f2) = (frequencies[a.min(b)], frequencies[a.max(b)]);
+    Some((f1, f2))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::vrm::lip_sync::analyzer::analyze;
+    use std::f32::consts::PI;
+
+    /// Synthesizes a vowel by filtering a pulse train through two formant resonators.
+    pub(crate) fn synthesize_vowel(
+        f1: f32,
+        f2: f32,
+        sample_rate: u32,
+        len: usize,
+    ) -> Vec<f32> {
+        let rate = sample_rate as f32;
+        let period = (rate / 120.0) as usize;
+        let mut samples = (0..len)
+            .map(|i| if i % period == 0 { 1.0 } else { 0.0 })
+            .collect::<Vec<f32>>();
+        for (frequency, bandwidth) in [(f1, 80.0), (f2, 100.0)] {
+            let r = (-PI * bandwidth / rate).exp();
+            let c = 2.0 * r * (2.0 * PI * frequency / rate).cos();
+            let (mut y1, mut y2) = (0.0, 0.0);
+            for sample in samples.iter_mut() {
+                let y = *sample + c * y1 - r * r * y2;
+                y2 = y1;
+                y1 = y;
+                *sample = y;
+            }
+        }
+        let peak = samples.iter().fold(0.0_f32, |m, s| m.max(s.abs()));
+        samples.iter().map(|s| s / peak * 0.5).collect()
+    }
+
+    #[test]
+    fn silence_closes_mouth() {
+        let weights = analyze(&[0.0; 512], 16000, 0.01);
+        assert_eq!(weights.iter().map(|(_, w)| w).sum::<f32>(), 0.0);
+    }
+
+    #[test]
+    fn detect_vowels_from_formants() {
+        for (expected, (f1, f2)) in [
+            ("aa", (800.0, 1300.0)),
+            ("ih", (300.0, 2300.0)),
+            ("ou", (350.0, 1300.0)),
+            ("ee", (500.0, 1900.0)),
+            ("oh", (500.0, 850.0)),
+        ] {
+            let samples = synthesize_vowel(f1, f2, 44100, 2048);
+            let weights = analyze(&samples, 44100, 0.01);
+            let (preset, _) = weights
+                .iter()
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .unwrap();
+            assert_eq!(preset.as_str(), expected, "{weights:?}");
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Follows the audio played by `bevy_audio` to feed [`LipSyncInput`].
+
+use crate::vrm::lip_sync::wav::Wav;
+use crate::vrm::lip_sync::LipSyncInput;
+use bevy::audio::{
+    AudioPlayer, AudioSink, AudioSinkPlayback, AudioSource, PlaybackMode, PlaybackSettings,
+};
+use bevy::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Moves the mouth of VRM with the audio played by an [`AudioPlayer`].
+///
+/// Insert this component into the root entity of the VRM with the entity that has the [`AudioPlayer`].
+/// The source is decoded once, and the samples played since the last frame are pushed into [`LipSyncInput`].
+/// The playback position only advances while the [`AudioSink`] is playing and follows its speed,
+/// so pausing or stopping the audio closes the mouth.
+///
+/// This component requires the `audio` feature, and only `.wav` sources are supported.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+///
+/// fn speak(
+///     mut commands: Commands,
+///     asset_server: Res<AssetServer>,
+///     vrm: Query<Entity, With<Vrm>>,
+/// ) {
+///     let player = commands
+///         .spawn(AudioPlayer::new(asset_server.load("speech.wav")))
+///         .id();
+///     commands
+///         .entity(vrm.single().unwrap())
+///         .insert(LipSyncAudio::new(player));
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+#[require(LipSyncInput)]
+pub struct LipSyncAudio {
+    /// The entity that has the [`AudioPlayer`].
+    pub player: Entity,
+    /// The playback position of the audio.
+    position: Duration,
+    followed: Option<Entity>,
+    #[reflect(ignore)]
+    source: Option<DecodedAudio>,
+}
+
+impl LipSyncAudio {
+    pub const fn new(player: Entity) -> Self {
+        Self {
+            player,
+            position: Duration::ZERO,
+            followed: None,
+            source: None,
+        }
+    }
+
+    /// Returns the playback position of the followed audio.
+    #[inline]
+    pub const fn position(&self) -> Duration {
+        self.position
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct DecodedAudio {
+    samples: Arc<[f32]>,
+    sample_rate: u32,
+}
+
+impl DecodedAudio {
+    /// Returns the samples played between `start` and `end`.
+    fn played(
+        &self,
+        start: Duration,
+        end: Duration,
+        looped: bool,
+    ) -> Vec<f32> {
+        let len = self.samples.len();
+        if len == 0 {
+            return Vec::new();
+        }
+        let index =
+            |position: Duration| (position.as_secs_f64() * self.sample_rate as f64) as usize;
+        if looped {
+            (index(start)..index(end))
+                .map(|i| self.samples[i % len])
+                .collect()
+        } else {
+            self.samples[index(start).min(len)..index(end).min(len)].to_vec()
+        }
+    }
+}
+
+pub(super) fn follow_audio(
+    time: Res<Time>,
+    sources: Res<Assets<AudioSource>>,
+    players: Query<(&AudioPlayer, &PlaybackSettings, Option<&AudioSink>)>,
+    mut vrms: Query<(&mut LipSyncAudio, &mut LipSyncInput)>,
+) {
+    for (mut audio, mut input) in vrms.iter_mut() {
+        let Ok((player, settings, sink)) = players.get(audio.player) else {
+            continue;
+        };
+        if audio.followed != Some(audio.player) {
+            audio.followed = Some(audio.player);
+            audio.position = Duration::ZERO;
+            audio.source = None;
+        }
+        if audio.source.is_none() {
+            let Some(source) = sources.get(&player.0) else {
+                continue;
+            };
+            audio.source = Some(match Wav::from_bytes(&source.bytes) {
+                Ok(wav) => DecodedAudio {
+                    samples: wav.samples.into(),
+                    sample_rate: wav.sample_rate,
+                },
+                Err(_e) => {
+                    warn!("Failed to decode the audio for lip sync: {_e}");
+                    DecodedAudio::default()
+                }
+            });
+        }
+        let Some(sink) = sink else {
+            continue;
+        };
+        if sink.is_paused() || sink.empty() {
+            continue;
+        }
+        let Some(source) = audio.source.clone() else {
+            continue;
+        };
+        let speed = sink.speed();
+        // The played samples are consumed at the speed of the sink, which also shifts the formants.
+        input.sample_rate = (source.sample_rate as f32 * speed).round() as u32;
+        let start = audio.position;
+        audio.position += time.delta().mul_f32(speed);
+        let looped = matches!(settings.mode, PlaybackMode::Loop);
+        input.push(&source.played(start, audio.position, looped));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vrm::lip_sync::audio::DecodedAudio;
+    use std::time::Duration;
+
+    fn audio() -> DecodedAudio {
+        DecodedAudio {
+            samples: vec![0.0, 0.1, 0.2, 0.3].into(),
+            sample_rate: 4,
+        }
+    }
+
+    #[test]
+    fn played_samples_stop_at_the_end() {
+        let played = audio().played(
+            Duration::from_millis(500),
+            Duration::from_millis(1500),
+            false,
+        );
+        assert_eq!(played, [0.2, 0.3]);
+    }
+
+    #[test]
+    fn played_samples_wrap_around_if_looped() {
+        let played = audio().played(
+            Duration::from_millis(500),
+            Duration::from_millis(1500),
+            true,
+        );
+        assert_eq!(played, [0.2, 0.3, 0.0, 0.1]);
+    }
+}
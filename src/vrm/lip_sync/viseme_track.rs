@@ -0,0 +1,331 @@
+//! Plays the viseme timings given by TTS engines onto the mouth expressions.
+
+use crate::error::AppResult;
+use crate::vrm::expressions::ProceduralExpressionWeights;
+use crate::vrm::lip_sync::{write_mouth, LipSyncInput, VisemeWeights};
+use bevy::app::{App, Plugin, Update};
+use bevy::asset::io::Reader;
+use bevy::asset::{Asset, AssetApp, AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub(super) struct VisemeTrackPlugin;
+
+impl Plugin for VisemeTrackPlugin {
+    fn build(
+        &self,
+        app: &mut App,
+    ) {
+        app.init_asset::<VisemeTrack>()
+            .register_asset_loader(VisemeTrackLoader)
+            .register_type::<VisemeTrackPlayer>()
+            .register_type::<VisemeClock>()
+            .add_systems(Update, play_viseme_tracks.before(write_mouth))
+            .add_observer(close_mouth);
+    }
+}
+
+/// A mouth shape of the viseme timeline.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Reflect)]
+#[serde(rename_all = "lowercase")]
+pub enum Viseme {
+    Aa,
+    Ih,
+    Ou,
+    Ee,
+    Oh,
+}
+
+/// A keyframe of [`VisemeTrack`].
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+pub struct VisemeKey {
+    /// The time in seconds from the start of the track.
+    pub time: f32,
+    pub viseme: Viseme,
+    /// The weight of the viseme at `time`.
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+const fn default_weight() -> f32 {
+    1.0
+}
+
+/// A timeline of visemes, such as the phoneme timings given by TTS engines.
+///
+/// The asset is loaded from a `.viseme.json` file that contains a list of keys:
+///
+/// ```json
+/// [
+///     { "time": 0.0, "viseme": "aa", "weight": 1.0 },
+///     { "time": 0.2, "viseme": "ih", "weight": 0.8 },
+///     { "time": 0.4, "viseme": "oh", "weight": 0.0 }
+/// ]
+/// ```
+///
+/// Between two keys, the mouth crossfades from the former viseme to the latter.
+/// The last key is held after the end of the track.
+#[derive(Asset, Debug, Clone, Default, PartialEq, Reflect)]
+pub struct VisemeTrack {
+    keys: Vec<VisemeKey>,
+}
+
+impl VisemeTrack {
+    /// Creates a track from the keys; they are sorted by time.
+    pub fn new(mut keys: Vec<VisemeKey>) -> Self {
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { keys }
+    }
+
+    /// Parses the JSON list of keys.
+    pub fn from_json(bytes: &[u8]) -> AppResult<Self> {
+        Ok(Self::new(serde_json::from_slice(bytes)?))
+    }
+
+    /// Returns the keys sorted by time.
+    #[inline]
+    pub fn keys(&self) -> &[VisemeKey] {
+        &self.keys
+    }
+
+    /// Returns the time of the last key.
+    pub fn duration(&self) -> Duration {
+        self.keys
+            .last()
+            .map(|key| Duration::from_secs_f32(key.time.max(0.0)))
+            .unwrap_or_default()
+    }
+
+    /// Returns the mouth weights at the time.
+    pub fn sample(
+        &self,
+        time: Duration,
+    ) -> VisemeWeights {
+        let time = time.as_secs_f32();
+        let next = self.keys.partition_point(|key| key.time <= time);
+        let mut weights = VisemeWeights::default();
+        match (
+            next.checked_sub(1).map(|i| &self.keys[i]),
+            self.keys.get(next),
+        ) {
+            (Some(prev), Some(next)) => {
+                let span = next.time - prev.time;
+                let t = if span <= 0.0 {
+                    1.0
+                } else {
+                    (time - prev.time) / span
+                };
+                *weights.get_mut(prev.viseme) += prev.weight * (1.0 - t);
+                *weights.get_mut(next.viseme) += next.weight * t;
+            }
+            (Some(last), None) => {
+                *weights.get_mut(last.viseme) = last.weight;
+            }
+            (None, _) => {}
+        }
+        weights
+    }
+}
+
+impl VisemeWeights {
+    fn get_mut(
+        &mut self,
+        viseme: Viseme,
+    ) -> &mut f32 {
+        match viseme {
+            Viseme::Aa => &mut self.aa,
+            Viseme::Ih => &mut self.ih,
+            Viseme::Ou => &mut self.ou,
+            Viseme::Ee => &mut self.ee,
+            Viseme::Oh => &mut self.oh,
+        }
+    }
+}
+
+#[derive(Default, TypePath)]
+struct VisemeTrackLoader;
+
+impl AssetLoader for VisemeTrackLoader {
+    type Asset = VisemeTrack;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _: &Self::Settings,
+        _: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        VisemeTrack::from_json(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["viseme.json"]
+    }
+}
+
+/// The clock that [`VisemeTrackPlayer`] follows.
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+pub enum VisemeClock {
+    /// Advances with [`Time`].
+    Time,
+    /// The playback position given by the user, such as the position of the audio.
+    Manual(Duration),
+}
+
+/// Plays [`VisemeTrack`] onto the `aa`, `ih`, `ou`, `ee` and `oh` expressions.
+///
+/// Insert this component into the root entity of the VRM.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+///
+/// fn speak(
+///     mut commands: Commands,
+///     asset_server: Res<AssetServer>,
+///     vrm: Query<Entity, With<Vrm>>,
+/// ) {
+///     commands
+///         .entity(vrm.single().unwrap())
+///         .insert(VisemeTrackPlayer::new(asset_server.load("speech.viseme.json")));
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct VisemeTrackPlayer {
+    pub track: Handle<VisemeTrack>,
+    pub clock: VisemeClock,
+    elapsed: Duration,
+    weights: VisemeWeights,
+}
+
+impl VisemeTrackPlayer {
+    /// Creates a player that follows [`Time`].
+    pub fn new(track: Handle<VisemeTrack>) -> Self {
+        Self {
+            track,
+            clock: VisemeClock::Time,
+            elapsed: Duration::ZERO,
+            weights: VisemeWeights::default(),
+        }
+    }
+
+    /// Sets the clock.
+    pub fn with_clock(
+        mut self,
+        clock: VisemeClock,
+    ) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns the current playback position.
+    #[inline]
+    pub const fn elapsed(&self) -> Duration {
+        match self.clock {
+            VisemeClock::Time => self.elapsed,
+            VisemeClock::Manual(position) => position,
+        }
+    }
+
+    /// Returns the weights of the mouth expressions at the current playback position.
+    #[inline]
+    pub const fn weights(&self) -> VisemeWeights {
+        self.weights
+    }
+}
+
+pub(super) fn play_viseme_tracks(
+    time: Res<Time>,
+    tracks: Res<Assets<VisemeTrack>>,
+    mut players: Query<&mut VisemeTrackPlayer>,
+) {
+    for mut player in players.iter_mut() {
+        let Some(track) = tracks.get(player.track.id()) else {
+            continue;
+        };
+        if player.clock == VisemeClock::Time {
+            player.elapsed += time.delta();
+        }
+        player.weights = track.sample(player.elapsed());
+    }
+}
+
+/// Leaves the mouth to [`LipSyncInput`] if it is still moving the mouth, otherwise closes it.
+fn close_mouth(
+    trigger: Trigger<OnRemove, VisemeTrackPlayer>,
+    mut vrms: Query<(Option<&LipSyncInput>, &mut ProceduralExpressionWeights)>,
+) {
+    if let Ok((input, mut procedural)) = vrms.get_mut(trigger.target()) {
+        input
+            .map(LipSyncInput::weights)
+            .unwrap_or_default()
+            .write_to(&mut procedural);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::success;
+    use crate::tests::{test_app, TestResult};
+    use crate::vrm::expressions::ProceduralExpressionWeights;
+    use crate::vrm::lip_sync::LipSyncPlugin;
+    use bevy::prelude::*;
+    use std::time::Duration;
+
+    fn track() -> TestResult<VisemeTrack> {
+        Ok(VisemeTrack::from_json(
+            br#"[
+                { "time": 0.5, "viseme": "ih", "weight": 0.0 },
+                { "time": 0.0, "viseme": "aa" }
+            ]"#,
+        )?)
+    }
+
+    #[test]
+    fn sample_crossfades_between_keys() -> TestResult {
+        let track = track()?;
+        assert_eq!(track.duration(), Duration::from_millis(500));
+        let weights = track.sample(Duration::from_millis(250));
+        assert_eq!(weights.aa, 0.5);
+        assert_eq!(weights.ih, 0.0);
+        assert_eq!(track.sample(Duration::ZERO).aa, 1.0);
+        assert_eq!(
+            track.sample(Duration::from_secs(1)),
+            VisemeWeights::default()
+        );
+        success!()
+    }
+
+    #[test]
+    fn play_with_manual_clock() -> TestResult {
+        let mut app = test_app();
+        app.add_plugins(LipSyncPlugin);
+        let handle = app
+            .world_mut()
+            .resource_mut::<Assets<VisemeTrack>>()
+            .add(track()?);
+        let vrm = app
+            .world_mut()
+            .spawn((
+                VisemeTrackPlayer::new(handle)
+                    .with_clock(VisemeClock::Manual(Duration::from_millis(100))),
+                ProceduralExpressionWeights::default(),
+            ))
+            .id();
+        app.update();
+        let aa = app
+            .world()
+            .get::<ProceduralExpressionWeights>(vrm)
+            .unwrap()
+            .get(&VrmExpression::from("aa"))
+            .copied();
+        assert_eq!(aa, Some(0.8));
+        success!()
+    }
+}
@@ -0,0 +1,178 @@
+//! Decodes the RIFF WAVE (`.wav`) container into mono samples.
+//!
+//! - [`WAVE format`](http://soundfile.sapp.org/doc/WaveFormat/)
+
+use crate::error::AppResult;
+use anyhow::{bail, Context};
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_IEEE_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// The decoded contents of a `.wav` file.
+pub(crate) struct Wav {
+    /// The samples in `-1.0..=1.0`, with the channels averaged into mono.
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+impl Wav {
+    pub fn from_bytes(bytes: &[u8]) -> AppResult<Self> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            bail!("Not a wav file");
+        }
+        let mut offset = 12;
+        let mut format = None;
+        let mut data = None;
+        while offset + 8 <= bytes.len() {
+            let id = &bytes[offset..offset + 4];
+            let len = read_u32(bytes, offset + 4)? as usize;
+            let start = offset + 8;
+            let chunk = bytes
+                .get(start..(start + len).min(bytes.len()))
+                .context("wav chunk exceeds the file length")?;
+            match id {
+                b"fmt " => format = Some(Format::new(chunk)?),
+                b"data" => data = Some(chunk),
+                _ => {}
+            }
+            // Chunks are aligned to 2 bytes.
+            offset = start + len + (len & 1);
+        }
+        let format = format.context("Not found wav fmt chunk")?;
+        let data = data.context("Not found wav data chunk")?;
+        Ok(Self {
+            samples: format.decode(data)?,
+            sample_rate: format.sample_rate,
+        })
+    }
+
+    /// Encodes mono samples as 16-bit PCM.
+    #[cfg(test)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let data = self
+            .samples
+            .iter()
+            .flat_map(|s| ((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes())
+            .collect::<Vec<_>>();
+        let mut bytes = Vec::with_capacity(44 + data.len());
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16_u32.to_le_bytes());
+        bytes.extend_from_slice(&FORMAT_PCM.to_le_bytes());
+        bytes.extend_from_slice(&1_u16.to_le_bytes());
+        bytes.extend_from_slice(&self.sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(self.sample_rate * 2).to_le_bytes());
+        bytes.extend_from_slice(&2_u16.to_le_bytes());
+        bytes.extend_from_slice(&16_u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&data);
+        bytes
+    }
+}
+
+struct Format {
+    tag: u16,
+    channels: usize,
+    sample_rate: u32,
+    bits_per_sample: usize,
+}
+
+impl Format {
+    fn new(chunk: &[u8]) -> AppResult<Self> {
+        if chunk.len() < 16 {
+            bail!("wav fmt chunk is too short");
+        }
+        let mut tag = read_u16(chunk, 0)?;
+        if tag == FORMAT_EXTENSIBLE && 26 <= chunk.len() {
+            // The first two bytes of the sub format GUID hold the actual format.
+            tag = read_u16(chunk, 24)?;
+        }
+        Ok(Self {
+            tag,
+            channels: read_u16(chunk, 2)?.max(1) as usize,
+            sample_rate: read_u32(chunk, 4)?,
+            bits_per_sample: read_u16(chunk, 14)? as usize,
+        })
+    }
+
+    fn decode(
+        &self,
+        data: &[u8],
+    ) -> AppResult<Vec<f32>> {
+        let sample = |bytes: &[u8]| -> AppResult<f32> {
+            Ok(match (self.tag, self.bits_per_sample) {
+                (FORMAT_PCM, 8) => (bytes[0] as f32 - 128.0) / 128.0,
+                (FORMAT_PCM, 16) => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+                (FORMAT_PCM, 24) => {
+                    i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) as f32 / 2_147_483_648.0
+                }
+                (FORMAT_PCM, 32) => {
+                    i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32
+                        / 2_147_483_648.0
+                }
+                (FORMAT_IEEE_FLOAT, 32) => {
+                    f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+                }
+                (tag, bits) => bail!("Unsupported wav format: tag {tag}, {bits} bits"),
+            })
+        };
+        let sample_len = self.bits_per_sample / 8;
+        if sample_len == 0 {
+            bail!("Unsupported wav format: {} bits", self.bits_per_sample);
+        }
+        data.chunks_exact(sample_len * self.channels)
+            .map(|frame| {
+                let sum = frame
+                    .chunks_exact(sample_len)
+                    .map(sample)
+                    .sum::<AppResult<f32>>()?;
+                Ok(sum / self.channels as f32)
+            })
+            .collect()
+    }
+}
+
+fn read_u16(
+    bytes: &[u8],
+    offset: usize,
+) -> AppResult<u16> {
+    let b = bytes
+        .get(offset..offset + 2)
+        .context("Unexpected end of wav")?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(
+    bytes: &[u8],
+    offset: usize,
+) -> AppResult<u32> {
+    let b = bytes
+        .get(offset..offset + 4)
+        .context("Unexpected end of wav")?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::success;
+    use crate::tests::TestResult;
+    use crate::vrm::lip_sync::wav::Wav;
+
+    #[test]
+    fn round_trip_pcm16() -> TestResult {
+        let wav = Wav {
+            samples: vec![0.0, 0.5, -0.5],
+            sample_rate: 16000,
+        };
+        let decoded = Wav::from_bytes(&wav.to_bytes())?;
+        assert_eq!(decoded.sample_rate, 16000);
+        for (a, b) in decoded.samples.iter().zip(wav.samples.iter()) {
+            assert!((a - b).abs() < 1e-3);
+        }
+        assert!(Wav::from_bytes(b"not a wav").is_err());
+        success!()
+    }
+}
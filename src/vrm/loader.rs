@@ -1,11 +1,21 @@
+use crate::error::AppResult;
+use crate::glb::Glb;
+use crate::vrm::gltf::extensions::vrm0::{convert_document, is_vrm0};
+use anyhow::Context;
 use bevy::app::{App, Plugin};
-use bevy::asset::io::Reader;
+use bevy::asset::io::{Reader, VecReader};
+use bevy::asset::RenderAssetUsages;
 use bevy::asset::{Asset, AssetLoader, Handle, LoadContext};
 use bevy::gltf::{Gltf, GltfAssetLabel, GltfError, GltfLoader, GltfLoaderSettings};
-use bevy::image::{CompressedImageFormats, Image};
+use bevy::image::{
+    CompressedImageFormats, Image, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor,
+};
+use bevy::log::warn;
+use bevy::platform::collections::HashSet;
 use bevy::prelude::{AssetApp, Component, TypePath};
 use bevy::render::renderer::RenderDevice;
 use bevy::utils::default;
+use serde::{Deserialize, Serialize};
 
 pub struct VrmLoaderPlugin;
 
@@ -35,10 +45,13 @@ impl Plugin for VrmLoaderPlugin {
 /// A handle to load a VRM.
 /// This component is removed after the VRM is loaded.
 ///
+/// VRM 0.x files are also supported; they are converted into the VRM 1.0 structures while loading.
+///
 /// This handle is used to load a VRM, and after it is loaded, the following components are automatically inserted:
 ///
 /// - [`Vrm`](crate::prelude::Vrm)
 /// - [`VrmPath`](crate::prelude::VrmPath)
+/// - [`VrmMeta`](crate::prelude::VrmMeta)
 /// - [`BoneRestTransform`](crate::prelude::BoneRestTransform)
 /// - [`BoneRestGlobalTransform`](crate::prelude::BoneRestGlobalTransform)
 /// - [`SceneRoot`](bevy::scene::SceneRoot)
@@ -58,29 +71,174 @@ impl Plugin for VrmLoaderPlugin {
 #[derive(Debug, Component)]
 pub struct VrmHandle(pub Handle<VrmAsset>);
 
+/// Holds the handle of the loaded VRM asset.
+///
+/// This component is inserted into the VRM entity instead of the removed [`VrmHandle`].
+#[derive(Debug, Component, Clone)]
+pub(crate) struct VrmAssetHandle(pub Handle<VrmAsset>);
+
+/// The label of the thumbnail image sub-asset.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+///
+/// fn load_thumbnail(asset_server: Res<AssetServer>) {
+///     let thumbnail: Handle<Image> = asset_server.load("<vrm>.vrm#Thumbnail");
+/// }
+/// ```
+pub const VRM_THUMBNAIL_LABEL: &str = "Thumbnail";
+
+/// The settings of loading VRM.
+///
+/// Each subsystem can be disabled per asset, for example for background avatars that don't need spring bones.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+///
+/// fn spawn_crowd(
+///     mut commands: Commands,
+///     asset_server: Res<AssetServer>,
+/// ) {
+///     let handle = asset_server.load_with_settings("<vrm>.vrm", |settings: &mut VrmLoaderSettings| {
+///         settings.spring_bone = false;
+///         settings.mtoon = false;
+///     });
+///     commands.spawn(VrmHandle(handle));
+/// }
+/// ```
+#[derive(Serialize, Deserialize)]
+pub struct VrmLoaderSettings {
+    /// Whether to set up the spring bones from `VRMC_springBone`.
+    pub spring_bone: bool,
+    /// Whether to convert materials with `VRMC_materials_mtoon` into [`MToonMaterial`](crate::prelude::MToonMaterial).
+    ///
+    /// If `false`, the meshes keep [`StandardMaterial`](bevy::prelude::StandardMaterial).
+    pub mtoon: bool,
+    /// Whether to insert [`LookAtProperties`](crate::prelude::LookAtProperties) from `VRMC_vrm::lookAt`.
+    pub look_at: bool,
+    /// Whether to register the expressions from `VRMC_vrm::expressions`.
+    pub expressions: bool,
+    /// If specified, overrides the sampler of all textures embedded in the VRM.
+    ///
+    /// The sampler is applied when the textures are loaded, so it only affects this asset.
+    pub sampler: Option<ImageSamplerDescriptor>,
+    /// The settings passed to [`GltfLoader`].
+    ///
+    /// `include_source` is always enabled because the VRM extensions are read from the source.
+    pub gltf: GltfLoaderSettings,
+}
+
+impl Default for VrmLoaderSettings {
+    fn default() -> Self {
+        Self {
+            spring_bone: true,
+            mtoon: true,
+            look_at: true,
+            expressions: true,
+            sampler: None,
+            gltf: GltfLoaderSettings {
+                include_source: true,
+                ..default()
+            },
+        }
+    }
+}
+
+impl std::fmt::Debug for VrmLoaderSettings {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        f.debug_struct("VrmLoaderSettings")
+            .field("spring_bone", &self.spring_bone)
+            .field("mtoon", &self.mtoon)
+            .field("look_at", &self.look_at)
+            .field("expressions", &self.expressions)
+            .field("sampler", &self.sampler)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Clone for VrmLoaderSettings {
+    fn clone(&self) -> Self {
+        Self {
+            spring_bone: self.spring_bone,
+            mtoon: self.mtoon,
+            look_at: self.look_at,
+            expressions: self.expressions,
+            sampler: self.sampler.clone(),
+            gltf: self.gltf_settings(),
+        }
+    }
+}
+
+impl VrmLoaderSettings {
+    fn gltf_settings(&self) -> GltfLoaderSettings {
+        GltfLoaderSettings {
+            load_meshes: self.gltf.load_meshes,
+            load_materials: self.gltf.load_materials,
+            load_cameras: self.gltf.load_cameras,
+            load_lights: self.gltf.load_lights,
+            include_source: true,
+        }
+    }
+}
+
 #[derive(Debug, Asset, TypePath)]
 pub struct VrmAsset {
     pub(crate) gltf: Gltf,
     pub(crate) images: Vec<Handle<Image>>,
+    pub(crate) thumbnail: Option<Handle<Image>>,
+    pub(crate) settings: VrmLoaderSettings,
+}
+
+impl VrmAsset {
+    /// Returns the thumbnail image specified in `VRMC_vrm::meta::thumbnailImage`.
+    ///
+    /// The same image can also be loaded directly with the [`VRM_THUMBNAIL_LABEL`] label.
+    #[inline]
+    pub fn thumbnail(&self) -> Option<&Handle<Image>> {
+        self.thumbnail.as_ref()
+    }
 }
 
 struct VrmLoader(GltfLoader);
 
 impl AssetLoader for VrmLoader {
     type Asset = VrmAsset;
-    type Settings = ();
+    type Settings = VrmLoaderSettings;
     type Error = GltfError;
     async fn load(
         &self,
         reader: &mut dyn Reader,
-        _: &Self::Settings,
+        settings: &Self::Settings,
         load_context: &mut LoadContext<'_>,
     ) -> Result<Self::Asset, Self::Error> {
-        let settings = GltfLoaderSettings {
-            include_source: true,
-            ..default()
-        };
-        let gltf = self.0.load(reader, &settings, load_context).await?;
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut glb = None;
+        let mut thumbnail = None;
+        if Glb::is_glb(&bytes) {
+            let mut document = Glb::from_bytes(&bytes).map_err(std::io::Error::other)?;
+            if is_vrm0(&document.json) {
+                convert_document(&mut document).map_err(std::io::Error::other)?;
+                bytes = document.to_bytes().map_err(std::io::Error::other)?;
+            }
+            thumbnail = load_thumbnail(&document, load_context).await;
+            glb = Some(document);
+        }
+        let gltf = self
+            .0
+            .load(
+                &mut VecReader::new(bytes),
+                &settings.gltf_settings(),
+                load_context,
+            )
+            .await?;
+        if let (Some(sampler), Some(glb)) = (settings.sampler.as_ref(), glb.as_ref()) {
+            override_samplers(glb, sampler, settings.gltf.load_materials, load_context).await;
+        }
         Ok(VrmAsset {
             images: gltf
                 .source
@@ -92,6 +250,8 @@ impl AssetLoader for VrmLoader {
                 })
                 .collect(),
             gltf,
+            thumbnail,
+            settings: settings.clone(),
         })
     }
 
@@ -99,3 +259,209 @@ impl AssetLoader for VrmLoader {
         &["vrm"]
     }
 }
+
+async fn load_thumbnail(
+    glb: &Glb,
+    load_context: &mut LoadContext<'_>,
+) -> Option<Handle<Image>> {
+    let (bytes, extension) = match thumbnail_bytes(glb) {
+        Ok(Some(thumbnail)) => thumbnail,
+        Ok(None) => return None,
+        Err(e) => {
+            warn!("Failed to read the VRM thumbnail: {e}");
+            return None;
+        }
+    };
+    let loaded = load_context
+        .loader()
+        .immediate()
+        .with_reader(&mut VecReader::new(bytes.to_vec()))
+        .load::<Image>(format!("{VRM_THUMBNAIL_LABEL}.{extension}"))
+        .await;
+    match loaded {
+        Ok(image) => {
+            Some(load_context.add_loaded_labeled_asset(VRM_THUMBNAIL_LABEL.to_string(), image))
+        }
+        Err(e) => {
+            warn!("Failed to load the VRM thumbnail: {e}");
+            None
+        }
+    }
+}
+
+/// Reloads the embedded textures with [`VrmLoaderSettings::sampler`].
+///
+/// [`GltfLoader`] has already loaded them with the samplers of the glTF,
+/// so they are replaced with the labeled assets of the same labels.
+async fn override_samplers(
+    glb: &Glb,
+    sampler: &ImageSamplerDescriptor,
+    asset_usage: RenderAssetUsages,
+    load_context: &mut LoadContext<'_>,
+) {
+    let Some(textures) = glb.json["textures"].as_array() else {
+        return;
+    };
+    let linear = linear_textures(&glb.json);
+    for (index, texture) in textures.iter().enumerate() {
+        let Some(source) = texture["source"].as_u64() else {
+            continue;
+        };
+        let (bytes, extension) = match image_bytes(glb, source as usize) {
+            Ok(Some(image)) => image,
+            Ok(None) => {
+                warn!(
+                    "The sampler of texture {index} is not overridden because it is not embedded"
+                );
+                continue;
+            }
+            Err(e) => {
+                warn!("Failed to read texture {index}: {e}");
+                continue;
+            }
+        };
+        let is_srgb = !linear.contains(&index);
+        let sampler = sampler.clone();
+        let loaded = load_context
+            .loader()
+            .with_settings(move |settings: &mut ImageLoaderSettings| {
+                settings.is_srgb = is_srgb;
+                settings.sampler = ImageSampler::Descriptor(sampler.clone());
+                settings.asset_usage = asset_usage;
+            })
+            .immediate()
+            .with_reader(&mut VecReader::new(bytes.to_vec()))
+            .load::<Image>(format!("texture{index}.{extension}"))
+            .await;
+        match loaded {
+            Ok(image) => {
+                load_context
+                    .add_loaded_labeled_asset(GltfAssetLabel::Texture(index).to_string(), image);
+            }
+            Err(e) => {
+                warn!("Failed to override the sampler of texture {index}: {e}");
+            }
+        }
+    }
+}
+
+/// Returns the indices of the textures that hold non-color data, the same as [`GltfLoader`].
+fn linear_textures(json: &serde_json::Value) -> HashSet<usize> {
+    let mut linear = HashSet::new();
+    for material in json["materials"].as_array().into_iter().flatten() {
+        let textures = [
+            &material["normalTexture"],
+            &material["occlusionTexture"],
+            &material["pbrMetallicRoughness"]["metallicRoughnessTexture"],
+        ];
+        linear.extend(
+            textures
+                .into_iter()
+                .filter_map(|texture| texture["index"].as_u64())
+                .map(|index| index as usize),
+        );
+    }
+    linear
+}
+
+/// Returns the encoded bytes and the file extension of the thumbnail image.
+fn thumbnail_bytes(glb: &Glb) -> AppResult<Option<(&[u8], &'static str)>> {
+    let Some(image_index) = glb.json["extensions"]["VRMC_vrm"]["meta"]["thumbnailImage"].as_u64()
+    else {
+        return Ok(None);
+    };
+    image_bytes(glb, image_index as usize)
+}
+
+/// Returns the encoded bytes and the file extension of the image embedded in the binary chunk.
+fn image_bytes(
+    glb: &Glb,
+    image_index: usize,
+) -> AppResult<Option<(&[u8], &'static str)>> {
+    let json = &glb.json;
+    let image = json["images"]
+        .get(image_index)
+        .context("Invalid image index")?;
+    let extension = match image["mimeType"].as_str() {
+        Some("image/jpeg") => "jpg",
+        _ => "png",
+    };
+    let Some(view_index) = image["bufferView"].as_u64() else {
+        // The image is an external file or a data URI, which VRM does not use.
+        return Ok(None);
+    };
+    let view = json["bufferViews"]
+        .get(view_index as usize)
+        .context("Invalid image buffer view")?;
+    let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+    let length = view["byteLength"]
+        .as_u64()
+        .context("Not found byteLength")? as usize;
+    let bytes = glb
+        .bin
+        .as_ref()
+        .and_then(|bin| bin.get(offset..offset + length))
+        .context("The image buffer view exceeds the binary chunk")?;
+    Ok(Some((bytes, extension)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::glb::Glb;
+    use crate::success;
+    use crate::tests::TestResult;
+    use crate::vrm::loader::{linear_textures, thumbnail_bytes, VrmLoaderSettings};
+    use bevy::platform::collections::HashSet;
+    use serde_json::json;
+
+    #[test]
+    fn read_thumbnail_bytes() -> TestResult {
+        let glb = Glb {
+            json: json!({
+                "images": [{"bufferView": 0, "mimeType": "image/jpeg"}],
+                "bufferViews": [{"buffer": 0, "byteOffset": 2, "byteLength": 3}],
+                "extensions": {"VRMC_vrm": {"meta": {"thumbnailImage": 0}}}
+            }),
+            bin: Some(vec![0, 1, 2, 3, 4, 5]),
+        };
+        assert_eq!(thumbnail_bytes(&glb)?, Some((&[2, 3, 4][..], "jpg")));
+        success!()
+    }
+
+    #[test]
+    fn non_color_textures_are_linear() {
+        let json = json!({
+            "materials": [
+                {
+                    "pbrMetallicRoughness": {
+                        "baseColorTexture": {"index": 0},
+                        "metallicRoughnessTexture": {"index": 1}
+                    },
+                    "normalTexture": {"index": 2}
+                },
+                {"occlusionTexture": {"index": 3}, "emissiveTexture": {"index": 4}}
+            ]
+        });
+        assert_eq!(linear_textures(&json), HashSet::from([1, 2, 3]));
+    }
+
+    #[test]
+    fn no_thumbnail() -> TestResult {
+        let glb = Glb {
+            json: json!({"extensions": {"VRMC_vrm": {"meta": {}}}}),
+            bin: None,
+        };
+        assert_eq!(thumbnail_bytes(&glb)?, None);
+        success!()
+    }
+
+    #[test]
+    fn always_include_gltf_source() {
+        let mut settings = VrmLoaderSettings::default();
+        settings.gltf.include_source = false;
+        settings.gltf.load_cameras = false;
+        let gltf = settings.clone().gltf_settings();
+        assert!(gltf.include_source);
+        assert!(!gltf.load_cameras);
+    }
+}
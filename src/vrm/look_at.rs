@@ -1,18 +1,36 @@
 //! - [`look at specification(en)`](https://github.com/vrm-c/vrm-specification/blob/master/specification/VRMC_vrm-1.0/lookAt.md)
 //! - [`look at specification(ja)`](https://github.com/vrm-c/vrm-specification/blob/master/specification/VRMC_vrm-1.0/lookAt.ja.md)
 
+mod body;
+mod candidates;
+mod settings;
+mod weight;
+
 use crate::prelude::*;
 use crate::system_set::VrmSystemSets;
+use crate::vrm::expressions::{
+    mix_expressions, MixedExpressionWeights, ProceduralExpressionWeights,
+};
 use bevy::app::{App, Plugin};
 use bevy::prelude::*;
 use bevy::render::camera::RenderTarget;
 use bevy::window::{PrimaryWindow, WindowRef};
+pub(crate) use weight::EyePose;
+
+pub use body::{LookAtBody, LookAtBoneShare};
+pub use candidates::{LookAtCandidate, LookAtCandidates};
+pub use settings::{LookAtSettings, Saccade};
+pub use weight::{LookAtFade, LookAtWeight};
 
 /// Holds the entity of looking the target entity.
 /// This component should be inserted into the root entity of the VRM.
 ///
 /// [`LookAt::Cursor`] is used to look at the mouse cursor in the window.
 /// [`LookAt::Target`] is used to look at the specified entity.
+/// [`LookAt::Position`] and [`LookAt::Direction`] are used to look at a point or direction given directly.
+/// [`LookAt::Camera`] is used to look into the lens of the camera.
+///
+/// To switch between several targets automatically, use [`LookAtCandidates`].
 ///
 /// ```no_run
 /// use bevy::prelude::*;
@@ -31,7 +49,7 @@ use bevy::window::{PrimaryWindow, WindowRef};
 ///     ));
 /// }
 /// ```
-#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
 #[reflect(Component)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
@@ -42,7 +60,19 @@ pub enum LookAt {
     Cursor { camera: Option<Entity> },
 
     /// Specify the entity of the target.
+    /// The target is looked at by its [`GlobalTransform`], so it can be a child of other entities.
     Target(Entity),
+
+    /// Look at the position in the world space.
+    Position(Vec3),
+
+    /// Look in the direction in the local space of the VRM, such as the output of an eye tracker.
+    ///
+    /// `Dir3::Z` is the front of the VRM.
+    Direction(Dir3),
+
+    /// Look into the lens of the camera entity.
+    Camera(Entity),
 }
 
 pub(super) struct LookAtPlugin;
@@ -53,28 +83,74 @@ impl Plugin for LookAtPlugin {
         app: &mut App,
     ) {
         app.register_type::<LookAt>()
+            .register_type::<LookAtSettings>()
+            .register_type::<LookAtWeight>()
+            .register_type::<LookAtFade>()
+            .register_type::<EyePose>()
+            .register_type::<LookAtBody>()
+            .register_type::<LookAtCandidates>()
+            .register_type::<LookAtCandidate>()
+            .register_type::<LookAtBoneShare>()
+            .register_type::<Saccade>()
             .register_type::<LookAtProperties>()
             .register_type::<LookAtType>()
-            .add_systems(Update, track_looking_target.in_set(VrmSystemSets::LookAt));
+            .add_systems(
+                Update,
+                (
+                    weight::fade_look_at_weight,
+                    candidates::choose_candidates,
+                    track_looking_target,
+                )
+                    .chain()
+                    .in_set(VrmSystemSets::LookAt)
+                    .after(VrmSystemSets::Retarget)
+                    .before(mix_expressions),
+            )
+            .add_observer(reset_look_at_expressions)
+            .add_observer(body::restore_body);
     }
 }
 
 fn track_looking_target(
     par_commands: ParallelCommands,
-    vrms: Query<(
+    time: Res<Time>,
+    mut vrms: Query<(
+        Entity,
         &LookAt,
+        Option<&mut LookAtSettings>,
+        Option<&mut LookAtBody>,
         &LookAtProperties,
         &HeadBoneEntity,
+        Option<&NeckBoneEntity>,
+        Option<&UpperChestBoneEntity>,
         &LeftEyeBoneEntity,
         &RightEyeBoneEntity,
+        Option<&MixedExpressionWeights>,
+        Option<&mut ProceduralExpressionWeights>,
+        Option<(&LookAtWeight, &mut EyePose)>,
     )>,
     cameras: Query<(Entity, &Camera)>,
     transforms: Query<&Transform>,
     global_transforms: Query<&GlobalTransform>,
     windows: Query<(&Window, Has<PrimaryWindow>)>,
+    parents: Query<&ChildOf>,
 ) {
-    vrms.par_iter()
-        .for_each(|(look_at, properties, head, left_eye, right_eye)| {
+    vrms.par_iter_mut().for_each(
+        |(
+            entity,
+            look_at,
+            settings,
+            body,
+            properties,
+            head,
+            neck,
+            upper_chest,
+            left_eye,
+            right_eye,
+            mixed,
+            procedural,
+            blend,
+        )| {
             let Ok(head_gtf) = global_transforms.get(head.0) else {
                 return;
             };
@@ -86,19 +162,59 @@ fn track_looking_target(
             look_at_space_tf.translation = Vec3::from(properties.offset_from_head_bone);
             look_at_space_tf.rotation = head_tf.rotation.inverse();
             let look_at_space = head_gtf.mul_transform(look_at_space_tf);
-            let Some(target) = calc_target_position(
+            let angles = calc_target_position(
                 look_at,
+                entity,
                 head.0,
-                &transforms,
+                &look_at_space,
                 &global_transforms,
                 &cameras,
                 &windows,
-            ) else {
-                return;
+            )
+            .map(|target| calc_yaw_pitch(&look_at_space, target));
+            let (angles, reach) = match &body {
+                Some(body) => {
+                    let (applied_yaw, applied_pitch) = body.applied_below_head();
+                    (
+                        angles.map(|(yaw, pitch)| (yaw + applied_yaw, pitch + applied_pitch)),
+                        body.reach(),
+                    )
+                }
+                None => (angles, Vec2::ZERO),
+            };
+            let (yaw, pitch) = match (settings, angles) {
+                (Some(mut settings), angles) => {
+                    settings.advance(entity, angles, properties, reach, time.delta())
+                }
+                (None, Some(angles)) => angles,
+                (None, None) => return,
+            };
+            let (yaw, pitch) = match body {
+                Some(mut body) => body.distribute(
+                    &par_commands,
+                    [
+                        Some(head.0),
+                        neck.map(|neck| neck.0),
+                        upper_chest.map(|upper_chest| upper_chest.0),
+                    ],
+                    global_transforms
+                        .get(entity)
+                        .map(|gtf| gtf.rotation())
+                        .unwrap_or_default(),
+                    yaw,
+                    pitch,
+                    blend.as_ref().map(|(weight, _)| weight.0).unwrap_or(1.0),
+                    &transforms,
+                    &global_transforms,
+                    &parents,
+                ),
+                None => (yaw, pitch),
             };
-            let (yaw, pitch) = calc_yaw_pitch(&look_at_space, target);
             match properties.r#type {
                 LookAtType::Bone => {
+                    // Expressions with `overrideLookAt` suppress the eye movement.
+                    let multiplier = mixed.map(|mixed| mixed.look_at).unwrap_or(1.0);
+                    let (yaw, pitch) = (yaw * multiplier, pitch * multiplier);
                     apply_bone(
                         &par_commands,
                         &transforms,
@@ -107,19 +223,49 @@ fn track_looking_target(
                         properties,
                         yaw,
                         pitch,
+                        blend,
                     );
                 }
                 LookAtType::Expression => {
-                    todo!("Expression look at is not supported yet");
+                    // The expressions are suppressed by `overrideLookAt` when they are mixed.
+                    let Some(mut procedural) = procedural else {
+                        return;
+                    };
+                    let blend_weight = blend.map(|(weight, _)| weight.0).unwrap_or(1.0);
+                    for (preset, weight) in expression_weights(properties, yaw, pitch) {
+                        let weight = weight * blend_weight.clamp(0.0, 1.0);
+                        let expression = VrmExpression::from(preset);
+                        if procedural.get(&expression).copied().unwrap_or_default() != weight {
+                            procedural.insert(expression, weight);
+                        }
+                    }
                 }
             }
-        });
+        },
+    );
+}
+
+fn reset_look_at_expressions(
+    trigger: Trigger<OnRemove, LookAt>,
+    mut vrms: Query<&mut ProceduralExpressionWeights>,
+) {
+    if let Ok(mut procedural) = vrms.get_mut(trigger.target()) {
+        for preset in [
+            VrmExpressionPreset::LookLeft,
+            VrmExpressionPreset::LookRight,
+            VrmExpressionPreset::LookUp,
+            VrmExpressionPreset::LookDown,
+        ] {
+            procedural.remove(&VrmExpression::from(preset));
+        }
+    }
 }
 
 fn calc_target_position(
     look_at: &LookAt,
     vrm_entity: Entity,
-    transforms: &Query<&Transform>,
+    head_entity: Entity,
+    look_at_space: &GlobalTransform,
     global_transforms: &Query<&GlobalTransform>,
     cameras: &Query<(Entity, &Camera)>,
     windows: &Query<(&Window, Has<PrimaryWindow>)>,
@@ -128,7 +274,7 @@ fn calc_target_position(
         LookAt::Cursor { camera } => match camera {
             Some(camera_entity) => calc_look_at_cursor_position(
                 *camera_entity,
-                vrm_entity,
+                head_entity,
                 global_transforms,
                 cameras,
                 windows,
@@ -136,14 +282,22 @@ fn calc_target_position(
             None => cameras.iter().find_map(|(camera_entity, _)| {
                 calc_look_at_cursor_position(
                     camera_entity,
-                    vrm_entity,
+                    head_entity,
                     global_transforms,
                     cameras,
                     windows,
                 )
             }),
         },
-        LookAt::Target(target_entity) => transforms.get(*target_entity).map(|t| t.translation).ok(),
+        LookAt::Target(entity) | LookAt::Camera(entity) => global_transforms
+            .get(*entity)
+            .map(GlobalTransform::translation)
+            .ok(),
+        LookAt::Position(position) => Some(*position),
+        LookAt::Direction(direction) => {
+            let vrm_gtf = global_transforms.get(vrm_entity).ok()?;
+            Some(look_at_space.translation() + vrm_gtf.rotation() * direction.as_vec3())
+        }
     }
 }
 
@@ -155,6 +309,7 @@ fn apply_bone(
     properties: &LookAtProperties,
     yaw: f32,
     pitch: f32,
+    blend: Option<(&LookAtWeight, Mut<EyePose>)>,
 ) {
     let Ok(left_eye_tf) = transforms.get(left_eye.0) else {
         return;
@@ -162,8 +317,22 @@ fn apply_bone(
     let Ok(right_eye_tf) = transforms.get(right_eye.0) else {
         return;
     };
-    let applied_left_eye_tf = apply_left_eye_bone(left_eye_tf, properties, yaw, pitch);
-    let applied_right_eye_tf = apply_right_eye_bone(right_eye_tf, properties, yaw, pitch);
+    let mut applied_left_eye_tf = apply_left_eye_bone(left_eye_tf, properties, yaw, pitch);
+    let mut applied_right_eye_tf = apply_right_eye_bone(right_eye_tf, properties, yaw, pitch);
+    if let Some((weight, mut pose)) = blend {
+        applied_left_eye_tf.rotation = pose.left.blend(
+            left_eye.0,
+            left_eye_tf.rotation,
+            applied_left_eye_tf.rotation,
+            weight.0,
+        );
+        applied_right_eye_tf.rotation = pose.right.blend(
+            right_eye.0,
+            right_eye_tf.rotation,
+            applied_right_eye_tf.rotation,
+            weight.0,
+        );
+    }
     par_commands.command_scope(move |mut commands: Commands| {
         commands.entity(left_eye.0).insert(applied_left_eye_tf);
         commands.entity(right_eye.0).insert(applied_right_eye_tf);
@@ -221,6 +390,39 @@ fn calc_yaw_pitch(
     (yaw, pitch)
 }
 
+/// Maps the yaw and pitch onto the weights of `lookLeft`, `lookRight`, `lookUp` and `lookDown`.
+///
+/// Positive yaw looks left and positive pitch looks down.
+fn expression_weights(
+    properties: &LookAtProperties,
+    yaw_degrees: f32,
+    pitch_degrees: f32,
+) -> [(VrmExpressionPreset, f32); 4] {
+    let map = |range: RangeMap, degrees: f32| {
+        if range.input_max_value <= 0.0 {
+            return 0.0;
+        }
+        (degrees.min(range.input_max_value) / range.input_max_value * range.output_scale)
+            .clamp(0.0, 1.0)
+    };
+    let horizontal = properties.range_map_horizontal_outer;
+    [
+        (VrmExpressionPreset::LookLeft, map(horizontal, yaw_degrees)),
+        (
+            VrmExpressionPreset::LookRight,
+            map(horizontal, -yaw_degrees),
+        ),
+        (
+            VrmExpressionPreset::LookDown,
+            map(properties.range_map_vertical_down, pitch_degrees),
+        ),
+        (
+            VrmExpressionPreset::LookUp,
+            map(properties.range_map_vertical_up, -pitch_degrees),
+        ),
+    ]
+}
+
 fn apply_left_eye_bone(
     left_eye: &Transform,
     properties: &LookAtProperties,
@@ -304,3 +506,116 @@ fn apply_right_eye_bone(
         0.0,
     ))
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::prelude::*;
+    use crate::tests::test_app;
+    use crate::vrm::look_at::{expression_weights, LookAtPlugin};
+    use bevy::prelude::*;
+
+    pub(crate) struct TestAvatar {
+        pub app: App,
+        pub root: Entity,
+        pub neck: Entity,
+        pub head: Entity,
+        pub left_eye: Entity,
+    }
+
+    impl TestAvatar {
+        /// Spawns the root, neck, head and eyes looking at the target.
+        ///
+        /// The eyes rotate by the yaw and pitch as they are.
+        pub fn new(target: Vec3) -> Self {
+            let mut app = test_app();
+            app.add_plugins((TransformPlugin, LookAtPlugin));
+            let world = app.world_mut();
+            let target = world.spawn(Transform::from_translation(target)).id();
+            let root = world.spawn(Transform::default()).id();
+            let neck = world
+                .spawn((Transform::from_xyz(0.0, 1.5, 0.0), ChildOf(root)))
+                .id();
+            let head = world
+                .spawn((Transform::from_xyz(0.0, 0.1, 0.0), ChildOf(neck)))
+                .id();
+            let left_eye = world.spawn((Transform::default(), ChildOf(head))).id();
+            let right_eye = world.spawn((Transform::default(), ChildOf(head))).id();
+            let range = RangeMap {
+                input_max_value: 90.0,
+                output_scale: 90.0,
+            };
+            world.entity_mut(root).insert((
+                LookAt::Target(target),
+                LookAtProperties {
+                    offset_from_head_bone: [0.0; 3],
+                    range_map_horizontal_inner: range,
+                    range_map_horizontal_outer: range,
+                    range_map_vertical_down: range,
+                    range_map_vertical_up: range,
+                    r#type: LookAtType::Bone,
+                },
+                HeadBoneEntity(head),
+                NeckBoneEntity(neck),
+                LeftEyeBoneEntity(left_eye),
+                RightEyeBoneEntity(right_eye),
+            ));
+            Self {
+                app,
+                root,
+                neck,
+                head,
+                left_eye,
+            }
+        }
+
+        /// Returns the local yaw of the bone in degrees.
+        pub fn yaw(
+            &self,
+            bone: Entity,
+        ) -> f32 {
+            let rotation = self.app.world().get::<Transform>(bone).unwrap().rotation;
+            rotation.to_euler(EulerRot::YXZ).0.to_degrees()
+        }
+
+        pub fn update(
+            &mut self,
+            frames: usize,
+        ) {
+            for _ in 0..frames {
+                self.app.update();
+            }
+        }
+    }
+
+    fn properties() -> LookAtProperties {
+        let range = |input_max_value| RangeMap {
+            input_max_value,
+            output_scale: 1.0,
+        };
+        LookAtProperties {
+            offset_from_head_bone: [0.0; 3],
+            range_map_horizontal_inner: range(90.0),
+            range_map_horizontal_outer: range(90.0),
+            range_map_vertical_down: range(10.0),
+            range_map_vertical_up: range(20.0),
+            r#type: LookAtType::Expression,
+        }
+    }
+
+    #[test]
+    fn map_yaw_pitch_to_expressions() {
+        let weights = expression_weights(&properties(), 45.0, -10.0);
+        assert_eq!(
+            weights,
+            [
+                (VrmExpressionPreset::LookLeft, 0.5),
+                (VrmExpressionPreset::LookRight, 0.0),
+                (VrmExpressionPreset::LookDown, 0.0),
+                (VrmExpressionPreset::LookUp, 0.5),
+            ]
+        );
+        let weights = expression_weights(&properties(), -180.0, 30.0);
+        assert_eq!(weights[1], (VrmExpressionPreset::LookRight, 1.0));
+        assert_eq!(weights[2], (VrmExpressionPreset::LookDown, 1.0));
+    }
+}
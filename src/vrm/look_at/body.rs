@@ -0,0 +1,268 @@
+use bevy::prelude::*;
+
+/// Turns the head, neck and optionally the upper chest toward the target of [`LookAt`](crate::prelude::LookAt).
+///
+/// Insert this component into the root entity of the VRM together with [`LookAt`](crate::prelude::LookAt).
+/// Each bone takes a share of the yaw and pitch up to its limits, and the eyes follow the rest.
+/// The rotations are added on top of the current pose, so they blend with a playing VRMA.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+///
+/// fn spawn_vrm(
+///     mut commands: Commands,
+///     asset_server: Res<AssetServer>,
+/// ) {
+///     commands.spawn((
+///         VrmHandle(asset_server.load("model.vrm")),
+///         LookAt::Cursor { camera: None },
+///         LookAtBody::default().with_upper_chest(LookAtBoneShare::new(0.1, 15.0, 10.0)),
+///     ));
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component, Default)]
+pub struct LookAtBody {
+    pub head: LookAtBoneShare,
+    /// Ignored if the VRM does not have the `neck` bone.
+    pub neck: LookAtBoneShare,
+    /// Ignored if the VRM does not have the `upperChest` bone.
+    pub upper_chest: Option<LookAtBoneShare>,
+    offsets: [BoneOffset; 3],
+}
+
+/// The share of the gaze taken by a bone of [`LookAtBody`].
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+pub struct LookAtBoneShare {
+    /// The ratio of the yaw and pitch taken by the bone, between `0.0` and `1.0`.
+    pub weight: f32,
+    /// The maximum yaw of the bone in degrees.
+    pub max_yaw: f32,
+    /// The maximum pitch of the bone in degrees.
+    pub max_pitch: f32,
+}
+
+impl LookAtBoneShare {
+    pub const fn new(
+        weight: f32,
+        max_yaw: f32,
+        max_pitch: f32,
+    ) -> Self {
+        Self {
+            weight,
+            max_yaw,
+            max_pitch,
+        }
+    }
+
+    fn split(
+        &self,
+        yaw: f32,
+        pitch: f32,
+    ) -> (f32, f32) {
+        let weight = self.weight.clamp(0.0, 1.0);
+        let max_yaw = self.max_yaw.max(0.0);
+        let max_pitch = self.max_pitch.max(0.0);
+        (
+            (yaw * weight).clamp(-max_yaw, max_yaw),
+            (pitch * weight).clamp(-max_pitch, max_pitch),
+        )
+    }
+}
+
+/// The rotation added to a bone in the last frame.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Reflect)]
+struct BoneOffset {
+    bone: Option<Entity>,
+    yaw: f32,
+    pitch: f32,
+    /// The rotation of the pose without the offset.
+    base: Quat,
+    /// The rotation written with the offset.
+    written: Quat,
+}
+
+impl Default for LookAtBody {
+    fn default() -> Self {
+        Self {
+            head: LookAtBoneShare::new(0.4, 40.0, 25.0),
+            neck: LookAtBoneShare::new(0.2, 25.0, 15.0),
+            upper_chest: None,
+            offsets: [BoneOffset::default(); 3],
+        }
+    }
+}
+
+impl LookAtBody {
+    /// Enables the rotation of the upper chest.
+    pub fn with_upper_chest(
+        mut self,
+        share: LookAtBoneShare,
+    ) -> Self {
+        self.upper_chest = Some(share);
+        self
+    }
+
+    /// Forgets the rotations added to the bones, such as when the bones are respawned.
+    pub(crate) fn clear_offsets(&mut self) {
+        self.offsets = [BoneOffset::default(); 3];
+    }
+
+    /// Returns the sum of the maximum yaw and pitch of the bones.
+    pub(super) fn reach(&self) -> Vec2 {
+        self.shares()
+            .into_iter()
+            .flatten()
+            .map(|share| Vec2::new(share.max_yaw, share.max_pitch))
+            .sum()
+    }
+
+    /// Returns the yaw and pitch added to the bones below the head in the last frame.
+    ///
+    /// The look at space follows the parent of the head, so they have to be added back
+    /// to get the angles relative to the pose.
+    pub(super) fn applied_below_head(&self) -> (f32, f32) {
+        self.offsets[1..]
+            .iter()
+            .fold((0.0, 0.0), |(yaw, pitch), offset| {
+                (yaw + offset.yaw, pitch + offset.pitch)
+            })
+    }
+
+    /// Rotates the bones by their shares and returns the yaw and pitch left for the eyes.
+    ///
+    /// `bones` are the head, neck and upper chest, and `weight` is [`LookAtWeight`](crate::prelude::LookAtWeight).
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn distribute(
+        &mut self,
+        par_commands: &ParallelCommands,
+        bones: [Option<Entity>; 3],
+        root_rotation: Quat,
+        yaw: f32,
+        pitch: f32,
+        weight: f32,
+        transforms: &Query<&Transform>,
+        global_transforms: &Query<&GlobalTransform>,
+        parents: &Query<&ChildOf>,
+    ) -> (f32, f32) {
+        let shares = self.shares();
+        let (mut eye_yaw, mut eye_pitch) = (yaw, pitch);
+        for ((bone, share), offset) in bones.into_iter().zip(shares).zip(self.offsets.iter_mut()) {
+            let (Some(bone), Some(share)) = (bone, share) else {
+                if let Some((bone, restored)) = offset.restore(transforms) {
+                    par_commands.command_scope(|mut commands| {
+                        commands.entity(bone).insert(restored);
+                    });
+                }
+                continue;
+            };
+            let Ok(tf) = transforms.get(bone) else {
+                continue;
+            };
+            if offset.bone != Some(bone) || tf.rotation != offset.written {
+                // The pose has been changed by others such as VRMA.
+                offset.bone = Some(bone);
+                offset.base = tf.rotation;
+            }
+            let (share_yaw, share_pitch) = share.split(yaw, pitch);
+            let weight = weight.clamp(0.0, 1.0);
+            (offset.yaw, offset.pitch) = (share_yaw * weight, share_pitch * weight);
+            // The eyes follow what the bones have actually turned.
+            eye_yaw -= offset.yaw;
+            eye_pitch -= offset.pitch;
+
+            let parent_rotation = parents
+                .get(bone)
+                .and_then(|parent| global_transforms.get(parent.parent()))
+                .map(|gtf| gtf.rotation())
+                .unwrap_or(root_rotation);
+            let rotation = root_rotation
+                * Quat::from_euler(
+                    EulerRot::YXZ,
+                    offset.yaw.to_radians(),
+                    offset.pitch.to_radians(),
+                    0.0,
+                )
+                * root_rotation.inverse();
+            offset.written =
+                (parent_rotation.inverse() * rotation * parent_rotation * offset.base).normalize();
+            let applied = tf.with_rotation(offset.written);
+            par_commands.command_scope(|mut commands| {
+                commands.entity(bone).insert(applied);
+            });
+        }
+        (eye_yaw, eye_pitch)
+    }
+
+    fn shares(&self) -> [Option<LookAtBoneShare>; 3] {
+        [Some(self.head), Some(self.neck), self.upper_chest]
+    }
+}
+
+impl BoneOffset {
+    /// Puts the bone back to the pose without the offset, unless the pose has been changed by others.
+    fn restore(
+        &mut self,
+        transforms: &Query<&Transform>,
+    ) -> Option<(Entity, Transform)> {
+        let offset = std::mem::take(self);
+        let bone = offset.bone?;
+        let tf = transforms.get(bone).ok()?;
+        (tf.rotation == offset.written).then(|| (bone, tf.with_rotation(offset.base)))
+    }
+}
+
+pub(super) fn restore_body(
+    trigger: Trigger<OnRemove, LookAtBody>,
+    mut commands: Commands,
+    mut bodies: Query<&mut LookAtBody>,
+    transforms: Query<&Transform>,
+) {
+    if let Ok(mut body) = bodies.get_mut(trigger.target()) {
+        for offset in body.offsets.iter_mut() {
+            if let Some((bone, restored)) = offset.restore(&transforms) {
+                commands.entity(bone).insert(restored);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::success;
+    use crate::tests::TestResult;
+    use crate::vrm::look_at::tests::TestAvatar;
+    use bevy::prelude::*;
+
+    #[test]
+    fn turn_head_and_neck() -> TestResult {
+        let mut avatar = TestAvatar::new(Vec3::new(10.0, 1.6, 1.0));
+        avatar
+            .app
+            .world_mut()
+            .entity_mut(avatar.root)
+            .insert(LookAtBody::default());
+        avatar.update(5);
+        // The target is about 84 degrees to the left.
+        let head_yaw = avatar.yaw(avatar.head);
+        let neck_yaw = avatar.yaw(avatar.neck);
+        assert!((head_yaw - 33.7).abs() < 0.5, "{head_yaw}");
+        assert!((neck_yaw - 16.9).abs() < 0.5, "{neck_yaw}");
+
+        // The offsets are not accumulated over frames.
+        avatar.update(5);
+        assert!((avatar.yaw(avatar.head) - head_yaw).abs() < 1e-3);
+
+        avatar
+            .app
+            .world_mut()
+            .entity_mut(avatar.root)
+            .remove::<LookAtBody>();
+        avatar.update(1);
+        assert_eq!(avatar.yaw(avatar.head), 0.0);
+        assert_eq!(avatar.yaw(avatar.neck), 0.0);
+        success!()
+    }
+}
@@ -0,0 +1,225 @@
+use crate::prelude::*;
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// A target of [`LookAtCandidates`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Reflect)]
+pub struct LookAtCandidate {
+    pub target: Entity,
+    /// The candidate with the higher priority is looked at first.
+    /// Among the same priority, the nearest one is chosen.
+    pub priority: i32,
+}
+
+impl LookAtCandidate {
+    pub const fn new(
+        target: Entity,
+        priority: i32,
+    ) -> Self {
+        Self { target, priority }
+    }
+}
+
+/// Chooses the target of [`LookAt`] from several entities, such as the speakers around the avatar.
+///
+/// Insert this component into the root entity of the VRM.
+/// Every frame, the visible candidate with the highest priority, or the nearest one among the same priority,
+/// is written into [`LookAt::Target`].
+/// A candidate is visible if it is within `max_distance` and `max_angle` from the front of the VRM.
+///
+/// To avoid flickering between candidates, the chosen target is kept for `min_hold`,
+/// and a nearer candidate of the same priority replaces it only when it is nearer by `switch_margin`.
+/// If no candidate is visible, [`LookAt`] is left as it is.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+///
+/// fn spawn_vrm(
+///     mut commands: Commands,
+///     asset_server: Res<AssetServer>,
+/// ) {
+///     let speaker = commands.spawn(Transform::from_xyz(0.0, 1.5, 1.0)).id();
+///     let listener = commands.spawn(Transform::from_xyz(1.0, 1.5, 1.0)).id();
+///     commands.spawn((
+///         VrmHandle(asset_server.load("model.vrm")),
+///         LookAtCandidates::new([
+///             LookAtCandidate::new(speaker, 1),
+///             LookAtCandidate::new(listener, 0),
+///         ]),
+///     ));
+/// }
+/// ```
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component, Default)]
+pub struct LookAtCandidates {
+    pub candidates: Vec<LookAtCandidate>,
+    /// The maximum distance to the candidates.
+    pub max_distance: f32,
+    /// The maximum angle in degrees between the front of the VRM and the candidates.
+    pub max_angle: f32,
+    /// The ratio by which a candidate of the same priority must be nearer than the current one to replace it.
+    pub switch_margin: f32,
+    /// The minimum time to keep looking at the chosen candidate.
+    pub min_hold: Duration,
+    selected: Option<Entity>,
+    held: Duration,
+}
+
+impl Default for LookAtCandidates {
+    fn default() -> Self {
+        Self {
+            candidates: Vec::new(),
+            max_distance: 10.0,
+            max_angle: 90.0,
+            switch_margin: 0.2,
+            min_hold: Duration::from_millis(500),
+            selected: None,
+            held: Duration::ZERO,
+        }
+    }
+}
+
+impl LookAtCandidates {
+    pub fn new(candidates: impl IntoIterator<Item = LookAtCandidate>) -> Self {
+        Self {
+            candidates: candidates.into_iter().collect(),
+            ..default()
+        }
+    }
+
+    /// Returns the candidate currently looked at.
+    #[inline]
+    pub const fn selected(&self) -> Option<Entity> {
+        self.selected
+    }
+
+    /// Chooses the candidate from the visible ones with their distances.
+    fn choose(
+        &mut self,
+        visible: &[(LookAtCandidate, f32)],
+        delta: Duration,
+    ) -> Option<Entity> {
+        self.held += delta;
+        let best = visible
+            .iter()
+            .min_by(|(a, a_distance), (b, b_distance)| {
+                b.priority
+                    .cmp(&a.priority)
+                    .then(a_distance.total_cmp(b_distance))
+            })
+            .copied();
+        let current = visible
+            .iter()
+            .find(|(candidate, _)| Some(candidate.target) == self.selected)
+            .copied();
+        let next = match (current, best) {
+            (_, None) => return None,
+            (None, Some((best, _))) => best.target,
+            (Some((current, _)), Some(_)) if self.held < self.min_hold => current.target,
+            (Some((current, distance)), Some((best, best_distance))) => {
+                if current.priority < best.priority
+                    || best_distance < distance * (1.0 - self.switch_margin)
+                {
+                    best.target
+                } else {
+                    current.target
+                }
+            }
+        };
+        if self.selected != Some(next) {
+            self.selected = Some(next);
+            self.held = Duration::ZERO;
+        }
+        self.selected
+    }
+}
+
+pub(super) fn choose_candidates(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut vrms: Query<(
+        Entity,
+        &mut LookAtCandidates,
+        Option<&mut LookAt>,
+        &HeadBoneEntity,
+    )>,
+    global_transforms: Query<&GlobalTransform>,
+) {
+    for (entity, mut candidates, look_at, head) in vrms.iter_mut() {
+        let (Ok(vrm_gtf), Ok(head_gtf)) =
+            (global_transforms.get(entity), global_transforms.get(head.0))
+        else {
+            continue;
+        };
+        let origin = head_gtf.translation();
+        let front = vrm_gtf.rotation() * Vec3::Z;
+        let visible = candidates
+            .candidates
+            .iter()
+            .filter_map(|candidate| {
+                let offset = global_transforms.get(candidate.target).ok()?.translation() - origin;
+                let distance = offset.length();
+                let angle = front.angle_between(offset).to_degrees();
+                (distance <= candidates.max_distance && angle <= candidates.max_angle)
+                    .then_some((*candidate, distance))
+            })
+            .collect::<Vec<_>>();
+        let Some(target) = candidates.choose(&visible, time.delta()) else {
+            continue;
+        };
+        match look_at {
+            Some(mut look_at) => {
+                look_at.set_if_neq(LookAt::Target(target));
+            }
+            None => {
+                commands.entity(entity).insert(LookAt::Target(target));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+    use std::time::Duration;
+
+    #[test]
+    fn switch_with_hysteresis() {
+        let a = LookAtCandidate::new(Entity::from_raw(1), 0);
+        let b = LookAtCandidate::new(Entity::from_raw(2), 0);
+        let c = LookAtCandidate::new(Entity::from_raw(3), 1);
+        let mut candidates = LookAtCandidates::default();
+        let frame = Duration::from_secs(1);
+
+        assert_eq!(
+            candidates.choose(&[(a, 2.0), (b, 3.0)], frame),
+            Some(a.target)
+        );
+        // Not near enough to replace the current one.
+        assert_eq!(
+            candidates.choose(&[(a, 2.0), (b, 1.9)], frame),
+            Some(a.target)
+        );
+        assert_eq!(
+            candidates.choose(&[(a, 2.0), (b, 1.0)], frame),
+            Some(b.target)
+        );
+        // The higher priority wins regardless of the distance, after the hold time.
+        assert_eq!(
+            candidates.choose(&[(b, 1.0), (c, 5.0)], Duration::ZERO),
+            Some(b.target)
+        );
+        assert_eq!(
+            candidates.choose(&[(b, 1.0), (c, 5.0)], frame),
+            Some(c.target)
+        );
+        // The current one is replaced immediately when it becomes invisible.
+        assert_eq!(
+            candidates.choose(&[(a, 2.0)], Duration::ZERO),
+            Some(a.target)
+        );
+        assert_eq!(candidates.choose(&[], frame), None);
+    }
+}
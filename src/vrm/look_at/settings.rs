@@ -0,0 +1,296 @@
+use crate::prelude::*;
+use crate::random::XorShift32;
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// Smooths the eye movement driven by [`LookAt`].
+///
+/// Insert this component into the root entity of the VRM together with [`LookAt`].
+/// Without it, the eyes snap to the target every frame.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+///
+/// fn spawn_vrm(
+///     mut commands: Commands,
+///     asset_server: Res<AssetServer>,
+/// ) {
+///     commands.spawn((
+///         VrmHandle(asset_server.load("model.vrm")),
+///         LookAt::Cursor { camera: None },
+///         LookAtSettings::default().with_saccade(Saccade::default()),
+///     ));
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component, Default)]
+pub struct LookAtSettings {
+    /// The time constant of the damping toward the target.
+    ///
+    /// If zero, the eyes move toward the target as fast as `max_angular_speed` allows.
+    pub smoothing: Duration,
+    /// The maximum angular speed of the eyes in degrees per second.
+    pub max_angular_speed: f32,
+    /// Small random jumps of the gaze while fixating.
+    pub saccade: Option<Saccade>,
+    /// If `true`, the eyes return to the center when the target goes out of the range maps
+    /// or is not found, instead of staying at the limit.
+    pub return_to_center: bool,
+    yaw: f32,
+    pitch: f32,
+    saccade_offset: Vec2,
+    saccade_remaining: Duration,
+    rng: XorShift32,
+}
+
+/// The micro-saccade generator of [`LookAtSettings`].
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+pub struct Saccade {
+    /// The minimum interval between saccades.
+    pub min_interval: Duration,
+    /// The maximum interval between saccades.
+    pub max_interval: Duration,
+    /// The maximum offset of the gaze in degrees.
+    pub amplitude: f32,
+}
+
+impl Default for Saccade {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_millis(500),
+            max_interval: Duration::from_secs(2),
+            amplitude: 1.5,
+        }
+    }
+}
+
+impl Default for LookAtSettings {
+    fn default() -> Self {
+        Self {
+            smoothing: Duration::from_millis(80),
+            max_angular_speed: 400.0,
+            saccade: None,
+            return_to_center: true,
+            yaw: 0.0,
+            pitch: 0.0,
+            saccade_offset: Vec2::ZERO,
+            saccade_remaining: Duration::ZERO,
+            rng: XorShift32::default(),
+        }
+    }
+}
+
+impl LookAtSettings {
+    /// Enables the micro-saccades.
+    pub fn with_saccade(
+        mut self,
+        saccade: Saccade,
+    ) -> Self {
+        self.saccade = Some(saccade);
+        self
+    }
+
+    /// Returns the current yaw and pitch of the gaze in degrees, including the saccade.
+    #[inline]
+    pub fn angles(&self) -> (f32, f32) {
+        (
+            self.yaw + self.saccade_offset.x,
+            self.pitch + self.saccade_offset.y,
+        )
+    }
+
+    /// Moves the gaze toward the target angles and returns the yaw and pitch to apply.
+    pub(super) fn advance(
+        &mut self,
+        entity: Entity,
+        target: Option<(f32, f32)>,
+        properties: &LookAtProperties,
+        reach: Vec2,
+        delta: Duration,
+    ) -> (f32, f32) {
+        if self.rng.is_unseeded() {
+            self.rng = XorShift32::from_entity(entity);
+        }
+        let current = Vec2::new(self.yaw, self.pitch);
+        let goal = match target {
+            Some((yaw, pitch))
+                if !self.return_to_center || in_range(properties, reach, yaw, pitch) =>
+            {
+                Vec2::new(yaw, pitch)
+            }
+            _ if self.return_to_center => Vec2::ZERO,
+            _ => current,
+        };
+        let dt = delta.as_secs_f32();
+        let t = if self.smoothing.is_zero() {
+            1.0
+        } else {
+            1.0 - (-dt / self.smoothing.as_secs_f32()).exp()
+        };
+        let step = ((goal - current) * t).clamp_length_max(self.max_angular_speed.max(0.0) * dt);
+        (self.yaw, self.pitch) = (current + step).into();
+        self.advance_saccade(delta);
+        self.angles()
+    }
+
+    fn advance_saccade(
+        &mut self,
+        delta: Duration,
+    ) {
+        let Some(saccade) = self.saccade else {
+            self.saccade_offset = Vec2::ZERO;
+            return;
+        };
+        if delta < self.saccade_remaining {
+            self.saccade_remaining -= delta;
+            return;
+        }
+        let min = saccade.min_interval.as_secs_f32();
+        let max = saccade.max_interval.as_secs_f32().max(min);
+        self.saccade_remaining = Duration::from_secs_f32(min + (max - min) * self.rng.next_f32());
+        // Saccades are ballistic, so the offset jumps without the damping.
+        let angle = self.rng.next_f32() * std::f32::consts::TAU;
+        let radius = self.rng.next_f32().sqrt() * saccade.amplitude;
+        self.saccade_offset = Vec2::from_angle(angle) * radius;
+    }
+}
+
+/// Returns `true` if the angles are within the input range of the range maps,
+/// extended by the `reach` of the head and spine.
+fn in_range(
+    properties: &LookAtProperties,
+    reach: Vec2,
+    yaw: f32,
+    pitch: f32,
+) -> bool {
+    let horizontal = properties
+        .range_map_horizontal_inner
+        .input_max_value
+        .max(properties.range_map_horizontal_outer.input_max_value);
+    let vertical = if 0.0 < pitch {
+        properties.range_map_vertical_down.input_max_value
+    } else {
+        properties.range_map_vertical_up.input_max_value
+    };
+    yaw.abs() <= horizontal + reach.x && pitch.abs() <= vertical + reach.y
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+    use std::time::Duration;
+
+    fn properties() -> LookAtProperties {
+        let range = |input_max_value| RangeMap {
+            input_max_value,
+            output_scale: 10.0,
+        };
+        LookAtProperties {
+            offset_from_head_bone: [0.0; 3],
+            range_map_horizontal_inner: range(30.0),
+            range_map_horizontal_outer: range(60.0),
+            range_map_vertical_down: range(20.0),
+            range_map_vertical_up: range(20.0),
+            r#type: LookAtType::Bone,
+        }
+    }
+
+    fn entity() -> Entity {
+        Entity::from_raw(1)
+    }
+
+    #[test]
+    fn limit_angular_speed() {
+        let mut settings = LookAtSettings {
+            smoothing: Duration::ZERO,
+            max_angular_speed: 100.0,
+            ..default()
+        };
+        let delta = Duration::from_millis(100);
+        let (yaw, pitch) = settings.advance(
+            entity(),
+            Some((40.0, 0.0)),
+            &properties(),
+            Vec2::ZERO,
+            delta,
+        );
+        assert!((yaw - 10.0).abs() < 1e-4);
+        assert_eq!(pitch, 0.0);
+        for _ in 0..10 {
+            settings.advance(
+                entity(),
+                Some((40.0, 0.0)),
+                &properties(),
+                Vec2::ZERO,
+                delta,
+            );
+        }
+        assert_eq!(settings.angles(), (40.0, 0.0));
+    }
+
+    #[test]
+    fn return_to_center_out_of_range() {
+        let mut settings = LookAtSettings {
+            smoothing: Duration::ZERO,
+            max_angular_speed: f32::INFINITY,
+            ..default()
+        };
+        let delta = Duration::from_millis(100);
+        settings.advance(
+            entity(),
+            Some((40.0, 10.0)),
+            &properties(),
+            Vec2::ZERO,
+            delta,
+        );
+        assert_eq!(settings.angles(), (40.0, 10.0));
+        settings.advance(
+            entity(),
+            Some((90.0, 10.0)),
+            &properties(),
+            Vec2::ZERO,
+            delta,
+        );
+        assert_eq!(settings.angles(), (0.0, 0.0));
+
+        settings.return_to_center = false;
+        settings.advance(
+            entity(),
+            Some((90.0, 10.0)),
+            &properties(),
+            Vec2::ZERO,
+            delta,
+        );
+        assert_eq!(settings.angles(), (90.0, 10.0));
+        settings.advance(entity(), None, &properties(), Vec2::ZERO, delta);
+        assert_eq!(settings.angles(), (90.0, 10.0));
+    }
+
+    #[test]
+    fn saccade_within_amplitude() {
+        let mut settings = LookAtSettings {
+            smoothing: Duration::ZERO,
+            max_angular_speed: f32::INFINITY,
+            saccade: Some(Saccade {
+                amplitude: 2.0,
+                ..default()
+            }),
+            ..default()
+        };
+        let mut moved = false;
+        for _ in 0..100 {
+            let (yaw, pitch) = settings.advance(
+                entity(),
+                Some((0.0, 0.0)),
+                &properties(),
+                Vec2::ZERO,
+                Duration::from_millis(100),
+            );
+            assert!(Vec2::new(yaw, pitch).length() <= 2.0 + 1e-4);
+            moved |= yaw != 0.0 || pitch != 0.0;
+        }
+        assert!(moved);
+    }
+}
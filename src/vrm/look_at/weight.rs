@@ -0,0 +1,200 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// Blends the look at with the animated pose, such as a playing VRMA.
+///
+/// Insert this component into the root entity of the VRM together with [`LookAt`](crate::prelude::LookAt).
+/// `0.0` keeps the animated pose and `1.0` looks at the target fully.
+/// The weight applies to the eye bones, the look at expressions and [`LookAtBody`](crate::prelude::LookAtBody).
+///
+/// Without this component, the look at overwrites the eye bones.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Component, Default)]
+#[require(EyePose)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub struct LookAtWeight(pub f32);
+
+impl Default for LookAtWeight {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Fades [`LookAtWeight`] toward `target` at the rate of the full range per `duration`.
+///
+/// This component is removed when the weight reaches the target.
+/// If the VRM does not have [`LookAtWeight`] yet, it starts from `1.0`,
+/// so insert `LookAtWeight(0.0)` together to fade in from the animated pose.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+/// use std::time::Duration;
+///
+/// fn start_cutscene(
+///     mut commands: Commands,
+///     vrm: Query<Entity, With<Vrm>>,
+/// ) {
+///     commands
+///         .entity(vrm.single().unwrap())
+///         .insert(LookAtFade::fade_out(Duration::from_millis(500)));
+/// }
+/// ```
+#[derive(Component, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
+#[require(LookAtWeight)]
+pub struct LookAtFade {
+    pub target: f32,
+    pub duration: Duration,
+}
+
+impl LookAtFade {
+    /// Fades the weight to `1.0`.
+    pub const fn fade_in(duration: Duration) -> Self {
+        Self {
+            target: 1.0,
+            duration,
+        }
+    }
+
+    /// Fades the weight to `0.0`.
+    pub const fn fade_out(duration: Duration) -> Self {
+        Self {
+            target: 0.0,
+            duration,
+        }
+    }
+}
+
+/// The animated rotations of the eye bones to blend the look at with.
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Component, Default)]
+pub(crate) struct EyePose {
+    pub(super) left: AnimatedRotation,
+    pub(super) right: AnimatedRotation,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Reflect)]
+pub(super) struct AnimatedRotation {
+    bone: Option<Entity>,
+    /// The rotation of the animated pose.
+    base: Quat,
+    /// The rotation written by the look at.
+    written: Quat,
+}
+
+impl AnimatedRotation {
+    /// Blends the rotation of the look at with the animated rotation and returns the result.
+    pub fn blend(
+        &mut self,
+        bone: Entity,
+        current: Quat,
+        look_at: Quat,
+        weight: f32,
+    ) -> Quat {
+        if self.bone != Some(bone) || current != self.written {
+            // The pose has been changed by others such as VRMA.
+            self.bone = Some(bone);
+            self.base = current;
+        }
+        self.written = self.base.slerp(look_at, weight.clamp(0.0, 1.0));
+        self.written
+    }
+}
+
+pub(super) fn fade_look_at_weight(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut vrms: Query<(Entity, &LookAtFade, &mut LookAtWeight)>,
+) {
+    for (entity, fade, mut weight) in vrms.iter_mut() {
+        let step = if fade.duration.is_zero() {
+            f32::INFINITY
+        } else {
+            time.delta_secs() / fade.duration.as_secs_f32()
+        };
+        let diff = fade.target - weight.0;
+        if diff.abs() <= step {
+            weight.0 = fade.target;
+            commands.entity(entity).remove::<LookAtFade>();
+        } else {
+            weight.0 += step.copysign(diff);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::success;
+    use crate::tests::TestResult;
+    use crate::vrm::look_at::tests::TestAvatar;
+    use crate::vrm::look_at::weight::fade_look_at_weight;
+    use bevy::prelude::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    #[test]
+    fn blend_with_animated_pose() -> TestResult {
+        // The target is 45 degrees to the left.
+        let mut avatar = TestAvatar::new(Vec3::new(1.0, 1.6, 1.0));
+        let world = avatar.app.world_mut();
+        world
+            .entity_mut(avatar.left_eye)
+            .insert(Transform::from_rotation(Quat::from_rotation_y(
+                (-15.0_f32).to_radians(),
+            )));
+        world.entity_mut(avatar.root).insert(LookAtWeight(0.5));
+        avatar.update(3);
+        let yaw = avatar.yaw(avatar.left_eye);
+        assert!((yaw - 15.0).abs() < 0.5, "{yaw}");
+
+        avatar
+            .app
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .world_mut()
+            .entity_mut(avatar.root)
+            .insert(LookAtFade::fade_out(Duration::from_millis(500)));
+        avatar.update(8);
+        let world = avatar.app.world();
+        assert_eq!(
+            world.get::<LookAtWeight>(avatar.root),
+            Some(&LookAtWeight(0.0))
+        );
+        assert!(world.get::<LookAtFade>(avatar.root).is_none());
+        let yaw = avatar.yaw(avatar.left_eye);
+        assert!((yaw + 15.0).abs() < 1e-3, "{yaw}");
+        success!()
+    }
+
+    #[test]
+    fn reach_fade_target_exactly() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .add_systems(Update, fade_look_at_weight);
+        let vrm = app
+            .world_mut()
+            .spawn((
+                LookAtWeight(0.15),
+                LookAtFade {
+                    target: 0.7,
+                    duration: Duration::from_millis(100),
+                },
+            ))
+            .id();
+        for _ in 0..3 {
+            app.update();
+            let weight = app.world().get::<LookAtWeight>(vrm).unwrap().0;
+            assert!(weight == 0.15 || weight == 0.7, "{weight}");
+        }
+        let world = app.world();
+        assert_eq!(world.get::<LookAtWeight>(vrm), Some(&LookAtWeight(0.7)));
+        assert!(world.get::<LookAtFade>(vrm).is_none());
+    }
+}
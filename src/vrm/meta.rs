@@ -0,0 +1,365 @@
+//! This module exposes the meta information of VRM and checks it against the license policy of the application.
+//!
+//! - [`meta specification(en)`](https://github.com/vrm-c/vrm-specification/blob/master/specification/VRMC_vrm-1.0/meta.md)
+//! - [`meta specification(ja)`](https://github.com/vrm-c/vrm-specification/blob/master/specification/VRMC_vrm-1.0/meta.ja.md)
+
+use crate::vrm::gltf::extensions::vrmc_vrm::Meta;
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+
+pub mod prelude {
+    pub use crate::vrm::meta::{
+        AvatarPermission, CommercialUsage, CreditNotation, LicenseViolation,
+        LicenseViolationAction, Modification, VrmLicensePolicy, VrmLicenseViolated, VrmMeta,
+    };
+}
+
+/// The meta information of the VRM.
+///
+/// This component is automatically inserted into the VRM entity after the [`VrmHandle`](crate::prelude::VrmHandle) is loaded.
+/// If the VRM does not have the meta information, the default values of the specification are used.
+#[derive(Component, Debug, Clone, PartialEq, Default, Reflect)]
+#[reflect(Component, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub struct VrmMeta {
+    /// The name of the avatar.
+    pub name: Option<String>,
+    /// The version of the avatar.
+    pub version: Option<String>,
+    /// The creators of the avatar.
+    pub authors: Vec<String>,
+    /// The copyright information of the avatar.
+    pub copyright_information: Option<String>,
+    /// The contact information of the creators.
+    pub contact_information: Option<String>,
+    /// The references or original works of the avatar.
+    pub references: Vec<String>,
+    /// The licenses of the third party components.
+    pub third_party_licenses: Option<String>,
+    /// The URL of the license document.
+    pub license_url: Option<String>,
+    /// The URL of the other license document which the creators specified additionally.
+    pub other_license_url: Option<String>,
+    /// Who is allowed to perform with the avatar.
+    pub avatar_permission: AvatarPermission,
+    /// Whether the avatar can be used for commercial purposes.
+    pub commercial_usage: CommercialUsage,
+    /// Whether the avatar can be used for excessively violent expressions.
+    pub allow_excessively_violent_usage: bool,
+    /// Whether the avatar can be used for excessively sexual expressions.
+    pub allow_excessively_sexual_usage: bool,
+    /// Whether the avatar can be used for political or religious activities.
+    pub allow_political_or_religious_usage: bool,
+    /// Whether the avatar can be used for antisocial or hate activities.
+    pub allow_antisocial_or_hate_usage: bool,
+    /// Whether the avatar can be redistributed.
+    pub allow_redistribution: bool,
+    /// Whether the credit notation is required.
+    pub credit_notation: CreditNotation,
+    /// Whether the avatar can be modified.
+    pub modification: Modification,
+}
+
+impl VrmMeta {
+    /// Returns the list of the usages that the policy requires but this avatar does not allow.
+    pub fn violations(
+        &self,
+        policy: &VrmLicensePolicy,
+    ) -> Vec<LicenseViolation> {
+        let mut violations = Vec::new();
+        if self.avatar_permission < policy.avatar_permission {
+            violations.push(LicenseViolation::AvatarPermission(self.avatar_permission));
+        }
+        if self.commercial_usage < policy.commercial_usage {
+            violations.push(LicenseViolation::CommercialUsage(self.commercial_usage));
+        }
+        if self.modification < policy.modification {
+            violations.push(LicenseViolation::Modification(self.modification));
+        }
+        if policy.redistribution && !self.allow_redistribution {
+            violations.push(LicenseViolation::Redistribution);
+        }
+        if policy.excessively_violent_usage && !self.allow_excessively_violent_usage {
+            violations.push(LicenseViolation::ExcessivelyViolentUsage);
+        }
+        if policy.excessively_sexual_usage && !self.allow_excessively_sexual_usage {
+            violations.push(LicenseViolation::ExcessivelySexualUsage);
+        }
+        if policy.political_or_religious_usage && !self.allow_political_or_religious_usage {
+            violations.push(LicenseViolation::PoliticalOrReligiousUsage);
+        }
+        if policy.antisocial_or_hate_usage && !self.allow_antisocial_or_hate_usage {
+            violations.push(LicenseViolation::AntisocialOrHateUsage);
+        }
+        violations
+    }
+}
+
+impl From<&Meta> for VrmMeta {
+    fn from(meta: &Meta) -> Self {
+        Self {
+            name: meta.name.clone(),
+            version: meta.version.clone(),
+            authors: meta.authors.clone(),
+            copyright_information: meta.copyright_information.clone(),
+            contact_information: meta.contact_information.clone(),
+            references: meta.references.clone(),
+            third_party_licenses: meta.third_party_licenses.clone(),
+            license_url: meta.license_url.clone(),
+            other_license_url: meta.other_license_url.clone(),
+            avatar_permission: AvatarPermission::from(meta.avatar_permission.as_deref()),
+            commercial_usage: CommercialUsage::from(meta.commercial_usage.as_deref()),
+            allow_excessively_violent_usage: meta.allow_excessively_violent_usage,
+            allow_excessively_sexual_usage: meta.allow_excessively_sexual_usage,
+            allow_political_or_religious_usage: meta.allow_political_or_religious_usage,
+            allow_antisocial_or_hate_usage: meta.allow_antisocial_or_hate_usage,
+            allow_redistribution: meta.allow_redistribution,
+            credit_notation: CreditNotation::from(meta.credit_notation.as_deref()),
+            modification: Modification::from(meta.modification.as_deref()),
+        }
+    }
+}
+
+/// Who is allowed to perform with the avatar.
+///
+/// The variants are ordered from the most restrictive to the most permissive.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default, Reflect)]
+#[reflect(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub enum AvatarPermission {
+    /// Only the author is allowed.
+    #[default]
+    OnlyAuthor,
+    /// Only the persons who have a separate license are allowed.
+    OnlySeparatelyLicensedPerson,
+    /// Everyone is allowed.
+    Everyone,
+}
+
+impl From<Option<&str>> for AvatarPermission {
+    fn from(value: Option<&str>) -> Self {
+        match value {
+            Some("onlySeparatelyLicensedPerson") => Self::OnlySeparatelyLicensedPerson,
+            Some("everyone") => Self::Everyone,
+            _ => Self::OnlyAuthor,
+        }
+    }
+}
+
+/// The commercial usage of the avatar.
+///
+/// The variants are ordered from the most restrictive to the most permissive.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default, Reflect)]
+#[reflect(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub enum CommercialUsage {
+    /// Only non-profit usage by individuals is allowed.
+    #[default]
+    PersonalNonProfit,
+    /// Profitable usage by individuals is allowed.
+    PersonalProfit,
+    /// Usage by corporations is allowed.
+    Corporation,
+}
+
+impl From<Option<&str>> for CommercialUsage {
+    fn from(value: Option<&str>) -> Self {
+        match value {
+            Some("personalProfit") => Self::PersonalProfit,
+            Some("corporation") => Self::Corporation,
+            _ => Self::PersonalNonProfit,
+        }
+    }
+}
+
+/// Whether the credit notation is required.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default, Reflect)]
+#[reflect(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub enum CreditNotation {
+    /// The credit notation is required.
+    #[default]
+    Required,
+    /// The credit notation is not required.
+    Unnecessary,
+}
+
+impl From<Option<&str>> for CreditNotation {
+    fn from(value: Option<&str>) -> Self {
+        match value {
+            Some("unnecessary") => Self::Unnecessary,
+            _ => Self::Required,
+        }
+    }
+}
+
+/// Whether the avatar can be modified.
+///
+/// The variants are ordered from the most restrictive to the most permissive.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default, Reflect)]
+#[reflect(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub enum Modification {
+    /// Modification is prohibited.
+    #[default]
+    Prohibited,
+    /// Modification is allowed, but the modified avatar cannot be redistributed.
+    AllowModification,
+    /// Modification and redistribution of the modified avatar are allowed.
+    AllowModificationRedistribution,
+}
+
+impl From<Option<&str>> for Modification {
+    fn from(value: Option<&str>) -> Self {
+        match value {
+            Some("allowModification") => Self::AllowModification,
+            Some("allowModificationRedistribution") => Self::AllowModificationRedistribution,
+            _ => Self::Prohibited,
+        }
+    }
+}
+
+/// The usages of the avatars that the application needs.
+///
+/// When a VRM does not allow one of them, [`VrmLicenseViolated`] is triggered on the VRM entity,
+/// and if [`VrmLicensePolicy::on_violation`] is [`LicenseViolationAction::Refuse`], the VRM is not spawned.
+///
+/// By default, nothing is required.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+///
+/// App::new()
+///     .add_plugins((DefaultPlugins, VrmPlugin))
+///     .insert_resource(VrmLicensePolicy {
+///         commercial_usage: CommercialUsage::Corporation,
+///         redistribution: true,
+///         on_violation: LicenseViolationAction::Refuse,
+///         ..default()
+///     });
+/// ```
+#[derive(Resource, Debug, Clone, PartialEq, Default, Reflect)]
+#[reflect(Resource, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub struct VrmLicensePolicy {
+    /// The minimum [`AvatarPermission`] required.
+    pub avatar_permission: AvatarPermission,
+    /// The minimum [`CommercialUsage`] required.
+    pub commercial_usage: CommercialUsage,
+    /// The minimum [`Modification`] required.
+    pub modification: Modification,
+    /// Whether the redistribution is required.
+    pub redistribution: bool,
+    /// Whether the excessively violent usage is required.
+    pub excessively_violent_usage: bool,
+    /// Whether the excessively sexual usage is required.
+    pub excessively_sexual_usage: bool,
+    /// Whether the political or religious usage is required.
+    pub political_or_religious_usage: bool,
+    /// Whether the antisocial or hate usage is required.
+    pub antisocial_or_hate_usage: bool,
+    /// What to do when a VRM violates this policy.
+    pub on_violation: LicenseViolationAction,
+}
+
+/// What to do when a VRM violates the [`VrmLicensePolicy`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default, Reflect)]
+#[reflect(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub enum LicenseViolationAction {
+    /// Spawn the VRM and trigger [`VrmLicenseViolated`].
+    #[default]
+    Notify,
+    /// Trigger [`VrmLicenseViolated`] and do not spawn the VRM.
+    ///
+    /// The VRM entity only has [`VrmMeta`] in this case.
+    Refuse,
+}
+
+/// A usage that the [`VrmLicensePolicy`] requires but the VRM does not allow.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub enum LicenseViolation {
+    /// The avatar permission is lower than required. The value is the permission of the avatar.
+    AvatarPermission(AvatarPermission),
+    /// The commercial usage is lower than required. The value is the usage allowed by the avatar.
+    CommercialUsage(CommercialUsage),
+    /// The modification is lower than required. The value is the modification allowed by the avatar.
+    Modification(Modification),
+    Redistribution,
+    ExcessivelyViolentUsage,
+    ExcessivelySexualUsage,
+    PoliticalOrReligiousUsage,
+    AntisocialOrHateUsage,
+}
+
+/// An event that is emitted when the VRM violates the [`VrmLicensePolicy`].
+///
+/// This event is emitted as a trigger.
+/// The target of the trigger is the VRM entity.
+#[derive(Debug, Event, Clone, Reflect)]
+pub struct VrmLicenseViolated {
+    /// Whether the VRM has been refused to spawn.
+    pub refused: bool,
+    pub violations: Vec<LicenseViolation>,
+}
+
+pub(super) struct VrmMetaPlugin;
+
+impl Plugin for VrmMetaPlugin {
+    fn build(
+        &self,
+        app: &mut App,
+    ) {
+        app.register_type::<VrmMeta>()
+            .register_type::<VrmLicensePolicy>()
+            .init_resource::<VrmLicensePolicy>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vrm::meta::{
+        CommercialUsage, LicenseViolation, Modification, VrmLicensePolicy, VrmMeta,
+    };
+    use bevy::utils::default;
+
+    #[test]
+    fn default_policy_has_no_violations() {
+        assert!(VrmMeta::default()
+            .violations(&VrmLicensePolicy::default())
+            .is_empty());
+    }
+
+    #[test]
+    fn detect_violations() {
+        let meta = VrmMeta {
+            commercial_usage: CommercialUsage::PersonalProfit,
+            modification: Modification::AllowModificationRedistribution,
+            allow_redistribution: false,
+            ..default()
+        };
+        let policy = VrmLicensePolicy {
+            commercial_usage: CommercialUsage::Corporation,
+            modification: Modification::AllowModification,
+            redistribution: true,
+            ..default()
+        };
+        assert_eq!(
+            meta.violations(&policy),
+            vec![
+                LicenseViolation::CommercialUsage(CommercialUsage::PersonalProfit),
+                LicenseViolation::Redistribution,
+            ]
+        );
+    }
+}
@@ -1,15 +1,28 @@
-use crate::vrm::expressions::VrmExpressionRegistry;
+use crate::prelude::{LookAtBody, LookAtProperties};
+use crate::vrm::expressions::{
+    ExpressionMaterialBindRegistry, MixedExpressionWeights, ProceduralExpressionWeights,
+    VrmExpressionRegistry, VrmExpressionWeights,
+};
+use crate::vrm::first_person::{FirstPersonLayersApplied, FirstPersonRegistry};
 use crate::vrm::gltf::extensions::VrmExtensions;
-use crate::vrm::humanoid_bone::HumanoidBoneRegistry;
-use crate::vrm::loader::{VrmAsset, VrmHandle};
+use crate::vrm::humanoid_bone::{
+    remove_bone_entities, HumanoidBoneRegistry, HumanoidBonesAttached,
+};
+use crate::vrm::loader::{VrmAsset, VrmAssetHandle, VrmHandle};
+use crate::vrm::look_at::EyePose;
+use crate::vrm::meta::{LicenseViolationAction, VrmLicensePolicy, VrmLicenseViolated, VrmMeta};
 use crate::vrm::mtoon::VrmcMaterialRegistry;
 use crate::vrm::spring_bone::registry::*;
+use crate::vrm::spring_bone::setup::{
+    AttachedColliderShapes, AttachedJointProps, AttachedSpringRoots,
+};
+use crate::vrm::validation::{validate_vrm, VrmValidationReport};
 use crate::vrm::{Vrm, VrmPath};
-use bevy::app::{App, Update};
+use bevy::app::{App, PreUpdate, Update};
 use bevy::asset::Assets;
 use bevy::gltf::GltfNode;
 use bevy::prelude::*;
-use bevy::scene::SceneRoot;
+use bevy::scene::{SceneInstance, SceneRoot, SceneSpawner};
 
 pub(crate) struct VrmSpawnPlugin;
 
@@ -18,7 +31,70 @@ impl Plugin for VrmSpawnPlugin {
         &self,
         app: &mut App,
     ) {
-        app.add_systems(Update, spawn_vrm);
+        app.add_systems(PreUpdate, reload_vrm)
+            .add_systems(Update, spawn_vrm);
+    }
+}
+
+/// Rebuilds the VRM on the same entity when its asset is modified, such as by hot reloading.
+///
+/// Only the spawned scene and the components derived from the asset are replaced,
+/// so child entities like VRMA, [`LookAt`](crate::prelude::LookAt) and user components on the root are kept.
+fn reload_vrm(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<VrmAsset>>,
+    mut vrms: Query<(
+        Entity,
+        &VrmAssetHandle,
+        Option<&SceneInstance>,
+        Option<&mut EyePose>,
+        Option<&mut LookAtBody>,
+    )>,
+) {
+    for event in events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        for (vrm_entity, handle, instance, eye_pose, body) in vrms.iter_mut() {
+            if handle.0.id() != *id {
+                continue;
+            }
+            if let Some(instance) = instance.map(|instance| **instance) {
+                // Removing `SceneRoot` only unregisters the instance, so its entities are despawned first.
+                commands.queue(move |world: &mut World| {
+                    world.resource_scope(|world, mut scene_spawner: Mut<SceneSpawner>| {
+                        scene_spawner.despawn_instance_sync(world, &instance);
+                    });
+                });
+            }
+            if let Some(mut eye_pose) = eye_pose {
+                *eye_pose = EyePose::default();
+            }
+            if let Some(mut body) = body {
+                body.clear_offsets();
+            }
+            let mut vrm = commands.entity(vrm_entity);
+            remove_bone_entities(&mut vrm);
+            vrm.remove::<(
+                SceneRoot,
+                SceneInstance,
+                HumanoidBonesAttached,
+                AttachedJointProps,
+                AttachedColliderShapes,
+                AttachedSpringRoots,
+                FirstPersonLayersApplied,
+            )>()
+            .remove::<(
+                VrmcMaterialRegistry,
+                VrmExpressionRegistry,
+                ExpressionMaterialBindRegistry,
+                SpringJointPropsRegistry,
+                SpringColliderRegistry,
+                SpringNodeRegistry,
+                LookAtProperties,
+            )>()
+            .insert(VrmHandle(handle.0.clone()));
+        }
     }
 }
 
@@ -26,32 +102,69 @@ fn spawn_vrm(
     mut commands: Commands,
     node_assets: Res<Assets<GltfNode>>,
     vrm_assets: Res<Assets<VrmAsset>>,
+    license_policy: Res<VrmLicensePolicy>,
     handles: Query<(Entity, &VrmHandle)>,
 ) {
     for (vrm_handle_entity, handle) in handles.iter() {
         let Some(vrm) = vrm_assets.get(handle.0.id()) else {
             continue;
         };
-        commands.entity(vrm_handle_entity).remove::<VrmHandle>();
+        commands
+            .entity(vrm_handle_entity)
+            .remove::<(VrmHandle, VrmValidationReport)>()
+            .insert(VrmAssetHandle(handle.0.clone()));
 
         let Some(scene) = vrm.gltf.scenes.first() else {
+            error!("Failed to spawn VRM: the glTF has no scene");
+            commands
+                .entity(vrm_handle_entity)
+                .insert(validate_vrm(&vrm.gltf));
             continue;
         };
         let extensions = match VrmExtensions::from_gltf(&vrm.gltf) {
             Ok(extensions) => extensions,
             Err(_e) => {
+                let report = validate_vrm(&vrm.gltf);
                 error!("Failed to load VRM extensions: {_e}");
+                for diagnostic in report.errors() {
+                    error!("{diagnostic}");
+                }
+                commands.entity(vrm_handle_entity).insert(report);
                 continue;
             }
         };
 
+        let meta = extensions
+            .vrmc_vrm
+            .meta
+            .as_ref()
+            .map(VrmMeta::from)
+            .unwrap_or_default();
+        let violations = meta.violations(&license_policy);
+        let refused =
+            !violations.is_empty() && license_policy.on_violation == LicenseViolationAction::Refuse;
+        commands.entity(vrm_handle_entity).insert(meta);
+        if !violations.is_empty() {
+            commands
+                .entity(vrm_handle_entity)
+                .trigger(VrmLicenseViolated {
+                    refused,
+                    violations,
+                });
+        }
+        if refused {
+            continue;
+        }
+
+        let settings = &vrm.settings;
         let mut cmd = commands.entity(vrm_handle_entity);
-        cmd.insert((
+        cmd.insert_if_new(Name::new(
+            extensions.name().unwrap_or_else(|| "VRM".to_string()),
+        ))
+        .insert((
             Vrm,
-            Name::new(extensions.name().unwrap_or_else(|| "VRM".to_string())),
             SceneRoot(scene.clone()),
-            VrmcMaterialRegistry::new(&vrm.gltf, vrm.images.clone()),
-            VrmExpressionRegistry::new(&extensions, &node_assets, &vrm.gltf.nodes),
+            FirstPersonRegistry::new(&extensions, &node_assets, &vrm.gltf.nodes),
             HumanoidBoneRegistry::new(
                 &extensions.vrmc_vrm.humanoid.human_bones,
                 &node_assets,
@@ -59,7 +172,26 @@ fn spawn_vrm(
             ),
         ));
 
-        if let Some(spring_bone) = extensions.vrmc_spring_bone.as_ref() {
+        if settings.mtoon {
+            cmd.insert(VrmcMaterialRegistry::new(&vrm.gltf, vrm.images.clone()));
+        }
+
+        if settings.expressions {
+            cmd.insert_if_new(VrmExpressionWeights::default()).insert((
+                VrmExpressionRegistry::new(&extensions, &node_assets, &vrm.gltf.nodes),
+                ProceduralExpressionWeights::default(),
+                MixedExpressionWeights::default(),
+            ));
+            if settings.mtoon {
+                cmd.insert(ExpressionMaterialBindRegistry::new(&extensions, &vrm.gltf));
+            }
+        }
+
+        if let Some(spring_bone) = extensions
+            .vrmc_spring_bone
+            .as_ref()
+            .filter(|_| settings.spring_bone)
+        {
             cmd.insert((
                 SpringJointPropsRegistry::new(
                     &spring_bone.all_joints(),
@@ -71,7 +203,12 @@ fn spawn_vrm(
             ));
         }
 
-        if let Some(look_at) = extensions.vrmc_vrm.look_at.clone() {
+        if let Some(look_at) = extensions
+            .vrmc_vrm
+            .look_at
+            .clone()
+            .filter(|_| settings.look_at)
+        {
             cmd.insert(look_at);
         }
 
@@ -113,3 +250,64 @@ fn output_vrm_extensions(
     )
     .unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::success;
+    use crate::tests::TestResult;
+    use crate::vrm::tests::{spawn_vrm, update_until, vrm_app};
+    use bevy::asset::io::memory::Dir;
+    use bevy::prelude::*;
+    use bevy::scene::{SceneInstance, SceneSpawner};
+    use std::path::Path;
+
+    #[test]
+    fn reload_on_modified() -> TestResult {
+        let dir = Dir::default();
+        dir.insert_asset(
+            Path::new("test.vrm"),
+            VrmBuilder::new("Test").humanoid().build()?,
+        );
+        let mut app = vrm_app(&dir);
+        let vrm = spawn_vrm(&mut app, "test.vrm");
+        app.world_mut()
+            .entity_mut(vrm)
+            .insert((LookAt::Cursor { camera: None }, LookAtBody::default()));
+        let vrma = app.world_mut().spawn(ChildOf(vrm)).id();
+        app.update();
+        let head = app.world().get::<HeadBoneEntity>(vrm).unwrap().0;
+        let instance = **app.world().get::<SceneInstance>(vrm).unwrap();
+
+        dir.insert_asset(
+            Path::new("test.vrm"),
+            VrmBuilder::new("Test")
+                .humanoid()
+                .node("Hair", Some("head"), Vec3::Y)
+                .build()?,
+        );
+        app.world()
+            .resource::<AssetServer>()
+            .reload("memory://test.vrm");
+        update_until(&mut app, |world| {
+            world
+                .get::<HeadBoneEntity>(vrm)
+                .is_some_and(|new_head| new_head.0 != head)
+        });
+
+        let world = app.world();
+        assert!(world.get_entity(head).is_err());
+        assert_eq!(
+            world
+                .resource::<SceneSpawner>()
+                .iter_instance_entities(instance)
+                .count(),
+            0
+        );
+        let vrm = world.entity(vrm);
+        assert!(vrm.contains::<LookAt>());
+        assert!(vrm.contains::<LookAtBody>());
+        assert!(world.get_entity(vrma).is_ok());
+        success!()
+    }
+}
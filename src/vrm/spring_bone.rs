@@ -1,5 +1,5 @@
 pub mod registry;
-mod setup;
+pub(crate) mod setup;
 mod update;
 
 use crate::prelude::ColliderShape;
@@ -9,6 +9,76 @@ use crate::vrm::spring_bone::update::SpringBoneUpdatePlugin;
 use bevy::app::App;
 use bevy::math::{Mat4, Quat, Vec3};
 use bevy::prelude::*;
+use std::time::Duration;
+
+/// The settings of the spring bone simulation shared by all VRMs.
+///
+/// By default, the spring bones are simulated at a fixed 60 Hz step,
+/// so the same input gives the same motion regardless of the frame rate.
+/// Use [`SpringBoneTimestep::Variable`] to simulate once per frame with the frame time.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+/// use std::time::Duration;
+///
+/// App::new()
+///     .add_plugins((DefaultPlugins, VrmPlugin))
+///     .insert_resource(SpringBoneSimulationSettings {
+///         timestep: SpringBoneTimestep::Fixed(Duration::from_secs_f64(1.0 / 120.0)),
+///         ..default()
+///     })
+///     .run();
+/// ```
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Resource, Default)]
+pub struct SpringBoneSimulationSettings {
+    pub timestep: SpringBoneTimestep,
+    /// The maximum time simulated in a frame.
+    ///
+    /// Longer frames, such as while dragging the window, are simulated as this length
+    /// to keep the spring bones from exploding.
+    pub max_delta: Duration,
+    /// The maximum number of simulation steps in a frame.
+    ///
+    /// A [`SpringBoneTimestep::Fixed`] step much shorter than the frame time or
+    /// a large [`SpringBoneTimestep::Substeps`] would otherwise simulate the spring bones
+    /// too many times in a frame. The time beyond this number of fixed steps is dropped.
+    pub max_steps: u32,
+}
+
+impl Default for SpringBoneSimulationSettings {
+    fn default() -> Self {
+        Self {
+            timestep: SpringBoneTimestep::Fixed(Duration::from_secs_f64(1.0 / 60.0)),
+            max_delta: Duration::from_millis(100),
+            max_steps: 8,
+        }
+    }
+}
+
+/// How the frame time is divided into the steps of the spring bone simulation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Reflect)]
+pub enum SpringBoneTimestep {
+    /// Simulates once per frame with the frame time.
+    Variable,
+    /// Simulates at the fixed interval and interpolates the rotations between the last two steps.
+    ///
+    /// The same input gives the same motion regardless of the frame rate.
+    Fixed(Duration),
+    /// Divides each frame into the number of steps.
+    Substeps(u32),
+}
+
+/// The shapes of the spring bone colliders attached to the node, in the order of `VRMC_springBone::colliders`.
+///
+/// A node can have several colliders, such as a sphere and a capsule on the head.
+/// [`VrmExporter`](crate::prelude::VrmExporter) writes them back into the colliders of the node in this order.
+#[derive(Component, Debug, Clone, Default, PartialEq, Deref, DerefMut, Reflect)]
+#[reflect(Component, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub struct ColliderShapes(pub Vec<ColliderShape>);
 
 /// The component that holds the spring bone state of each Joint
 ///
@@ -24,6 +94,17 @@ pub(crate) struct SpringJointState {
     bone_length: f32,
     initial_local_matrix: Mat4,
     initial_local_rotation: Quat,
+    /// The local rotations simulated at the last two steps, used for the interpolation.
+    prev_rotation: Quat,
+    current_rotation: Quat,
+}
+
+impl SpringJointState {
+    /// Returns the local rotation of the joint before the simulation started.
+    #[inline]
+    pub(crate) const fn initial_local_rotation(&self) -> Quat {
+        self.initial_local_rotation
+    }
 }
 
 #[derive(Component, Debug, Clone, PartialEq, Default, Reflect)]
@@ -79,7 +160,11 @@ impl Plugin for VrmSpringBonePlugin {
         &self,
         app: &mut App,
     ) {
-        app.register_type::<SpringRoot>()
+        app.register_type::<SpringBoneSimulationSettings>()
+            .register_type::<SpringBoneTimestep>()
+            .init_resource::<SpringBoneSimulationSettings>()
+            .register_type::<ColliderShapes>()
+            .register_type::<SpringRoot>()
             .register_type::<SpringJointState>()
             .register_type::<SpringJoints>()
             .register_type::<SpringColliders>()
@@ -22,11 +22,14 @@ impl Plugin for SpringBoneRegistryPlugin {
     }
 }
 
+/// The node names and shapes of the colliders in the order of `VRMC_springBone::colliders`.
+///
+/// A node appears several times if it has several colliders.
 #[derive(Component, Deref, Debug, Default, Clone, PartialEq, Reflect)]
 #[reflect(Component, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
-pub(crate) struct SpringColliderRegistry(pub(crate) HashMap<Name, ColliderShape>);
+pub(crate) struct SpringColliderRegistry(pub(crate) Vec<(Name, ColliderShape)>);
 
 impl SpringColliderRegistry {
     pub fn new(
@@ -40,7 +43,7 @@ impl SpringColliderRegistry {
                 .filter_map(|collider| {
                     let node_handle = nodes.get(collider.node)?;
                     let node = node_assets.get(node_handle)?;
-                    Some((Name::new(node.name.clone()), collider.shape))
+                    Some((Name::new(node.name.clone()), collider.resolved_shape()))
                 })
                 .collect(),
         )
@@ -138,7 +141,7 @@ fn obtain_colliders(
         .iter()
         .flat_map(|collider| {
             let name = get_node_name(collider.node, node_assets, nodes)?;
-            Some((name, collider.shape))
+            Some((name, collider.resolved_shape()))
         })
         .collect()
 }
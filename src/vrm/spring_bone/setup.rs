@@ -5,9 +5,10 @@ use crate::vrm::spring_bone::registry::{
     SpringColliderRegistry, SpringJointPropsRegistry, SpringNodeRegistry,
 };
 use crate::vrm::spring_bone::{
-    SpringCenterNode, SpringColliders, SpringJointState, SpringJoints, SpringRoot,
+    ColliderShapes, SpringCenterNode, SpringColliders, SpringJointState, SpringJoints, SpringRoot,
 };
 use bevy::app::{App, Update};
+use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
 
 pub struct SpringBoneSetupPlugin;
@@ -95,12 +96,16 @@ fn attach_collider_shapes(
         if !child_searcher.has_been_spawned_all_bones(entity, bone_registry) {
             return;
         }
+        let mut shapes = HashMap::<&Name, ColliderShapes>::new();
         for (name, shape) in nodes.iter() {
+            shapes.entry(name).or_default().push(*shape);
+        }
+        for (name, shapes) in shapes {
             let Some(collider_entity) = child_searcher.find_from_name(entity, name) else {
                 continue;
             };
             par_commands.command_scope(|mut commands| {
-                commands.entity(collider_entity).insert(*shape);
+                commands.entity(collider_entity).insert(shapes);
             });
         }
         par_commands.command_scope(|mut commands| {
@@ -194,6 +199,8 @@ fn init_spring_joint_states(
                 bone_length: tail_tf.translation.length(),
                 initial_local_matrix: head_tf.compute_matrix(),
                 initial_local_rotation: head_tf.rotation,
+                prev_rotation: head_tf.rotation,
+                current_rotation: head_tf.rotation,
             };
             par_commands.command_scope(|mut commands| {
                 commands.entity(head_entity).insert(state);
@@ -418,11 +425,7 @@ mod tests {
                         joints: vec![Name::new("head")],
                         ..default()
                     }]),
-                    SpringColliderRegistry(
-                        [(Name::new("head"), ColliderShape::default())]
-                            .into_iter()
-                            .collect(),
-                    ),
+                    SpringColliderRegistry(vec![(Name::new("head"), ColliderShape::default())]),
                     SpringJointPropsRegistry(
                         [(Name::new("head"), SpringJointProps::default())]
                             .into_iter()
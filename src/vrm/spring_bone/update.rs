@@ -1,10 +1,14 @@
 use crate::system_set::VrmSystemSets;
 use crate::vrm::gltf::extensions::vrmc_spring_bone::ColliderShape;
-use crate::vrm::spring_bone::{SpringJointProps, SpringJointState, SpringRoot};
+use crate::vrm::spring_bone::{
+    SpringBoneSimulationSettings, SpringBoneTimestep, SpringJointProps, SpringJointState,
+    SpringRoot,
+};
 use bevy::app::App;
 use bevy::math::Vec3;
 use bevy::prelude::*;
 use bevy::time::Time;
+use std::time::Duration;
 
 pub struct SpringBoneUpdatePlugin;
 
@@ -20,13 +24,83 @@ impl Plugin for SpringBoneUpdatePlugin {
     }
 }
 
+/// Divides the frame time into the simulation steps.
+#[derive(Debug, Default)]
+struct SpringBoneClock {
+    accumulated: Duration,
+}
+
+#[derive(Debug, PartialEq)]
+struct Steps {
+    count: u32,
+    delta_time: f32,
+    /// The ratio of the interpolation from the previous step to the current one.
+    alpha: f32,
+}
+
+impl SpringBoneClock {
+    fn advance(
+        &mut self,
+        settings: &SpringBoneSimulationSettings,
+        delta: Duration,
+    ) -> Steps {
+        let delta = delta.min(settings.max_delta);
+        let max_steps = settings.max_steps.max(1);
+        match settings.timestep {
+            SpringBoneTimestep::Fixed(step) if !step.is_zero() => {
+                self.accumulated += delta;
+                let count = self.accumulated.as_nanos() / step.as_nanos();
+                self.accumulated =
+                    Duration::from_nanos((self.accumulated.as_nanos() % step.as_nanos()) as u64);
+                Steps {
+                    count: count.min(max_steps as u128) as u32,
+                    delta_time: step.as_secs_f32(),
+                    alpha: self.accumulated.as_secs_f32() / step.as_secs_f32(),
+                }
+            }
+            SpringBoneTimestep::Substeps(count) if 1 < count => {
+                let count = count.min(max_steps);
+                Steps {
+                    count,
+                    delta_time: delta.as_secs_f32() / count as f32,
+                    alpha: 1.0,
+                }
+            }
+            _ => Steps {
+                count: 1,
+                delta_time: delta.as_secs_f32(),
+                alpha: 1.0,
+            },
+        }
+    }
+}
+
 fn update_spring_bones(
     mut transforms: Query<(&mut Transform, &mut GlobalTransform)>,
     mut joints: Query<(&ChildOf, &mut SpringJointState, &SpringJointProps)>,
     spring_roots: Query<&SpringRoot>,
     time: Res<Time>,
+    settings: Res<SpringBoneSimulationSettings>,
+    mut clock: Local<SpringBoneClock>,
+) {
+    let steps = clock.advance(&settings, time.delta());
+    for _ in 0..steps.count {
+        simulate(
+            steps.delta_time,
+            &mut transforms,
+            &mut joints,
+            &spring_roots,
+        );
+    }
+    interpolate(steps.alpha, &mut transforms, &mut joints, &spring_roots);
+}
+
+fn simulate(
+    delta_time: f32,
+    transforms: &mut Query<(&mut Transform, &mut GlobalTransform)>,
+    joints: &mut Query<(&ChildOf, &mut SpringJointState, &SpringJointProps)>,
+    spring_roots: &Query<&SpringRoot>,
 ) {
-    let delta_time = time.delta_secs();
     for spring_root in spring_roots.iter() {
         let center_gtf = spring_root
             .center_node
@@ -42,7 +116,11 @@ fn update_spring_bones(
                 .map(|(_, gtf)| *gtf)
                 .unwrap_or_default();
             let parent_global_rotation = parent_gtf.to_scale_rotation_translation().1;
-            let Ok(head_global_pos) = transforms.get(joint).map(|(_, gtf)| gtf.translation())
+            // The global transform of the joint may be stale after the previous joint has moved,
+            // so the head is placed from the parent instead.
+            let Ok(head_global_pos) = transforms
+                .get(joint)
+                .map(|(tf, _)| parent_gtf.transform_point(tf.translation))
             else {
                 continue;
             };
@@ -67,7 +145,7 @@ fn update_spring_bones(
                 props.hit_radius,
                 head_global_pos,
                 state.bone_length,
-                &transforms,
+                transforms,
             );
 
             state.prev_tail = state.current_tail;
@@ -85,6 +163,34 @@ fn update_spring_bones(
             tf.rotation =
                 state.initial_local_rotation * Quat::from_rotation_arc(state.bone_axis, to);
             *gtf = parent_gtf.mul_transform(*tf);
+            state.prev_rotation = state.current_rotation;
+            state.current_rotation = tf.rotation;
+        }
+    }
+}
+
+/// Applies the rotations interpolated between the last two steps.
+fn interpolate(
+    alpha: f32,
+    transforms: &mut Query<(&mut Transform, &mut GlobalTransform)>,
+    joints: &mut Query<(&ChildOf, &mut SpringJointState, &SpringJointProps)>,
+    spring_roots: &Query<&SpringRoot>,
+) {
+    for spring_root in spring_roots.iter() {
+        for joint in spring_root.joints.iter().copied() {
+            let Ok((child_of, state, _)) = joints.get(joint) else {
+                continue;
+            };
+            let parent_gtf = transforms
+                .get(child_of.parent())
+                .map(|(_, gtf)| *gtf)
+                .unwrap_or_default();
+            let rotation = state.prev_rotation.slerp(state.current_rotation, alpha);
+            let Ok((mut tf, mut gtf)) = transforms.get_mut(joint) else {
+                continue;
+            };
+            tf.rotation = rotation;
+            *gtf = parent_gtf.mul_transform(*tf);
         }
     }
 }
@@ -132,3 +238,173 @@ fn apply_collision(
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::vrm::spring_bone::update::{interpolate, simulate, SpringBoneClock, Steps};
+    use crate::vrm::spring_bone::{
+        SpringBoneSimulationSettings, SpringBoneTimestep, SpringJointProps, SpringJointState,
+        SpringJoints, SpringRoot,
+    };
+    use bevy::ecs::system::SystemState;
+    use bevy::prelude::*;
+    use std::time::Duration;
+
+    fn settings(timestep: SpringBoneTimestep) -> SpringBoneSimulationSettings {
+        SpringBoneSimulationSettings {
+            timestep,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fixed_steps_independent_of_frame_rate() {
+        let settings = settings(SpringBoneTimestep::Fixed(Duration::from_millis(20)));
+        let mut slow = SpringBoneClock::default();
+        let mut fast = SpringBoneClock::default();
+        let slow_steps = (0..3)
+            .map(|_| slow.advance(&settings, Duration::from_millis(40)).count)
+            .sum::<u32>();
+        let mut fast_steps = 0;
+        let mut alpha = 0.0;
+        for _ in 0..12 {
+            let steps = fast.advance(&settings, Duration::from_millis(10));
+            fast_steps += steps.count;
+            alpha = steps.alpha;
+        }
+        assert_eq!(slow_steps, 6);
+        assert_eq!(fast_steps, 6);
+        assert_eq!(alpha, 0.0);
+
+        let steps = fast.advance(&settings, Duration::from_millis(10));
+        assert_eq!(steps.count, 0);
+        assert!((steps.alpha - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn clamp_delta() {
+        let settings = settings(SpringBoneTimestep::Variable);
+        let steps = SpringBoneClock::default().advance(&settings, Duration::from_secs(2));
+        assert_eq!(
+            steps,
+            Steps {
+                count: 1,
+                delta_time: 0.1,
+                alpha: 1.0,
+            }
+        );
+    }
+
+    #[test]
+    fn split_into_substeps() {
+        let settings = settings(SpringBoneTimestep::Substeps(4));
+        let steps = SpringBoneClock::default().advance(&settings, Duration::from_millis(40));
+        assert_eq!(steps.count, 4);
+        assert!((steps.delta_time - 0.01).abs() < 1e-6);
+        assert_eq!(steps.alpha, 1.0);
+    }
+
+    #[test]
+    fn cap_steps_per_frame() {
+        let fixed = settings(SpringBoneTimestep::Fixed(Duration::from_micros(1)));
+        let mut clock = SpringBoneClock::default();
+        let steps = clock.advance(&fixed, Duration::from_millis(100));
+        assert_eq!(steps.count, fixed.max_steps);
+        assert_eq!(clock.accumulated, Duration::ZERO);
+
+        let substeps = settings(SpringBoneTimestep::Substeps(1000));
+        let steps = SpringBoneClock::default().advance(&substeps, Duration::from_millis(80));
+        assert_eq!(steps.count, substeps.max_steps);
+        assert!((steps.delta_time * steps.count as f32 - 0.08).abs() < 1e-6);
+    }
+
+    /// Swings a horizontal chain of two joints down by gravity and returns the tails and rotations.
+    fn swing_chain(
+        settings: &SpringBoneSimulationSettings,
+        frame: Duration,
+        frames: u32,
+    ) -> Vec<(Vec3, Quat)> {
+        let mut world = World::new();
+        let root = world.spawn(Transform::default()).id();
+        let joint1 = world.spawn((ChildOf(root), Transform::default())).id();
+        let joint2 = world
+            .spawn((ChildOf(joint1), Transform::from_xyz(0.1, 0.0, 0.0)))
+            .id();
+        let joints = [joint1, joint2];
+        for (i, joint) in joints.into_iter().enumerate() {
+            let tail = Vec3::new(0.1 * (i + 1) as f32, 0.0, 0.0);
+            let head_tf = *world.get::<Transform>(joint).unwrap();
+            world.entity_mut(joint).insert((
+                GlobalTransform::from_translation(tail - Vec3::X * 0.1),
+                SpringJointState {
+                    prev_tail: tail,
+                    current_tail: tail,
+                    bone_axis: Vec3::X,
+                    bone_length: 0.1,
+                    initial_local_matrix: head_tf.compute_matrix(),
+                    ..default()
+                },
+                SpringJointProps {
+                    drag_force: 0.4,
+                    gravity_dir: Vec3::NEG_Y,
+                    gravity_power: 1.0,
+                    hit_radius: 0.0,
+                    stiffness: 0.5,
+                },
+            ));
+        }
+        world.entity_mut(root).insert((
+            GlobalTransform::default(),
+            SpringRoot {
+                joints: SpringJoints(joints.to_vec()),
+                ..default()
+            },
+        ));
+
+        let mut state: SystemState<(
+            Query<(&mut Transform, &mut GlobalTransform)>,
+            Query<(&ChildOf, &mut SpringJointState, &SpringJointProps)>,
+            Query<&SpringRoot>,
+        )> = SystemState::new(&mut world);
+        let mut clock = SpringBoneClock::default();
+        for _ in 0..frames {
+            let steps = clock.advance(settings, frame);
+            let (mut transforms, mut joint_states, spring_roots) = state.get_mut(&mut world);
+            for _ in 0..steps.count {
+                simulate(
+                    steps.delta_time,
+                    &mut transforms,
+                    &mut joint_states,
+                    &spring_roots,
+                );
+            }
+            interpolate(
+                steps.alpha,
+                &mut transforms,
+                &mut joint_states,
+                &spring_roots,
+            );
+        }
+        joints
+            .iter()
+            .map(|joint| {
+                (
+                    world.get::<SpringJointState>(*joint).unwrap().current_tail,
+                    world.get::<Transform>(*joint).unwrap().rotation,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chain_independent_of_frame_rate() {
+        let settings = settings(SpringBoneTimestep::Fixed(Duration::from_millis(10)));
+        let fast = swing_chain(&settings, Duration::from_millis(10), 9);
+        let slow = swing_chain(&settings, Duration::from_millis(15), 6);
+        assert!(fast[1].0.y < -0.05, "{fast:?}");
+        for ((fast_tail, fast_rotation), (slow_tail, slow_rotation)) in fast.iter().zip(&slow) {
+            assert!(fast_tail.abs_diff_eq(*slow_tail, 1e-5), "{fast:?} {slow:?}");
+            assert!(fast_rotation.abs_diff_eq(*slow_rotation, 1e-5));
+        }
+    }
+}
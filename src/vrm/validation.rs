@@ -0,0 +1,690 @@
+//! Validates the VRM extensions against the specification.
+//!
+//! - [`VRMC_vrm-1.0`](https://github.com/vrm-c/vrm-specification/blob/master/specification/VRMC_vrm-1.0/README.md)
+//! - [`VRMC_springBone-1.0`](https://github.com/vrm-c/vrm-specification/blob/master/specification/VRMC_springBone-1.0/README.md)
+//! - [`VRMC_materials_mtoon-1.0`](https://github.com/vrm-c/vrm-specification/blob/master/specification/VRMC_materials_mtoon-1.0/README.md)
+
+use crate::vrm::expressions::VrmExpressionPreset;
+use bevy::gltf::Gltf;
+use bevy::prelude::*;
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+
+pub mod prelude {
+    pub use crate::vrm::validation::{
+        validate_vrm, VrmDiagnostic, VrmDiagnosticSeverity, VrmValidationReport,
+    };
+}
+
+const SPEC_VERSION: &str = "1.0";
+
+/// The humanoid bones that are required by the specification.
+const REQUIRED_BONES: [&str; 15] = [
+    "hips",
+    "spine",
+    "head",
+    "leftUpperLeg",
+    "leftLowerLeg",
+    "leftFoot",
+    "rightUpperLeg",
+    "rightLowerLeg",
+    "rightFoot",
+    "leftUpperArm",
+    "leftLowerArm",
+    "leftHand",
+    "rightUpperArm",
+    "rightLowerArm",
+    "rightHand",
+];
+
+const OPTIONAL_BONES: [&str; 40] = [
+    "chest",
+    "upperChest",
+    "neck",
+    "leftEye",
+    "rightEye",
+    "jaw",
+    "leftToes",
+    "rightToes",
+    "leftShoulder",
+    "rightShoulder",
+    "leftThumbMetacarpal",
+    "leftThumbProximal",
+    "leftThumbDistal",
+    "leftIndexProximal",
+    "leftIndexIntermediate",
+    "leftIndexDistal",
+    "leftMiddleProximal",
+    "leftMiddleIntermediate",
+    "leftMiddleDistal",
+    "leftRingProximal",
+    "leftRingIntermediate",
+    "leftRingDistal",
+    "leftLittleProximal",
+    "leftLittleIntermediate",
+    "leftLittleDistal",
+    "rightThumbMetacarpal",
+    "rightThumbProximal",
+    "rightThumbDistal",
+    "rightIndexProximal",
+    "rightIndexIntermediate",
+    "rightIndexDistal",
+    "rightMiddleProximal",
+    "rightMiddleIntermediate",
+    "rightMiddleDistal",
+    "rightRingProximal",
+    "rightRingIntermediate",
+    "rightRingDistal",
+    "rightLittleProximal",
+    "rightLittleIntermediate",
+    "rightLittleDistal",
+];
+
+const MTOON_TEXTURES: [&str; 6] = [
+    "shadeMultiplyTexture",
+    "shadingShiftTexture",
+    "matcapTexture",
+    "rimMultiplyTexture",
+    "outlineWidthMultiplyTexture",
+    "uvAnimationMaskTexture",
+];
+
+/// The severity of [`VrmDiagnostic`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub enum VrmDiagnosticSeverity {
+    /// The model can be used, but it does not follow the specification.
+    Warning,
+    /// The model violates the specification and some features will not work.
+    Error,
+}
+
+/// A problem found by [`validate_vrm`].
+#[derive(Debug, Clone, PartialEq, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub struct VrmDiagnostic {
+    pub severity: VrmDiagnosticSeverity,
+    /// The [JSON pointer](https://datatracker.ietf.org/doc/html/rfc6901) to the problematic value in the glTF document.
+    pub pointer: String,
+    pub message: String,
+}
+
+impl Display for VrmDiagnostic {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(
+            f,
+            "[{:?}] {}: {}",
+            self.severity, self.pointer, self.message
+        )
+    }
+}
+
+/// The result of [`validate_vrm`].
+///
+/// This component is also inserted into the entity of [`VrmHandle`](crate::prelude::VrmHandle)
+/// if the VRM failed to spawn.
+#[derive(Component, Debug, Clone, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+pub struct VrmValidationReport {
+    pub diagnostics: Vec<VrmDiagnostic>,
+}
+
+impl VrmValidationReport {
+    /// Returns `true` if there is no [`VrmDiagnosticSeverity::Error`].
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// Returns the diagnostics whose severity is [`VrmDiagnosticSeverity::Error`].
+    pub fn errors(&self) -> impl Iterator<Item = &VrmDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == VrmDiagnosticSeverity::Error)
+    }
+
+    /// Returns the diagnostics whose severity is [`VrmDiagnosticSeverity::Warning`].
+    pub fn warnings(&self) -> impl Iterator<Item = &VrmDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == VrmDiagnosticSeverity::Warning)
+    }
+
+    fn error(
+        &mut self,
+        pointer: impl Into<String>,
+        message: impl Into<String>,
+    ) {
+        self.push(VrmDiagnosticSeverity::Error, pointer, message);
+    }
+
+    fn warning(
+        &mut self,
+        pointer: impl Into<String>,
+        message: impl Into<String>,
+    ) {
+        self.push(VrmDiagnosticSeverity::Warning, pointer, message);
+    }
+
+    fn push(
+        &mut self,
+        severity: VrmDiagnosticSeverity,
+        pointer: impl Into<String>,
+        message: impl Into<String>,
+    ) {
+        self.diagnostics.push(VrmDiagnostic {
+            severity,
+            pointer: pointer.into(),
+            message: message.into(),
+        });
+    }
+}
+
+/// Validates the VRM extensions of the glTF and lists every problem found.
+///
+/// The glTF must be loaded with `include_source`, which [`VrmAsset`](crate::prelude::VrmAsset) always enables.
+pub fn validate_vrm(gltf: &Gltf) -> VrmValidationReport {
+    let Some(source) = gltf.source.as_ref() else {
+        let mut report = VrmValidationReport::default();
+        report.error("", "The glTF source is not included");
+        return report;
+    };
+    match serde_json::to_value(source.document.as_json()) {
+        Ok(json) => validate_json(&json),
+        Err(e) => {
+            let mut report = VrmValidationReport::default();
+            report.error("", format!("Failed to serialize the glTF document: {e}"));
+            report
+        }
+    }
+}
+
+pub(crate) fn validate_json(json: &Value) -> VrmValidationReport {
+    let mut report = VrmValidationReport::default();
+    let validator = Validator { json };
+    match json.pointer("/extensions/VRMC_vrm") {
+        Some(vrmc_vrm) => {
+            let pointer = "/extensions/VRMC_vrm";
+            validator.spec_version(&mut report, vrmc_vrm, pointer);
+            validator.humanoid(&mut report, vrmc_vrm, pointer);
+            validator.expressions(&mut report, vrmc_vrm, pointer);
+            validator.first_person(&mut report, vrmc_vrm, pointer);
+            validator.meta(&mut report, vrmc_vrm, pointer);
+        }
+        None => report.error("/extensions/VRMC_vrm", "VRMC_vrm extension is missing"),
+    }
+    if let Some(spring_bone) = json.pointer("/extensions/VRMC_springBone") {
+        let pointer = "/extensions/VRMC_springBone";
+        validator.spec_version(&mut report, spring_bone, pointer);
+        validator.spring_bone(&mut report, spring_bone, pointer);
+    }
+    validator.materials(&mut report);
+    report
+}
+
+struct Validator<'a> {
+    json: &'a Value,
+}
+
+impl Validator<'_> {
+    fn len(
+        &self,
+        key: &str,
+    ) -> usize {
+        self.json[key].as_array().map(Vec::len).unwrap_or_default()
+    }
+
+    fn spec_version(
+        &self,
+        report: &mut VrmValidationReport,
+        extension: &Value,
+        pointer: &str,
+    ) {
+        match extension["specVersion"].as_str() {
+            Some(SPEC_VERSION) => {}
+            Some(version) => report.warning(
+                format!("{pointer}/specVersion"),
+                format!("Expected specVersion {SPEC_VERSION}, but found {version}"),
+            ),
+            None => report.error(format!("{pointer}/specVersion"), "specVersion is missing"),
+        }
+    }
+
+    /// Checks that the value is a valid index of the top-level array `key`.
+    fn index(
+        &self,
+        report: &mut VrmValidationReport,
+        value: &Value,
+        key: &str,
+        pointer: String,
+    ) -> Option<usize> {
+        let Some(index) = value.as_u64().map(|i| i as usize) else {
+            report.error(pointer, format!("The index of {key} is missing"));
+            return None;
+        };
+        let len = self.len(key);
+        if len <= index {
+            report.error(
+                pointer,
+                format!("{key}[{index}] is out of range (length: {len})"),
+            );
+            return None;
+        }
+        Some(index)
+    }
+
+    fn humanoid(
+        &self,
+        report: &mut VrmValidationReport,
+        vrmc_vrm: &Value,
+        pointer: &str,
+    ) {
+        let pointer = format!("{pointer}/humanoid/humanBones");
+        let Some(bones) = vrmc_vrm["humanoid"]["humanBones"].as_object() else {
+            report.error(pointer, "humanoid.humanBones is missing");
+            return;
+        };
+        for required in REQUIRED_BONES {
+            if !bones.contains_key(required) {
+                report.error(
+                    pointer.clone(),
+                    format!("The required humanoid bone `{required}` is missing"),
+                );
+            }
+        }
+        for (bone, node) in bones {
+            let bone_pointer = format!("{pointer}/{}", escape(bone));
+            if !REQUIRED_BONES.contains(&bone.as_str()) && !OPTIONAL_BONES.contains(&bone.as_str())
+            {
+                report.warning(
+                    bone_pointer.clone(),
+                    format!("Unknown humanoid bone `{bone}`"),
+                );
+            }
+            self.index(
+                report,
+                &node["node"],
+                "nodes",
+                format!("{bone_pointer}/node"),
+            );
+        }
+    }
+
+    fn expressions(
+        &self,
+        report: &mut VrmValidationReport,
+        vrmc_vrm: &Value,
+        pointer: &str,
+    ) {
+        let expressions = &vrmc_vrm["expressions"];
+        if let Some(presets) = expressions["preset"].as_object() {
+            for (name, expression) in presets {
+                let pointer = format!("{pointer}/expressions/preset/{}", escape(name));
+                if VrmExpressionPreset::from_name(name).is_none() {
+                    report.warning(
+                        pointer.clone(),
+                        format!("Unknown expression preset `{name}`"),
+                    );
+                }
+                self.morph_target_binds(report, expression, &pointer);
+            }
+        }
+        if let Some(custom) = expressions["custom"].as_object() {
+            for (name, expression) in custom {
+                let pointer = format!("{pointer}/expressions/custom/{}", escape(name));
+                if VrmExpressionPreset::from_name(name).is_some() {
+                    report.error(
+                        pointer.clone(),
+                        format!("The custom expression `{name}` conflicts with a preset"),
+                    );
+                }
+                self.morph_target_binds(report, expression, &pointer);
+            }
+        }
+    }
+
+    fn morph_target_binds(
+        &self,
+        report: &mut VrmValidationReport,
+        expression: &Value,
+        pointer: &str,
+    ) {
+        let Some(binds) = expression["morphTargetBinds"].as_array() else {
+            return;
+        };
+        for (i, bind) in binds.iter().enumerate() {
+            let pointer = format!("{pointer}/morphTargetBinds/{i}");
+            let Some(node) = self.index(report, &bind["node"], "nodes", format!("{pointer}/node"))
+            else {
+                continue;
+            };
+            let Some(mesh) = self.json["nodes"][node]["mesh"].as_u64() else {
+                report.error(
+                    format!("{pointer}/node"),
+                    format!("nodes[{node}] does not have a mesh"),
+                );
+                continue;
+            };
+            let targets = self.json["meshes"][mesh as usize]["primitives"]
+                .as_array()
+                .and_then(|primitives| primitives.first())
+                .and_then(|primitive| primitive["targets"].as_array())
+                .map(Vec::len)
+                .unwrap_or_default();
+            match bind["index"].as_u64() {
+                Some(index) if (index as usize) < targets => {}
+                Some(index) => report.error(
+                    format!("{pointer}/index"),
+                    format!(
+                        "The morph target {index} is out of range (meshes[{mesh}] has {targets})"
+                    ),
+                ),
+                None => report.error(format!("{pointer}/index"), "index is missing"),
+            }
+            if let Some(weight) = bind["weight"].as_f64() {
+                if !(0.0..=1.0).contains(&weight) {
+                    report.warning(
+                        format!("{pointer}/weight"),
+                        format!("The weight {weight} is out of the range [0, 1]"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn first_person(
+        &self,
+        report: &mut VrmValidationReport,
+        vrmc_vrm: &Value,
+        pointer: &str,
+    ) {
+        let Some(annotations) = vrmc_vrm["firstPerson"]["meshAnnotations"].as_array() else {
+            return;
+        };
+        for (i, annotation) in annotations.iter().enumerate() {
+            let pointer = format!("{pointer}/firstPerson/meshAnnotations/{i}");
+            self.index(
+                report,
+                &annotation["node"],
+                "nodes",
+                format!("{pointer}/node"),
+            );
+            if !matches!(
+                annotation["type"].as_str(),
+                Some("auto" | "both" | "thirdPersonOnly" | "firstPersonOnly")
+            ) {
+                report.error(format!("{pointer}/type"), "Invalid mesh annotation type");
+            }
+        }
+    }
+
+    fn meta(
+        &self,
+        report: &mut VrmValidationReport,
+        vrmc_vrm: &Value,
+        pointer: &str,
+    ) {
+        let meta = &vrmc_vrm["meta"];
+        if meta.is_null() {
+            report.error(format!("{pointer}/meta"), "meta is missing");
+            return;
+        }
+        if !meta["thumbnailImage"].is_null() {
+            self.index(
+                report,
+                &meta["thumbnailImage"],
+                "images",
+                format!("{pointer}/meta/thumbnailImage"),
+            );
+        }
+    }
+
+    fn spring_bone(
+        &self,
+        report: &mut VrmValidationReport,
+        spring_bone: &Value,
+        pointer: &str,
+    ) {
+        let colliders = spring_bone["colliders"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for (i, collider) in colliders.iter().enumerate() {
+            self.index(
+                report,
+                &collider["node"],
+                "nodes",
+                format!("{pointer}/colliders/{i}/node"),
+            );
+        }
+        let groups = spring_bone["colliderGroups"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for (i, group) in groups.iter().enumerate() {
+            for (j, collider) in group["colliders"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .enumerate()
+            {
+                if colliders.len() <= collider.as_u64().unwrap_or(u64::MAX) as usize {
+                    report.error(
+                        format!("{pointer}/colliderGroups/{i}/colliders/{j}"),
+                        format!("colliders[{collider}] is out of range"),
+                    );
+                }
+            }
+        }
+        let springs = spring_bone["springs"].as_array().into_iter().flatten();
+        for (i, spring) in springs.enumerate() {
+            let pointer = format!("{pointer}/springs/{i}");
+            for (j, joint) in spring["joints"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .enumerate()
+            {
+                self.index(
+                    report,
+                    &joint["node"],
+                    "nodes",
+                    format!("{pointer}/joints/{j}/node"),
+                );
+            }
+            for (j, group) in spring["colliderGroups"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .enumerate()
+            {
+                if groups.len() <= group.as_u64().unwrap_or(u64::MAX) as usize {
+                    report.error(
+                        format!("{pointer}/colliderGroups/{j}"),
+                        format!("colliderGroups[{group}] is out of range"),
+                    );
+                }
+            }
+            if !spring["center"].is_null() {
+                self.index(
+                    report,
+                    &spring["center"],
+                    "nodes",
+                    format!("{pointer}/center"),
+                );
+            }
+        }
+    }
+
+    fn materials(
+        &self,
+        report: &mut VrmValidationReport,
+    ) {
+        let materials = self.json["materials"].as_array().into_iter().flatten();
+        for (i, material) in materials.enumerate() {
+            let Some(mtoon) = material.pointer("/extensions/VRMC_materials_mtoon") else {
+                continue;
+            };
+            let pointer = format!("/materials/{i}/extensions/VRMC_materials_mtoon");
+            self.spec_version(report, mtoon, &pointer);
+            for key in MTOON_TEXTURES {
+                let texture = &mtoon[key];
+                if !texture.is_null() {
+                    self.index(
+                        report,
+                        &texture["index"],
+                        "textures",
+                        format!("{pointer}/{key}/index"),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Escapes the reference token of JSON pointer.
+fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vrm::validation::{validate_json, VrmDiagnosticSeverity, REQUIRED_BONES};
+    use serde_json::{json, Value};
+
+    fn valid_vrm() -> Value {
+        let bones = REQUIRED_BONES
+            .iter()
+            .enumerate()
+            .map(|(i, bone)| (bone.to_string(), json!({ "node": i })))
+            .collect::<serde_json::Map<_, _>>();
+        json!({
+            "nodes": (0..16).map(|i| if i == 15 { json!({ "mesh": 0 }) } else { json!({}) }).collect::<Vec<_>>(),
+            "meshes": [{ "primitives": [{ "targets": [{}, {}] }] }],
+            "textures": [{}],
+            "extensions": {
+                "VRMC_vrm": {
+                    "specVersion": "1.0",
+                    "meta": { "name": "test" },
+                    "humanoid": { "humanBones": bones },
+                    "expressions": {
+                        "preset": {
+                            "happy": { "morphTargetBinds": [{ "node": 15, "index": 1, "weight": 1.0 }] }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn pointers(json: &Value) -> Vec<String> {
+        validate_json(json)
+            .diagnostics
+            .into_iter()
+            .map(|d| d.pointer)
+            .collect()
+    }
+
+    #[test]
+    fn valid() {
+        let report = validate_json(&valid_vrm());
+        assert!(report.diagnostics.is_empty(), "{:?}", report.diagnostics);
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn missing_required_bone() {
+        let mut json = valid_vrm();
+        json["extensions"]["VRMC_vrm"]["humanoid"]["humanBones"]
+            .as_object_mut()
+            .unwrap()
+            .remove("head");
+        let report = validate_json(&json);
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.diagnostics[0].pointer,
+            "/extensions/VRMC_vrm/humanoid/humanBones"
+        );
+    }
+
+    #[test]
+    fn out_of_range_morph_target() {
+        let mut json = valid_vrm();
+        json["extensions"]["VRMC_vrm"]["expressions"]["preset"]["happy"]["morphTargetBinds"][0]
+            ["index"] = json!(2);
+        assert_eq!(
+            pointers(&json),
+            vec!["/extensions/VRMC_vrm/expressions/preset/happy/morphTargetBinds/0/index"]
+        );
+    }
+
+    #[test]
+    fn unknown_expression_preset() {
+        let mut json = valid_vrm();
+        json["extensions"]["VRMC_vrm"]["expressions"]["preset"]["joy/1"] = json!({});
+        let report = validate_json(&json);
+        assert!(report.is_valid());
+        assert_eq!(
+            report.warnings().next().unwrap().pointer,
+            "/extensions/VRMC_vrm/expressions/preset/joy~11"
+        );
+    }
+
+    #[test]
+    fn invalid_spring_bone_nodes() {
+        let mut json = valid_vrm();
+        json["extensions"]["VRMC_springBone"] = json!({
+            "specVersion": "1.0",
+            "colliders": [{ "node": 100, "shape": {} }],
+            "colliderGroups": [{ "colliders": [0, 1] }],
+            "springs": [{ "joints": [{ "node": 0 }, { "node": 99 }], "colliderGroups": [0] }],
+        });
+        assert_eq!(
+            pointers(&json),
+            vec![
+                "/extensions/VRMC_springBone/colliders/0/node",
+                "/extensions/VRMC_springBone/colliderGroups/0/colliders/1",
+                "/extensions/VRMC_springBone/springs/0/joints/1/node",
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_mtoon_texture_and_spec_version() {
+        let mut json = valid_vrm();
+        json["materials"] = json!([{
+            "extensions": {
+                "VRMC_materials_mtoon": {
+                    "specVersion": "0.9",
+                    "shadeMultiplyTexture": { "index": 0 },
+                    "matcapTexture": { "index": 3 }
+                }
+            }
+        }]);
+        let report = validate_json(&json);
+        let severities = report
+            .diagnostics
+            .iter()
+            .map(|d| (d.severity, d.pointer.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            severities,
+            vec![
+                (
+                    VrmDiagnosticSeverity::Warning,
+                    "/materials/0/extensions/VRMC_materials_mtoon/specVersion"
+                ),
+                (
+                    VrmDiagnosticSeverity::Error,
+                    "/materials/0/extensions/VRMC_materials_mtoon/matcapTexture/index"
+                ),
+            ]
+        );
+    }
+}
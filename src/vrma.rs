@@ -1,12 +1,14 @@
 pub(crate) mod animation;
 mod gltf;
 mod loader;
+mod recorder;
 mod retarget;
 mod spawn;
 
 use crate::macros::{entity_component, marker_component};
 use crate::vrma::animation::VrmaAnimationPlayersPlugin;
 use crate::vrma::loader::{VrmaAsset, VrmaLoaderPlugin};
+use crate::vrma::recorder::VrmaRecorderPlugin;
 use crate::vrma::retarget::VrmaRetargetPlugin;
 use crate::vrma::spawn::VrmaSpawnPlugin;
 use bevy::app::App;
@@ -17,8 +19,10 @@ use std::time::Duration;
 
 pub mod prelude {
     pub use crate::vrma::{
-        animation::prelude::*, loader::VrmaAsset, LoadedVrma, Vrma, VrmaDuration, VrmaEntity,
-        VrmaHandle, VrmaPath, VrmaPlugin,
+        animation::prelude::*,
+        loader::VrmaAsset,
+        recorder::{VrmaRecorded, VrmaRecorder},
+        LoadedVrma, Vrma, VrmaDuration, VrmaEntity, VrmaHandle, VrmaPath, VrmaPlugin,
     };
 }
 
@@ -36,6 +40,7 @@ impl Plugin for VrmaPlugin {
             VrmaSpawnPlugin,
             VrmaRetargetPlugin,
             VrmaAnimationPlayersPlugin,
+            VrmaRecorderPlugin,
         ));
 
         app.register_type::<Vrma>()
@@ -0,0 +1,538 @@
+//! This module records the humanoid pose and expressions of VRM into VRMA.
+//!
+//! - [`how to transform human pose`](https://github.com/vrm-c/vrm-specification/blob/master/specification/VRMC_vrm_animation-1.0/how_to_transform_human_pose.md)
+
+use crate::error::AppResult;
+use crate::glb::BufferWriter;
+use crate::prelude::*;
+use crate::system_param::child_searcher::ChildSearcher;
+use crate::vrm::expressions::{
+    requested_weight, ProceduralExpressionWeights, VrmExpressionRegistry,
+};
+use crate::vrm::humanoid_bone::{HumanoidBoneRegistry, HumanoidBonesAttached};
+use crate::vrm::{VrmBone, VrmExpression};
+use anyhow::bail;
+use bevy::app::{Animation, App, Plugin, PostUpdate};
+use bevy::prelude::*;
+use serde_json::{json, Map};
+use std::time::Duration;
+
+/// Records the humanoid pose and expression weights of VRM into VRMA.
+///
+/// Insert this component into the VRM entity to start recording.
+/// Each frame, the normalized rotation of each humanoid bone, the hips translation and
+/// the weight of each expression are sampled.
+///
+/// The expression weights include those driven by the built-in behaviours,
+/// such as [`AutoBlink`] and lip sync, so the take blinks and talks as the VRM did.
+/// They are sampled before `isBinary` and the override rules are applied,
+/// because those are applied again when the VRMA is played.
+///
+/// Removing this component stops recording and triggers [`VrmaRecorded`] on the VRM entity.
+/// If it is removed before any frame is sampled, nothing is triggered.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_vrm1::prelude::*;
+///
+/// fn start(mut commands: Commands, vrm: Query<Entity, With<Vrm>>) {
+///     commands.entity(vrm.single().unwrap()).insert(VrmaRecorder::default());
+/// }
+///
+/// fn stop(mut commands: Commands, vrm: Query<Entity, With<Vrm>>) {
+///     commands.entity(vrm.single().unwrap()).remove::<VrmaRecorder>();
+/// }
+///
+/// fn save(trigger: Trigger<VrmaRecorded>) {
+///     std::fs::write("take.vrma", &trigger.vrma).unwrap();
+/// }
+/// ```
+#[derive(Component, Debug, Default, Clone)]
+pub struct VrmaRecorder {
+    elapsed: Duration,
+    skeleton: Vec<RecordedBone>,
+    expressions: Vec<RecordedExpression>,
+    times: Vec<f32>,
+    hips_translations: Vec<Vec3>,
+}
+
+impl VrmaRecorder {
+    /// Returns the duration recorded so far.
+    #[inline]
+    pub const fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    fn is_initialized(&self) -> bool {
+        !self.skeleton.is_empty()
+    }
+
+    /// Builds the bytes of the `.vrma` file from the recorded samples.
+    fn to_vrma(&self) -> AppResult<Vec<u8>> {
+        if self.times.is_empty() {
+            bail!("No frames were recorded");
+        }
+        let mut writer = BufferWriter::default();
+        let input = writer.push_f32(&self.times, "SCALAR", true);
+
+        let mut nodes = Vec::new();
+        let mut channels = Vec::new();
+        let mut samplers = Vec::new();
+        let mut push_channel =
+            |writer: &mut BufferWriter, node: usize, path: &str, output: &[f32]| {
+                let output = match path {
+                    "rotation" => writer.push_f32(output, "VEC4", false),
+                    _ => writer.push_f32(output, "VEC3", false),
+                };
+                samplers
+                    .push(json!({ "input": input, "output": output, "interpolation": "LINEAR" }));
+                channels.push(json!({
+                    "sampler": samplers.len() - 1,
+                    "target": { "node": node, "path": path },
+                }));
+            };
+
+        let mut human_bones = Map::new();
+        for bone in &self.skeleton {
+            let node = nodes.len();
+            nodes.push(json!({
+                "name": bone.bone.0,
+                "translation": bone.translation.to_array(),
+            }));
+            human_bones.insert(bone.bone.0.clone(), json!({ "node": node }));
+            let rotations = bone
+                .rotations
+                .iter()
+                .flat_map(|r| r.to_array())
+                .collect::<Vec<_>>();
+            push_channel(&mut writer, node, "rotation", &rotations);
+        }
+        for (index, bone) in self.skeleton.iter().enumerate() {
+            let children = self
+                .skeleton
+                .iter()
+                .enumerate()
+                .filter(|(_, child)| child.parent == Some(index))
+                .map(|(child, _)| child)
+                .collect::<Vec<_>>();
+            if !children.is_empty() {
+                nodes[index]["children"] = json!(children);
+            }
+            if bone.bone.0 == "hips" {
+                let translations = self
+                    .hips_translations
+                    .iter()
+                    .flat_map(|t| t.to_array())
+                    .collect::<Vec<_>>();
+                push_channel(&mut writer, index, "translation", &translations);
+            }
+        }
+
+        let mut presets = Map::new();
+        let mut custom = Map::new();
+        for expression in &self.expressions {
+            let node = nodes.len();
+            nodes.push(json!({ "name": expression.expression.as_str() }));
+            let expressions = if expression.expression.is_custom() {
+                &mut custom
+            } else {
+                &mut presets
+            };
+            expressions.insert(expression.expression.to_string(), json!({ "node": node }));
+            // VRMA uses x coordinate to represent expression weight.
+            let translations = expression
+                .weights
+                .iter()
+                .flat_map(|w| [*w, 0., 0.])
+                .collect::<Vec<_>>();
+            push_channel(&mut writer, node, "translation", &translations);
+        }
+
+        let roots = self
+            .skeleton
+            .iter()
+            .enumerate()
+            .filter(|(_, bone)| bone.parent.is_none())
+            .map(|(index, _)| index)
+            .chain(self.skeleton.len()..nodes.len())
+            .collect::<Vec<_>>();
+        let json = json!({
+            "asset": { "version": "2.0", "generator": "bevy_vrm1" },
+            "extensionsUsed": ["VRMC_vrm_animation"],
+            "extensions": {
+                "VRMC_vrm_animation": {
+                    "specVersion": "1.0",
+                    "humanoid": { "humanBones": human_bones },
+                    "expressions": { "preset": presets, "custom": custom },
+                }
+            },
+            "scene": 0,
+            "scenes": [{ "nodes": roots }],
+            "nodes": nodes,
+            "animations": [{ "channels": channels, "samplers": samplers }],
+        });
+        writer.into_glb(json).to_bytes()
+    }
+}
+
+/// An event that is emitted when [`VrmaRecorder`] is removed.
+///
+/// This event is emitted as a trigger.
+/// The target of the trigger is the VRM entity.
+#[derive(Event, Debug, Clone)]
+pub struct VrmaRecorded {
+    /// The bytes of the `.vrma` file.
+    pub vrma: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+struct RecordedBone {
+    bone: VrmBone,
+    entity: Entity,
+    parent: Option<usize>,
+    /// The rest transform of the bone in the model space.
+    model_rest: Transform,
+    /// The translation of VRMA's node whose rest rotations are all identity.
+    translation: Vec3,
+    rotations: Vec<Quat>,
+}
+
+#[derive(Debug, Clone)]
+struct RecordedExpression {
+    expression: VrmExpression,
+    weights: Vec<f32>,
+}
+
+pub(super) struct VrmaRecorderPlugin;
+
+impl Plugin for VrmaRecorderPlugin {
+    fn build(
+        &self,
+        app: &mut App,
+    ) {
+        app.add_systems(
+            PostUpdate,
+            record
+                .after(Animation)
+                .before(TransformSystem::TransformPropagate),
+        )
+        .add_observer(trigger_recorded);
+    }
+}
+
+fn record(
+    time: Res<Time>,
+    mut recorders: Query<
+        (
+            Entity,
+            &mut VrmaRecorder,
+            &HumanoidBoneRegistry,
+            Option<&VrmExpressionRegistry>,
+            Option<&VrmExpressionWeights>,
+            Option<&ProceduralExpressionWeights>,
+        ),
+        With<HumanoidBonesAttached>,
+    >,
+    searcher: ChildSearcher,
+    parents: Query<&ChildOf>,
+    transforms: Query<(&Transform, Option<&BoneRestTransform>)>,
+) {
+    for (vrm, mut recorder, bones, expressions, weights, procedural) in recorders.iter_mut() {
+        if !recorder.is_initialized() {
+            recorder.skeleton = obtain_skeleton(vrm, bones, &searcher, &parents, &transforms);
+            recorder.expressions = expressions.map(obtain_expressions).unwrap_or_default();
+            if !recorder.is_initialized() {
+                continue;
+            }
+        }
+
+        let elapsed = recorder.elapsed;
+        recorder.times.push(elapsed.as_secs_f32());
+        recorder.elapsed += time.delta();
+
+        let recorder = recorder.as_mut();
+        for bone in recorder.skeleton.iter_mut() {
+            let Ok((pose, Some(rest))) = transforms.get(bone.entity) else {
+                bone.rotations.push(Quat::IDENTITY);
+                continue;
+            };
+            bone.rotations
+                .push(normalized_rotation(&bone.model_rest, rest, pose));
+            if bone.bone.0 == "hips" {
+                recorder
+                    .hips_translations
+                    .push(hips_translation(&bone.model_rest, rest, pose));
+            }
+        }
+        for expression in recorder.expressions.iter_mut() {
+            let weight = weights
+                .map(|weights| requested_weight(weights, procedural, &expression.expression))
+                .unwrap_or_default();
+            expression.weights.push(weight);
+        }
+    }
+}
+
+fn trigger_recorded(
+    trigger: Trigger<OnRemove, VrmaRecorder>,
+    mut commands: Commands,
+    recorders: Query<&VrmaRecorder>,
+) {
+    let Ok(recorder) = recorders.get(trigger.target()) else {
+        return;
+    };
+    if recorder.times.is_empty() {
+        warn!("VrmaRecorder was removed before any frame was recorded");
+        return;
+    }
+    match recorder.to_vrma() {
+        Ok(vrma) => {
+            commands
+                .entity(trigger.target())
+                .trigger(VrmaRecorded { vrma });
+        }
+        Err(_e) => {
+            error!("Failed to build VRMA: {_e}");
+        }
+    }
+}
+
+fn obtain_skeleton(
+    vrm: Entity,
+    bones: &HumanoidBoneRegistry,
+    searcher: &ChildSearcher,
+    parents: &Query<&ChildOf>,
+    transforms: &Query<(&Transform, Option<&BoneRestTransform>)>,
+) -> Vec<RecordedBone> {
+    let mut skeleton = bones
+        .keys()
+        .filter_map(|bone| {
+            let entity = searcher.find_from_bone_name(vrm, bone)?;
+            Some(RecordedBone {
+                bone: bone.clone(),
+                entity,
+                parent: None,
+                model_rest: model_space_rest(vrm, entity, parents, transforms)?,
+                translation: Vec3::ZERO,
+                rotations: Vec::new(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let entities = skeleton.iter().map(|bone| bone.entity).collect::<Vec<_>>();
+    for bone in skeleton.iter_mut() {
+        bone.parent = parents
+            .iter_ancestors(bone.entity)
+            .take_while(|ancestor| *ancestor != vrm)
+            .find_map(|ancestor| entities.iter().position(|e| *e == ancestor));
+    }
+    for index in 0..skeleton.len() {
+        let parent_translation = skeleton[index]
+            .parent
+            .map(|parent| skeleton[parent].model_rest.translation)
+            .unwrap_or_default();
+        skeleton[index].translation = skeleton[index].model_rest.translation - parent_translation;
+    }
+    skeleton
+}
+
+fn obtain_expressions(expressions: &VrmExpressionRegistry) -> Vec<RecordedExpression> {
+    expressions
+        .keys()
+        .map(|expression| RecordedExpression {
+            expression: expression.clone(),
+            weights: Vec::new(),
+        })
+        .collect()
+}
+
+/// Composes the rest transforms from the VRM root to the entity.
+///
+/// Nodes other than humanoid bones use the current transform.
+fn model_space_rest(
+    vrm: Entity,
+    entity: Entity,
+    parents: &Query<&ChildOf>,
+    transforms: &Query<(&Transform, Option<&BoneRestTransform>)>,
+) -> Option<Transform> {
+    let mut model = Transform::IDENTITY;
+    let mut current = entity;
+    while current != vrm {
+        let (tf, rest) = transforms.get(current).ok()?;
+        model = rest.map(|rest| rest.0).unwrap_or(*tf).mul_transform(model);
+        current = parents.get(current).ok()?.parent();
+    }
+    Some(model)
+}
+
+/// The inverse of the rotation applied in `bind_bone_rotations`.
+#[inline]
+fn normalized_rotation(
+    model_rest: &Transform,
+    rest: &BoneRestTransform,
+    pose: &Transform,
+) -> Quat {
+    model_rest.rotation * rest.rotation.inverse() * pose.rotation * model_rest.rotation.inverse()
+}
+
+/// Converts the hips translation into the model space.
+#[inline]
+fn hips_translation(
+    model_rest: &Transform,
+    rest: &BoneRestTransform,
+    pose: &Transform,
+) -> Vec3 {
+    let parent_rotation = model_rest.rotation * rest.rotation.inverse();
+    model_rest.translation + parent_rotation * (pose.translation - rest.translation)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::glb::Glb;
+    use crate::prelude::*;
+    use crate::success;
+    use crate::tests::TestResult;
+    use crate::vrm::expressions::ProceduralExpressionWeights;
+    use crate::vrm::humanoid_bone::{HumanoidBoneRegistry, HumanoidBonesAttached};
+    use crate::vrm::{VrmBone, VrmExpression};
+    use crate::vrma::gltf::extensions::VrmaExtensions;
+    use crate::vrma::recorder::{
+        hips_translation, normalized_rotation, RecordedBone, RecordedExpression, VrmaRecorded,
+        VrmaRecorder, VrmaRecorderPlugin,
+    };
+    use bevy::prelude::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn normalized_rotation_is_inverse_of_retarget() {
+        let rest_gtf = Quat::from_rotation_y(FRAC_PI_2);
+        let rest = BoneRestTransform(Transform::from_rotation(Quat::from_rotation_x(0.3)));
+        let model_rest = Transform::from_rotation(rest_gtf);
+        let pose = Transform::from_rotation(Quat::from_rotation_z(0.7));
+
+        let normalized = normalized_rotation(&model_rest, &rest, &pose);
+        // The rotation applied to the VRM in `bind_bone_rotations`.
+        let retargeted = rest.rotation * rest_gtf.inverse() * normalized * rest_gtf;
+        assert!(retargeted.abs_diff_eq(pose.rotation, 1e-5));
+    }
+
+    #[test]
+    fn hips_translation_in_model_space() {
+        let model_rest = Transform {
+            translation: Vec3::new(0., 1., 0.),
+            rotation: Quat::from_rotation_y(std::f32::consts::PI),
+            ..default()
+        };
+        let rest = BoneRestTransform(Transform::from_xyz(0., 1., 0.));
+        let pose = Transform::from_xyz(0., 1.5, 0.2);
+        let translation = hips_translation(&model_rest, &rest, &pose);
+        assert!(translation.abs_diff_eq(Vec3::new(0., 1.5, -0.2), 1e-5));
+    }
+
+    #[test]
+    fn empty_recording_is_error() {
+        assert!(VrmaRecorder::default().to_vrma().is_err());
+    }
+
+    #[test]
+    fn skip_recorded_if_no_frames() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, VrmaRecorderPlugin));
+        app.init_resource::<RecordedCount>();
+        app.add_observer(
+            |_: Trigger<VrmaRecorded>, mut count: ResMut<RecordedCount>| {
+                count.0 += 1;
+            },
+        );
+        let vrm = app.world_mut().spawn(VrmaRecorder::default()).id();
+        app.world_mut().entity_mut(vrm).remove::<VrmaRecorder>();
+        app.update();
+        assert_eq!(app.world().resource::<RecordedCount>().0, 0);
+    }
+
+    #[derive(Resource, Default)]
+    struct RecordedCount(usize);
+
+    #[test]
+    fn record_procedural_expression_weights() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, VrmaRecorderPlugin));
+        let recorder = VrmaRecorder {
+            skeleton: vec![RecordedBone {
+                bone: VrmBone::from("hips"),
+                entity: Entity::PLACEHOLDER,
+                parent: None,
+                model_rest: Transform::IDENTITY,
+                translation: Vec3::ZERO,
+                rotations: Vec::new(),
+            }],
+            expressions: vec![RecordedExpression {
+                expression: VrmExpression::from("blink"),
+                weights: Vec::new(),
+            }],
+            ..default()
+        };
+        let mut procedural = ProceduralExpressionWeights::default();
+        procedural.insert(VrmExpression::from("blink"), 1.0);
+        let vrm = app
+            .world_mut()
+            .spawn((
+                recorder,
+                HumanoidBoneRegistry::default(),
+                HumanoidBonesAttached,
+                VrmExpressionWeights::default(),
+                procedural,
+            ))
+            .id();
+        app.update();
+        let recorder = app.world().get::<VrmaRecorder>(vrm).unwrap();
+        assert_eq!(recorder.expressions[0].weights, [1.0]);
+    }
+
+    #[test]
+    fn build_vrma() -> TestResult {
+        let recorder = VrmaRecorder {
+            times: vec![0., 0.5],
+            hips_translations: vec![Vec3::Y, Vec3::Y],
+            skeleton: vec![
+                RecordedBone {
+                    bone: VrmBone::from("hips"),
+                    entity: Entity::PLACEHOLDER,
+                    parent: None,
+                    model_rest: Transform::from_xyz(0., 1., 0.),
+                    translation: Vec3::Y,
+                    rotations: vec![Quat::IDENTITY; 2],
+                },
+                RecordedBone {
+                    bone: VrmBone::from("spine"),
+                    entity: Entity::PLACEHOLDER,
+                    parent: Some(0),
+                    model_rest: Transform::from_xyz(0., 1.1, 0.),
+                    translation: Vec3::new(0., 0.1, 0.),
+                    rotations: vec![Quat::IDENTITY; 2],
+                },
+            ],
+            expressions: vec![RecordedExpression {
+                expression: VrmExpression::from("happy"),
+                weights: vec![0., 1.],
+            }],
+            ..default()
+        };
+        let glb = Glb::from_bytes(&recorder.to_vrma()?)?;
+        let extensions = VrmaExtensions::new(glb.json["extensions"].as_object().unwrap())?;
+        let animation = extensions.vrmc_vrm_animation;
+        assert_eq!(animation.humanoid.human_bones["hips"].node, 0);
+        assert_eq!(animation.humanoid.human_bones["spine"].node, 1);
+        assert_eq!(animation.expressions.unwrap().preset["happy"].node, 2);
+        assert_eq!(glb.json["nodes"][0]["children"], serde_json::json!([1]));
+        assert_eq!(glb.json["scenes"][0]["nodes"], serde_json::json!([0, 2]));
+        // hips rotation, spine rotation, hips translation and happy.
+        assert_eq!(
+            glb.json["animations"][0]["channels"]
+                .as_array()
+                .unwrap()
+                .len(),
+            4
+        );
+        success!()
+    }
+}
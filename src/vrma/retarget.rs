@@ -1,9 +1,12 @@
 mod bone;
 mod expressions;
 
-use crate::vrma::retarget::bone::VrmaRetargetingBonePlugin;
-use crate::vrma::retarget::expressions::VrmaRetargetExpressionsPlugin;
-use bevy::app::{App, Plugin, Update};
+use crate::vrm::humanoid_bone::HumanoidBonesAttached;
+use crate::vrm::Vrm;
+use crate::vrma::retarget::bone::{RetargetedHumanBones, VrmaRetargetingBonePlugin};
+use crate::vrma::retarget::expressions::{RetargetedExpressions, VrmaRetargetExpressionsPlugin};
+use crate::vrma::RetargetTo;
+use bevy::app::{App, Plugin, PreUpdate, Update};
 use bevy::prelude::*;
 use bevy::window::RequestRedraw;
 
@@ -20,10 +23,26 @@ impl Plugin for VrmaRetargetPlugin {
         app: &mut App,
     ) {
         app.add_plugins((VrmaRetargetingBonePlugin, VrmaRetargetExpressionsPlugin))
+            .add_systems(PreUpdate, retarget_again)
             .add_systems(Update, request_redraw.run_if(playing_animation));
     }
 }
 
+/// Retargets the VRMA again when the bones of the VRM are re-attached, such as after hot reloading.
+fn retarget_again(
+    mut commands: Commands,
+    vrms: Query<Entity, (With<Vrm>, Added<HumanoidBonesAttached>)>,
+    vrma: Query<(Entity, &RetargetTo)>,
+) {
+    for vrm_entity in vrms.iter() {
+        for (vrma_entity, _) in vrma.iter().filter(|(_, to)| to.0 == vrm_entity) {
+            commands
+                .entity(vrma_entity)
+                .remove::<(RetargetedHumanBones, RetargetedExpressions)>();
+        }
+    }
+}
+
 fn playing_animation(
     changed_bones: Query<Entity, (Changed<Transform>, With<CurrentRetargeting>)>
 ) -> bool {
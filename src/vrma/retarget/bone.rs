@@ -40,7 +40,7 @@ fn retarget_bones_to_vrm(
         (Entity, &RetargetTo, &HumanoidBoneRegistry),
         (Without<RetargetedHumanBones>, With<HumanoidBonesAttached>),
     >,
-    hips: Query<&HipsBoneEntity>,
+    hips: Query<&HipsBoneEntity, With<HumanoidBonesAttached>>,
     names: Query<&Name>,
     searcher: ChildSearcher,
 ) {
@@ -176,7 +176,9 @@ mod tests {
     fn has_been_attached_humanoid_bones() -> TestResult {
         let mut app = test_app();
         app.world_mut().run_system_once(|mut commands: Commands| {
-            let vrm = commands.spawn(HipsBoneEntity(Entity::PLACEHOLDER)).id();
+            let vrm = commands
+                .spawn((HipsBoneEntity(Entity::PLACEHOLDER), HumanoidBonesAttached))
+                .id();
             commands.spawn((
                 HumanoidBoneRegistry::default(),
                 RetargetTo(vrm),
@@ -191,4 +193,24 @@ mod tests {
             .is_ok());
         Ok(())
     }
+
+    #[test]
+    fn wait_until_vrm_bones_attached() -> TestResult {
+        let mut app = test_app();
+        app.world_mut().run_system_once(|mut commands: Commands| {
+            let vrm = commands.spawn(HipsBoneEntity(Entity::PLACEHOLDER)).id();
+            commands.spawn((
+                HumanoidBoneRegistry::default(),
+                RetargetTo(vrm),
+                HumanoidBonesAttached,
+            ));
+        })?;
+        app.world_mut().run_system_once(retarget_bones_to_vrm)?;
+        assert!(app
+            .world_mut()
+            .query::<&RetargetedHumanBones>()
+            .single(app.world())
+            .is_err());
+        Ok(())
+    }
 }
@@ -1,8 +1,10 @@
 //!  This module handles the retargeting of expressions from a VRM model to a mascot model.
 
+use crate::macros::marker_component;
 use crate::system_param::child_searcher::ChildSearcher;
 use crate::system_set::VrmSystemSets;
-use crate::vrm::expressions::VrmExpressionRegistry;
+use crate::vrm::expressions::{VrmExpressionPreset, VrmExpressionRegistry, VrmExpressionWeights};
+use crate::vrm::humanoid_bone::HumanoidBonesAttached;
 use crate::vrm::VrmExpression;
 use crate::vrma::gltf::extensions::VrmaExtensions;
 use crate::vrma::retarget::CurrentRetargeting;
@@ -17,8 +19,8 @@ impl Plugin for VrmaRetargetExpressionsPlugin {
         &self,
         app: &mut App,
     ) {
-        app.register_type::<RetargetExpressionTo>()
-            .register_type::<BindExpressionNode>()
+        app.register_type::<RetargetedExpressions>()
+            .register_type::<RetargetExpressionTo>()
             .add_systems(
                 Update,
                 (
@@ -29,6 +31,13 @@ impl Plugin for VrmaRetargetExpressionsPlugin {
     }
 }
 
+marker_component!(
+    /// A marker component that indicates that the expressions have been retargeted.
+    ///
+    /// This is attached to the VRMA entity.
+    RetargetedExpressions
+);
+
 #[derive(Component, Deref, Reflect)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
@@ -43,81 +52,105 @@ impl VrmaExpressionNames {
             expressions
                 .preset
                 .keys()
-                .map(|expression| VrmExpression(expression.clone()))
+                .filter_map(|name| VrmExpressionPreset::from_name(name).map(VrmExpression::Preset))
+                .chain(
+                    expressions
+                        .custom
+                        .keys()
+                        .map(|name| VrmExpression::Custom(name.clone())),
+                )
                 .collect(),
         )
     }
 }
 
-#[derive(Reflect)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
-struct BindExpressionNode {
-    pub expression_entity: Entity,
-    pub index: usize,
-}
-
 #[derive(Component, Reflect)]
 #[reflect(Component)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
-struct RetargetExpressionTo(Vec<BindExpressionNode>);
+struct RetargetExpressionTo {
+    vrm: Entity,
+    expression: VrmExpression,
+}
 
 fn retarget_expressions_to_mascot(
     mut commands: Commands,
-    vrma: Query<(Entity, &RetargetTo, &VrmaExpressionNames), Added<Children>>,
-    mascots: Query<&VrmExpressionRegistry>,
+    vrma: Query<
+        (Entity, &RetargetTo, &VrmaExpressionNames),
+        (With<Children>, Without<RetargetedExpressions>),
+    >,
+    mascots: Query<Option<&VrmExpressionRegistry>, With<HumanoidBonesAttached>>,
     searcher: ChildSearcher,
 ) {
     for (vrma_entity, retarget, expressions) in vrma.iter() {
         let Ok(vrm_expressions) = mascots.get(retarget.0) else {
             continue;
         };
+        commands.entity(vrma_entity).insert(RetargetedExpressions);
+        let Some(vrm_expressions) = vrm_expressions else {
+            continue;
+        };
         for expression_name in expressions.iter() {
             let Some(vrma_expression_entity) =
-                searcher.find_from_name(vrma_entity, expression_name)
+                searcher.find_from_name(vrma_entity, expression_name.as_str())
             else {
                 debug!("[Expressions] expression entity not found: {expression_name}");
                 continue;
             };
-            let Some(nodes) = vrm_expressions.get(expression_name) else {
-                debug!("[Expressions] expression nodes not found: {expression_name}");
+            if !vrm_expressions.contains_key(expression_name) {
+                debug!("[Expressions] expression not found in VRM: {expression_name}");
                 continue;
-            };
-            let binds = nodes
-                .iter()
-                .filter_map(|node| {
-                    Some(BindExpressionNode {
-                        expression_entity: searcher.find_from_name(retarget.0, &node.name)?,
-                        index: node.morph_target_index,
-                    })
-                })
-                .collect::<Vec<_>>();
-            commands
-                .entity(vrma_expression_entity)
-                .insert((RetargetSource, RetargetExpressionTo(binds)));
+            }
+            commands.entity(vrma_expression_entity).insert((
+                RetargetSource,
+                RetargetExpressionTo {
+                    vrm: retarget.0,
+                    expression: expression_name.clone(),
+                },
+            ));
         }
     }
 }
 
 fn bind_expressions(
-    mut expressions: Query<&mut MorphWeights>,
+    mut vrms: Query<&mut VrmExpressionWeights>,
     vrma: Query<
         (&Transform, &RetargetExpressionTo),
         (Changed<Transform>, With<CurrentRetargeting>),
     >,
 ) {
-    for (tf, RetargetExpressionTo(binds)) in vrma.iter() {
-        // VRMA uses x coordinate to represent expression weight.
-        let weight = tf.translation.x;
-        for BindExpressionNode {
-            expression_entity,
-            index,
-        } in binds.iter()
-        {
-            if let Ok(mut morph_weights) = expressions.get_mut(*expression_entity) {
-                morph_weights.weights_mut()[*index] = weight;
-            }
+    for (tf, retarget) in vrma.iter() {
+        if let Ok(mut weights) = vrms.get_mut(retarget.vrm) {
+            // VRMA uses x coordinate to represent expression weight.
+            weights.set(retarget.expression.clone(), tf.translation.x);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::success;
+    use crate::tests::TestResult;
+    use crate::vrm::expressions::VrmExpressionPreset;
+    use crate::vrm::VrmExpression;
+    use crate::vrma::gltf::extensions::VrmaExtensions;
+    use crate::vrma::retarget::VrmaExpressionNames;
+    use serde_json::json;
+
+    #[test]
+    fn collect_preset_and_custom_names() -> TestResult {
+        let json = json!({
+            "VRMC_vrm_animation": {
+                "humanoid": { "humanBones": {} },
+                "expressions": {
+                    "preset": { "happy": { "node": 0 } },
+                    "custom": { "tongueOut": { "node": 1 } },
+                }
+            }
+        });
+        let names = VrmaExpressionNames::new(&VrmaExtensions::new(json.as_object().unwrap())?);
+        assert!(names.contains(&VrmExpression::Preset(VrmExpressionPreset::Happy)));
+        assert!(names.contains(&VrmExpression::Custom("tongueOut".to_string())));
+        success!()
+    }
+}